@@ -1,10 +1,19 @@
 use spitfire_draw::{sprite::Sprite, utils::TextureRef};
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ops::Range,
 };
 use vek::Rect;
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FramePlayback {
+    #[default]
+    Forward,
+    Reverse,
+    /// Plays forward and then backward - both legs make single cycle.
+    PingPong,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FrameAnimation {
     /// [(image index, frame duration)]
@@ -13,9 +22,19 @@ pub struct FrameAnimation {
     current: Option<(usize, f32)>,
     /// {frame index: [event ids]}
     events: BTreeMap<usize, HashSet<String>>,
+    /// Playback direction, before speed sign is applied.
+    forward: bool,
+    /// Ping-pong has already bounced in current cycle.
+    bounced: bool,
+    completed_cycles: usize,
     pub speed: f32,
     pub is_playing: bool,
     pub looping: bool,
+    pub playback: FramePlayback,
+    /// Sub-range of frames to play, or all frames if `None`.
+    pub range: Option<Range<usize>>,
+    /// Number of cycles to play when looping, or infinite if `None`.
+    pub loop_count: Option<usize>,
 }
 
 impl Default for FrameAnimation {
@@ -24,14 +43,25 @@ impl Default for FrameAnimation {
             frames: Vec::new(),
             current: None,
             events: Default::default(),
+            forward: true,
+            bounced: false,
+            completed_cycles: 0,
             speed: 30.0,
             is_playing: false,
             looping: false,
+            playback: FramePlayback::Forward,
+            range: None,
+            loop_count: None,
         }
     }
 }
 
 impl FrameAnimation {
+    /// Reported when playback stops by itself, after last cycle.
+    pub const COMPLETED_EVENT: &'static str = "@completed";
+    /// Reported when looping playback completes cycle and starts another one.
+    pub const LOOPED_EVENT: &'static str = "@looped";
+
     pub fn new(images: Range<usize>) -> Self {
         let mut result = Self::default();
         for index in images {
@@ -73,12 +103,45 @@ impl FrameAnimation {
         self
     }
 
+    pub fn loops(mut self, count: usize) -> Self {
+        self.looping = true;
+        self.loop_count = Some(count);
+        self
+    }
+
+    pub fn playback(mut self, playback: FramePlayback) -> Self {
+        self.playback = playback;
+        self
+    }
+
+    pub fn reverse(self) -> Self {
+        self.playback(FramePlayback::Reverse)
+    }
+
+    pub fn ping_pong(self) -> Self {
+        self.playback(FramePlayback::PingPong)
+    }
+
+    pub fn range(mut self, frames: Range<usize>) -> Self {
+        self.range = Some(frames);
+        self
+    }
+
     pub fn play(&mut self) {
-        if self.frames.is_empty() {
+        let range = self.frames_range();
+        if range.is_empty() {
             return;
         }
+        self.forward = self.playback != FramePlayback::Reverse;
+        self.bounced = false;
+        self.completed_cycles = 0;
         self.is_playing = true;
-        self.current = Some((0, 0.0));
+        let index = if self.is_moving_forward() {
+            range.start
+        } else {
+            range.end - 1
+        };
+        self.current = Some((index, 0.0));
     }
 
     pub fn stop(&mut self) {
@@ -93,23 +156,53 @@ impl FrameAnimation {
         let Some((mut current_index, mut accumulator)) = self.current else {
             return Default::default();
         };
+        let range = self.frames_range();
+        if range.is_empty() {
+            return Default::default();
+        }
         let mut result = HashSet::default();
-        accumulator += (delta_time * self.speed).max(0.0);
+        accumulator += (delta_time * self.speed).abs();
         while accumulator >= self.frames[current_index].1 {
             accumulator -= self.frames[current_index].1;
             if let Some(events) = self.events.get(&current_index) {
                 result.extend(events.iter().map(|id| id.as_str()));
             }
-            current_index += 1;
-            if current_index >= self.frames.len() {
-                if self.looping {
-                    self.current = Some((0, accumulator));
-                    current_index = 0;
-                } else {
-                    self.is_playing = false;
-                    self.current = None;
-                    return result;
-                }
+            let forward = self.is_moving_forward();
+            if forward && current_index + 1 < range.end {
+                current_index += 1;
+                continue;
+            }
+            if !forward && current_index > range.start {
+                current_index -= 1;
+                continue;
+            }
+            if self.playback == FramePlayback::PingPong && !self.bounced {
+                self.bounced = true;
+                self.forward = !self.forward;
+                current_index = Self::bounce_index(current_index, forward, &range);
+                continue;
+            }
+            self.completed_cycles += 1;
+            if !self.looping
+                || self
+                    .loop_count
+                    .map(|count| self.completed_cycles >= count)
+                    .unwrap_or_default()
+            {
+                self.is_playing = false;
+                self.current = None;
+                result.insert(Self::COMPLETED_EVENT);
+                return result;
+            }
+            result.insert(Self::LOOPED_EVENT);
+            if self.playback == FramePlayback::PingPong {
+                self.bounced = false;
+                self.forward = !self.forward;
+                current_index = Self::bounce_index(current_index, forward, &range);
+            } else if forward {
+                current_index = range.start;
+            } else {
+                current_index = range.end - 1;
             }
         }
         self.current = Some((current_index, accumulator));
@@ -121,6 +214,133 @@ impl FrameAnimation {
             .and_then(|(index, _)| self.frames.get(index))
             .map(|(image_index, _)| *image_index)
     }
+
+    pub fn current_frame(&self) -> Option<usize> {
+        self.current.map(|(index, _)| index)
+    }
+
+    pub fn completed_cycles(&self) -> usize {
+        self.completed_cycles
+    }
+
+    /// Frames that are played, with `range` clamped to existing frames.
+    pub fn frames_range(&self) -> Range<usize> {
+        let count = self.frames.len();
+        match &self.range {
+            Some(range) => {
+                let end = range.end.min(count);
+                range.start.min(end)..end
+            }
+            None => 0..count,
+        }
+    }
+
+    /// Duration of single playback cycle in seconds, with current speed applied.
+    /// Ping-pong plays range edge frames once per bounce, looping cycles
+    /// after the first one also skip their first frame.
+    pub fn duration(&self) -> f32 {
+        let frames = &self.frames[self.frames_range()];
+        let total = frames.iter().map(|(_, duration)| *duration).sum::<f32>();
+        let total = match frames {
+            [first, .., last] if self.playback == FramePlayback::PingPong => {
+                if self.looping {
+                    total * 2.0 - first.1 - last.1
+                } else {
+                    total * 2.0 - last.1
+                }
+            }
+            _ if self.playback == FramePlayback::PingPong => total * 2.0,
+            _ => total,
+        };
+        if self.speed.abs() > f32::EPSILON {
+            total / self.speed.abs()
+        } else {
+            f32::INFINITY
+        }
+    }
+
+    /// Normalized position of current frame within played range, measured
+    /// from its first frame regardless of playback direction.
+    pub fn progress(&self) -> f32 {
+        let Some((index, accumulator)) = self.current else {
+            return 0.0;
+        };
+        let range = self.frames_range();
+        let total = self.frames[range.clone()]
+            .iter()
+            .map(|(_, duration)| *duration)
+            .sum::<f32>();
+        if total <= f32::EPSILON {
+            return 0.0;
+        }
+        let before = self.frames[range.start..index]
+            .iter()
+            .map(|(_, duration)| *duration)
+            .sum::<f32>();
+        let duration = self.frames[index].1;
+        let inside = if self.is_moving_forward() {
+            accumulator
+        } else {
+            duration - accumulator
+        };
+        ((before + inside) / total).clamp(0.0, 1.0)
+    }
+
+    /// Jumps to the beginning of given frame, clamped to played range.
+    pub fn seek_frame(&mut self, frame: usize) {
+        let range = self.frames_range();
+        if range.is_empty() {
+            return;
+        }
+        self.current = Some((frame.clamp(range.start, range.end - 1), 0.0));
+    }
+
+    /// Jumps to time in seconds since start of playback, measured from
+    /// first frame of played range, or from last one when moving backward.
+    /// Ping-pong continues onto its return leg.
+    pub fn seek_time(&mut self, time: f32) {
+        let range = self.frames_range();
+        if range.is_empty() {
+            return;
+        }
+        self.forward = self.playback != FramePlayback::Reverse;
+        self.bounced = false;
+        let mut forward = self.is_moving_forward();
+        let mut position = (time * self.speed.abs()).max(0.0);
+        let mut index = if forward { range.start } else { range.end - 1 };
+        while position >= self.frames[index].1 {
+            let next = if forward {
+                index + 1
+            } else {
+                index.wrapping_sub(1)
+            };
+            if range.contains(&next) {
+                position -= self.frames[index].1;
+                index = next;
+            } else if self.playback == FramePlayback::PingPong && !self.bounced {
+                position -= self.frames[index].1;
+                self.bounced = true;
+                self.forward = !self.forward;
+                index = Self::bounce_index(index, forward, &range);
+                forward = !forward;
+            } else {
+                break;
+            }
+        }
+        self.current = Some((index, position.min(self.frames[index].1)));
+    }
+
+    fn is_moving_forward(&self) -> bool {
+        self.forward != (self.speed < 0.0)
+    }
+
+    fn bounce_index(index: usize, forward: bool, range: &Range<usize>) -> usize {
+        if forward {
+            index.saturating_sub(1).max(range.start)
+        } else {
+            (index + 1).min(range.end - 1)
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -163,6 +383,21 @@ impl SpriteFrameAnimation {
         self
     }
 
+    pub fn loops(mut self, count: usize) -> Self {
+        self.animation = self.animation.loops(count);
+        self
+    }
+
+    pub fn playback(mut self, playback: FramePlayback) -> Self {
+        self.animation = self.animation.playback(playback);
+        self
+    }
+
+    pub fn range(mut self, frames: Range<usize>) -> Self {
+        self.animation = self.animation.range(frames);
+        self
+    }
+
     pub fn current_image(&self) -> Option<&SpriteAnimationImage> {
         self.images.get(&self.animation.current_image()?)
     }
@@ -177,3 +412,445 @@ impl SpriteFrameAnimation {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameAnimatorParameter {
+    Bool(bool),
+    Float(f32),
+    /// Stays set until consumed by transition that fires because of it.
+    Trigger(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameAnimatorCondition {
+    Bool(String, bool),
+    Greater(String, f32),
+    Less(String, f32),
+    Trigger(String),
+}
+
+impl FrameAnimatorCondition {
+    pub fn bool(name: impl ToString, value: bool) -> Self {
+        Self::Bool(name.to_string(), value)
+    }
+
+    pub fn greater(name: impl ToString, value: f32) -> Self {
+        Self::Greater(name.to_string(), value)
+    }
+
+    pub fn less(name: impl ToString, value: f32) -> Self {
+        Self::Less(name.to_string(), value)
+    }
+
+    pub fn trigger(name: impl ToString) -> Self {
+        Self::Trigger(name.to_string())
+    }
+
+    fn test(&self, parameters: &HashMap<String, FrameAnimatorParameter>) -> bool {
+        match self {
+            Self::Bool(name, value) => matches!(
+                parameters.get(name),
+                Some(FrameAnimatorParameter::Bool(v)) if v == value
+            ),
+            Self::Greater(name, value) => matches!(
+                parameters.get(name),
+                Some(FrameAnimatorParameter::Float(v)) if v > value
+            ),
+            Self::Less(name, value) => matches!(
+                parameters.get(name),
+                Some(FrameAnimatorParameter::Float(v)) if v < value
+            ),
+            Self::Trigger(name) => matches!(
+                parameters.get(name),
+                Some(FrameAnimatorParameter::Trigger(true))
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameAnimatorTransition {
+    /// Source state name, or any state if `None`.
+    pub from: Option<String>,
+    pub to: String,
+    pub conditions: Vec<FrameAnimatorCondition>,
+    /// Normalized time of source state (1.0 = one full cycle) that has to
+    /// pass before transition can fire.
+    pub exit_time: Option<f32>,
+    pub event: Option<String>,
+}
+
+impl FrameAnimatorTransition {
+    pub fn new(from: impl ToString, to: impl ToString) -> Self {
+        Self {
+            from: Some(from.to_string()),
+            to: to.to_string(),
+            conditions: Default::default(),
+            exit_time: None,
+            event: None,
+        }
+    }
+
+    pub fn any(to: impl ToString) -> Self {
+        Self {
+            from: None,
+            to: to.to_string(),
+            conditions: Default::default(),
+            exit_time: None,
+            event: None,
+        }
+    }
+
+    pub fn condition(mut self, condition: FrameAnimatorCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn conditions(
+        mut self,
+        conditions: impl IntoIterator<Item = FrameAnimatorCondition>,
+    ) -> Self {
+        self.conditions.extend(conditions);
+        self
+    }
+
+    pub fn exit_time(mut self, normalized_time: f32) -> Self {
+        self.exit_time = Some(normalized_time);
+        self
+    }
+
+    pub fn event(mut self, id: impl ToString) -> Self {
+        self.event = Some(id.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FrameAnimator {
+    states: HashMap<String, NamedFrameAnimation>,
+    transitions: Vec<FrameAnimatorTransition>,
+    parameters: HashMap<String, FrameAnimatorParameter>,
+    default_state: Option<String>,
+    current: Option<String>,
+    /// Seconds spent in current state.
+    state_time: f32,
+}
+
+impl FrameAnimator {
+    pub fn add_state(&mut self, name: impl ToString, animation: NamedFrameAnimation) {
+        let name = name.to_string();
+        if self.default_state.is_none() {
+            self.default_state = Some(name.clone());
+        }
+        self.states.insert(name, animation);
+    }
+
+    pub fn state(mut self, name: impl ToString, animation: NamedFrameAnimation) -> Self {
+        self.add_state(name, animation);
+        self
+    }
+
+    pub fn add_transition(&mut self, transition: FrameAnimatorTransition) {
+        self.transitions.push(transition);
+    }
+
+    pub fn transition(mut self, transition: FrameAnimatorTransition) -> Self {
+        self.add_transition(transition);
+        self
+    }
+
+    pub fn default_state(mut self, name: impl ToString) -> Self {
+        self.default_state = Some(name.to_string());
+        self
+    }
+
+    pub fn parameter(mut self, name: impl ToString, value: FrameAnimatorParameter) -> Self {
+        self.parameters.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn set_bool(&mut self, name: impl ToString, value: bool) {
+        self.parameters
+            .insert(name.to_string(), FrameAnimatorParameter::Bool(value));
+    }
+
+    pub fn set_float(&mut self, name: impl ToString, value: f32) {
+        self.parameters
+            .insert(name.to_string(), FrameAnimatorParameter::Float(value));
+    }
+
+    pub fn set_trigger(&mut self, name: impl ToString) {
+        self.parameters
+            .insert(name.to_string(), FrameAnimatorParameter::Trigger(true));
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) {
+        if let Some(FrameAnimatorParameter::Trigger(value)) = self.parameters.get_mut(name) {
+            *value = false;
+        }
+    }
+
+    pub fn get_parameter(&self, name: &str) -> Option<FrameAnimatorParameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn current_state(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn current_animation(&self) -> Option<&NamedFrameAnimation> {
+        self.states.get(self.current.as_deref()?)
+    }
+
+    pub fn current_image(&self) -> Option<usize> {
+        self.current_animation()?.animation.current_image()
+    }
+
+    pub fn state_time(&self) -> f32 {
+        self.state_time
+    }
+
+    pub fn normalized_state_time(&self) -> f32 {
+        self.current_animation()
+            .map(|state| self.state_time / state.animation.duration())
+            .unwrap_or_default()
+    }
+
+    /// Forces change to given state, without checking transitions.
+    pub fn play(&mut self, name: &str) -> bool {
+        if !self.states.contains_key(name) {
+            return false;
+        }
+        if let Some(state) = self
+            .current
+            .as_deref()
+            .and_then(|current| self.states.get_mut(current))
+        {
+            state.animation.stop();
+        }
+        if let Some(state) = self.states.get_mut(name) {
+            state.animation.play();
+        }
+        self.current = Some(name.to_owned());
+        self.state_time = 0.0;
+        true
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(state) = self
+            .current
+            .take()
+            .and_then(|current| self.states.get_mut(&current))
+        {
+            state.animation.stop();
+        }
+        self.state_time = 0.0;
+    }
+
+    /// Evaluates transitions and advances current state animation.
+    /// Returns passed animation events together with fired transition event.
+    pub fn update(&mut self, delta_time: f32) -> HashSet<&str> {
+        if self.current.is_none()
+            && let Some(name) = self.default_state.clone()
+        {
+            self.play(&name);
+        }
+        let fired = self.find_transition();
+        if let Some(index) = fired {
+            for condition in &self.transitions[index].conditions {
+                if let FrameAnimatorCondition::Trigger(name) = condition
+                    && let Some(FrameAnimatorParameter::Trigger(value)) =
+                        self.parameters.get_mut(name)
+                {
+                    *value = false;
+                }
+            }
+            let to = self.transitions[index].to.clone();
+            self.play(&to);
+        }
+        self.state_time += delta_time;
+        let mut result = self
+            .current
+            .as_deref()
+            .and_then(|current| self.states.get_mut(current))
+            .map(|state| state.animation.update(delta_time))
+            .unwrap_or_default();
+        if let Some(event) = fired.and_then(|index| self.transitions[index].event.as_deref()) {
+            result.insert(event);
+        }
+        result
+    }
+
+    fn find_transition(&self) -> Option<usize> {
+        let current = self.current.as_deref()?;
+        let normalized_time = self.normalized_state_time();
+        self.transitions.iter().position(|transition| {
+            let source_matches = match transition.from.as_deref() {
+                Some(from) => from == current,
+                None => transition.to != current,
+            };
+            source_matches
+                && self.states.contains_key(&transition.to)
+                && transition
+                    .exit_time
+                    .map(|exit_time| normalized_time >= exit_time)
+                    .unwrap_or(true)
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.test(&self.parameters))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(id: &str, animation: FrameAnimation) -> NamedFrameAnimation {
+        NamedFrameAnimation {
+            animation,
+            id: id.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_frame_animation_playback() {
+        let mut animation = FrameAnimation::new(0..4).speed(1.0).event(0, "first");
+        animation.play();
+        assert_eq!(animation.current_image(), Some(0));
+        assert!(animation.update(1.0).contains("first"));
+        assert_eq!(animation.current_image(), Some(1));
+        let events = animation.update(3.0);
+        assert!(events.contains(FrameAnimation::COMPLETED_EVENT));
+        assert!(!animation.is_playing);
+
+        let mut animation = FrameAnimation::new(0..4)
+            .speed(1.0)
+            .reverse()
+            .event(0, "first")
+            .playing();
+        assert_eq!(animation.current_image(), Some(3));
+        assert!(animation.update(2.5).is_empty());
+        assert_eq!(animation.current_image(), Some(1));
+        assert!((animation.progress() - 0.375).abs() < 1.0e-6);
+        let events = animation.update(1.5);
+        assert!(events.contains("first"));
+        assert!(events.contains(FrameAnimation::COMPLETED_EVENT));
+
+        let mut animation = FrameAnimation::new(0..4).speed(-1.0).looping().playing();
+        assert_eq!(animation.current_image(), Some(3));
+        assert!(animation.update(4.0).contains(FrameAnimation::LOOPED_EVENT));
+        assert_eq!(animation.current_image(), Some(3));
+
+        let mut animation = FrameAnimation::new(0..3).speed(1.0).ping_pong();
+        assert_eq!(animation.duration(), 5.0);
+        animation.play();
+        animation.seek_time(3.5);
+        assert_eq!(animation.current_image(), Some(1));
+        animation.update(1.0);
+        assert_eq!(animation.current_image(), Some(0));
+        assert!(
+            animation
+                .update(0.5)
+                .contains(FrameAnimation::COMPLETED_EVENT)
+        );
+
+        let mut animation = FrameAnimation::new(0..3).speed(1.0).ping_pong().loops(2);
+        animation.play();
+        let mut images = vec![];
+        for _ in 0..8 {
+            images.push(animation.current_image());
+            animation.update(1.0);
+        }
+        assert_eq!(
+            images,
+            vec![
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(1),
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(1)
+            ]
+        );
+        let events = animation.update(1.0);
+        assert!(events.contains(FrameAnimation::COMPLETED_EVENT));
+        assert_eq!(animation.completed_cycles(), 2);
+        assert_eq!(animation.duration(), 4.0);
+
+        let mut animation = FrameAnimation::new(0..10).speed(2.0).range(2..5).playing();
+        assert_eq!(animation.current_image(), Some(2));
+        animation.seek_time(1.25);
+        assert_eq!(animation.current_image(), Some(4));
+        animation.seek_frame(9);
+        assert_eq!(animation.current_image(), Some(4));
+        assert!(
+            animation
+                .update(0.5)
+                .contains(FrameAnimation::COMPLETED_EVENT)
+        );
+
+        let mut animation = FrameAnimation::new(0..4).speed(1.0).reverse().playing();
+        animation.update(2.5);
+        animation.seek_time(0.0);
+        assert_eq!(animation.current_image(), Some(3));
+        assert!(animation.update(0.5).is_empty());
+        assert_eq!(animation.current_image(), Some(3));
+        animation.seek_time(1.5);
+        assert_eq!(animation.current_image(), Some(2));
+        assert!((animation.progress() - 0.625).abs() < 1.0e-6);
+        animation.update(0.5);
+        assert_eq!(animation.current_image(), Some(1));
+    }
+
+    #[test]
+    fn test_frame_animator() {
+        let mut animator = FrameAnimator::default()
+            .state("idle", named("idle", FrameAnimation::new(0..2).looping()))
+            .state("run", named("run", FrameAnimation::new(0..4).looping()))
+            .state(
+                "attack",
+                named("attack", FrameAnimation::new(0..2).event(1, "hit")),
+            )
+            .transition(
+                FrameAnimatorTransition::new("idle", "run")
+                    .condition(FrameAnimatorCondition::greater("speed", 0.5)),
+            )
+            .transition(
+                FrameAnimatorTransition::new("run", "idle")
+                    .condition(FrameAnimatorCondition::less("speed", 0.5)),
+            )
+            .transition(
+                FrameAnimatorTransition::any("attack")
+                    .condition(FrameAnimatorCondition::trigger("attack"))
+                    .event("attack_started"),
+            )
+            .transition(FrameAnimatorTransition::new("attack", "idle").exit_time(1.0))
+            .parameter("speed", FrameAnimatorParameter::Float(0.0));
+
+        assert!(animator.update(0.0).is_empty());
+        assert_eq!(animator.current_state(), Some("idle"));
+
+        animator.set_float("speed", 1.0);
+        animator.update(0.01);
+        assert_eq!(animator.current_state(), Some("run"));
+
+        animator.set_trigger("attack");
+        let events = animator.update(0.01);
+        assert!(events.contains("attack_started"));
+        assert_eq!(animator.current_state(), Some("attack"));
+        assert_eq!(
+            animator.get_parameter("attack"),
+            Some(FrameAnimatorParameter::Trigger(false))
+        );
+
+        let events = animator.update(2.0 / 30.0);
+        assert!(events.contains("hit"));
+        assert_eq!(animator.current_state(), Some("attack"));
+        animator.update(0.0);
+        assert_eq!(animator.current_state(), Some("idle"));
+    }
+}
@@ -1,4 +1,11 @@
-use crate::{context::GameContext, game::GameObject};
+use crate::{
+    animation::{
+        ik::IkRig,
+        lod::{LodBudgetSubsystem, LodMetrics, LodPolicy},
+    },
+    context::GameContext,
+    game::GameObject,
+};
 use gltf::animation::Interpolation;
 use keket::database::{AssetDatabase, handle::AssetHandle};
 use nodio::{
@@ -15,7 +22,7 @@ use spitfire_draw::{
     utils::{Drawable, ShaderRef, Vertex, transform_to_matrix},
 };
 use spitfire_glow::{
-    graphics::{GraphicsBatch, GraphicsTarget},
+    graphics::{Camera, CameraScaling, GraphicsBatch, GraphicsTarget},
     renderer::{GlowBlending, GlowUniformValue},
 };
 use std::{
@@ -25,14 +32,19 @@ use std::{
     error::Error,
     f32,
     hash::Hash,
-    ops::Range,
-    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, atomic::AtomicU32},
+    ops::{Add, Mul, Range},
+    sync::{
+        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        atomic::{AtomicU32, AtomicU64, Ordering as AtomicOrdering},
+    },
 };
-use vek::{Mat4, Quaternion, Rgba, Transform, Vec2, Vec3};
+use vek::{Mat4, Quaternion, Rect, Rgba, Transform, Vec2, Vec3};
 
 #[derive(Debug, Default, Clone)]
 pub struct GltfMesh {
     pub primitives: Vec<GltfPrimitive>,
+    /// Default weights of primitives morph targets.
+    pub morph_weights: Vec<f32>,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -50,6 +62,14 @@ pub struct GltfPrimitive {
     pub blending: GlowBlending,
     pub triangles: Vec<Triangle>,
     pub vertices: Vec<GltfVertex>,
+    pub morph_targets: Vec<GltfMorphTarget>,
+}
+
+/// Only position deltas are applied, vertices carry no normals or tangents.
+#[derive(Debug, Default, Clone)]
+pub struct GltfMorphTarget {
+    /// Position deltas, one per primitive vertex.
+    pub positions: Vec<Vec3<f32>>,
 }
 
 #[derive(Debug, Clone)]
@@ -70,6 +90,8 @@ pub struct GltfAnimationChannel {
     pub times: Vec<f32>,
     pub duration: f32,
     pub values: GltfAnimationValues,
+    /// In and out tangents of keyframes, used by cubic spline interpolation.
+    pub tangents: Option<(GltfAnimationValues, GltfAnimationValues)>,
     pub interpolation: Interpolation,
 }
 
@@ -78,6 +100,7 @@ pub enum GltfAnimationValues {
     Translation(Vec<Vec3<f32>>),
     Rotation(Vec<Quaternion<f32>>),
     Scale(Vec<Vec3<f32>>),
+    MorphTargetWeights(Vec<Vec<f32>>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -112,6 +135,10 @@ impl GltfSceneMesh {
     }
 }
 
+pub struct GltfSceneCamera(pub GltfCamera);
+
+pub struct GltfSceneLight(pub GltfLight);
+
 pub struct GltfSceneSkin(AssetHandle);
 
 impl GltfSceneSkin {
@@ -120,6 +147,12 @@ impl GltfSceneSkin {
     }
 }
 
+/// Current morph target weights of node mesh.
+#[derive(Debug, Default, Clone)]
+pub struct GltfSceneMorphWeights {
+    pub weights: Vec<f32>,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct GltfSceneTransform {
     pub transform: Transform<f32, f32, f32>,
@@ -160,9 +193,63 @@ pub struct GltfNode {
     pub transform: Transform<f32, f32, f32>,
     pub mesh_handle: Option<AssetHandle>,
     pub skin_handle: Option<AssetHandle>,
+    pub morph_weights: Option<Vec<f32>>,
+    pub camera: Option<GltfCamera>,
+    pub light: Option<GltfLight>,
     pub children: Vec<Self>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfCameraProjection {
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+    Perspective {
+        aspect_ratio: Option<f32>,
+        yfov: f32,
+        znear: f32,
+        zfar: Option<f32>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfCamera {
+    pub name: String,
+    pub projection: GltfCameraProjection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfLightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfLight {
+    pub name: String,
+    pub kind: GltfLightKind,
+    pub color: Rgba<f32>,
+    pub intensity: f32,
+    pub range: Option<f32>,
+}
+
+impl GltfLight {
+    /// Lights shine along node local -Z axis.
+    pub fn world_direction(&self, world_matrix: Mat4<f32>) -> Vec3<f32> {
+        world_matrix
+            .mul_direction(-Vec3::unit_z())
+            .try_normalized()
+            .unwrap_or(-Vec3::unit_z())
+    }
+}
+
 #[derive(Default)]
 pub struct GltfSceneInstantiateOptions {
     #[allow(clippy::type_complexity)]
@@ -241,6 +328,15 @@ impl GltfSceneTemplate {
             animations: Default::default(),
             parameters: Default::default(),
             animation_node: None,
+            root_motion: None,
+            root_motion_delta: Default::default(),
+            lod_policy: None,
+            ik_rig: None,
+            lod_timer: 0.0,
+            lod_refresh_delay: 0.0,
+            lod_suspended: false,
+            pose_revision: Default::default(),
+            lod_renderables: None,
         };
         result.recompute_matrices();
         result
@@ -284,6 +380,31 @@ impl GltfSceneTemplate {
         if let Some(mesh_handle) = node.mesh_handle {
             let mesh = graph.insert(GltfSceneMesh(mesh_handle));
             graph.relate::<GltfSceneAttribute>(index, mesh);
+
+            let mesh_asset = mesh_handle.access_checked::<&GltfMesh>(database);
+            let weights = node
+                .morph_weights
+                .clone()
+                .or_else(|| {
+                    mesh_asset
+                        .as_ref()
+                        .map(|asset| asset.morph_weights.clone())
+                        .filter(|weights| !weights.is_empty())
+                })
+                .unwrap_or_else(|| {
+                    // glTF weights are optional and default to zero.
+                    let targets = mesh_asset
+                        .iter()
+                        .flat_map(|asset| &asset.primitives)
+                        .map(|primitive| primitive.morph_targets.len())
+                        .max()
+                        .unwrap_or_default();
+                    vec![0.0; targets]
+                });
+            if !weights.is_empty() {
+                let weights = graph.insert(GltfSceneMorphWeights { weights });
+                graph.relate::<GltfSceneAttribute>(index, weights);
+            }
         }
 
         if let Some(skin_handle) = node.skin_handle {
@@ -297,6 +418,16 @@ impl GltfSceneTemplate {
             }
         }
 
+        if let Some(camera) = &node.camera {
+            let camera = graph.insert(GltfSceneCamera(camera.clone()));
+            graph.relate::<GltfSceneAttribute>(index, camera);
+        }
+
+        if let Some(light) = &node.light {
+            let light = graph.insert(GltfSceneLight(light.clone()));
+            graph.relate::<GltfSceneAttribute>(index, light);
+        }
+
         if let Some(extract_extras) = &options.extract_extras {
             extract_extras(&node.extras, graph, index);
         }
@@ -363,6 +494,9 @@ pub struct GltfSceneAnimation {
     pub nodes_weight_override: HashMap<AnyIndex, f32>,
     pub events_timeline: Vec<GltfAnimationEvent>,
     events_passed: HashSet<usize>,
+    root_motion_sample: GltfRootMotionSample,
+    /// Maps animation bones from other container onto instance bones.
+    pub retarget: Option<Arc<GltfAnimationRetarget>>,
 }
 
 impl GltfSceneAnimation {
@@ -383,6 +517,8 @@ impl GltfSceneAnimation {
             nodes_weight_override: Default::default(),
             events_timeline: Default::default(),
             events_passed: Default::default(),
+            root_motion_sample: Default::default(),
+            retarget: None,
         })
     }
 
@@ -418,12 +554,20 @@ impl GltfSceneAnimation {
         self.playing = true;
         self.time = 0.0;
         self.weight = 1.0;
+        self.reset_root_motion();
     }
 
     pub fn stop(&mut self) {
         self.playing = false;
         self.time = 0.0;
         self.weight = 0.0;
+        self.reset_root_motion();
+    }
+
+    /// Forgets last root motion sample, so next applied pose produces no motion.
+    /// Useful after manually jumping with animation time.
+    pub fn reset_root_motion(&mut self) {
+        self.root_motion_sample = Default::default();
     }
 
     pub fn has_completed(&self) -> bool {
@@ -466,6 +610,11 @@ impl GltfSceneAnimation {
         self
     }
 
+    pub fn retarget(mut self, retarget: Arc<GltfAnimationRetarget>) -> Self {
+        self.retarget = Some(retarget);
+        self
+    }
+
     pub fn event(mut self, event: GltfAnimationEvent) -> Self {
         self.events_timeline.push(event);
         self
@@ -532,6 +681,130 @@ impl GltfSceneAnimation {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct GltfAnimationRetargetBone {
+    pub target: AnyIndex,
+    pub source_bind_transform: Transform<f32, f32, f32>,
+    /// Compensates different bone lengths of source and target skeletons.
+    pub translation_scale: f32,
+}
+
+/// Maps bones of animation source container onto scene instance bones.
+#[derive(Debug, Default, Clone)]
+pub struct GltfAnimationRetarget {
+    pub bones: HashMap<GltfNodeId, GltfAnimationRetargetBone>,
+}
+
+impl GltfAnimationRetarget {
+    /// Maps bones with the same names.
+    pub fn by_name(source: &GltfSceneTemplate, target: &GltfSceneInstance) -> Self {
+        Self::with_mapping(source, target, std::iter::empty::<(&str, &str)>())
+    }
+
+    /// Maps bones using (source name, target name) table, unmapped bones are matched by name.
+    pub fn with_mapping(
+        source: &GltfSceneTemplate,
+        target: &GltfSceneInstance,
+        mapping: impl IntoIterator<Item = (impl ToString, impl ToString)>,
+    ) -> Self {
+        let mapping = mapping
+            .into_iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect::<HashMap<_, _>>();
+        let mut result = Self::default();
+        let mut stack = source.root_nodes.iter().collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            stack.extend(node.children.iter());
+            let name = mapping.get(&node.name).unwrap_or(&node.name);
+            let Some(index) = target.find_bone_by_name(name) else {
+                continue;
+            };
+            let Some(transform) = target
+                .graph()
+                .query::<Related<GltfSceneAttribute, &GltfSceneTransform>>(index)
+                .next()
+            else {
+                continue;
+            };
+            let source_length = node.transform.position.magnitude();
+            let translation_scale = if source_length > f32::EPSILON {
+                transform.bind_transform.position.magnitude() / source_length
+            } else {
+                1.0
+            };
+            result.bones.insert(
+                node.id,
+                GltfAnimationRetargetBone {
+                    target: index,
+                    source_bind_transform: node.transform,
+                    translation_scale,
+                },
+            );
+        }
+        result
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct GltfRootMotionSample {
+    time: Option<f32>,
+    translation: Option<Vec3<f32>>,
+    rotation: Option<Quaternion<f32>>,
+}
+
+/// Extracts root node movement from applied animations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GltfRootMotion {
+    pub node: AnyIndex,
+    /// Per axis factor of extracted translation, non-extracted part stays in pose.
+    pub translation_mask: Vec3<f32>,
+    pub rotation: bool,
+}
+
+impl GltfRootMotion {
+    pub fn new(node: AnyIndex) -> Self {
+        Self {
+            node,
+            translation_mask: Vec3::one(),
+            rotation: false,
+        }
+    }
+
+    pub fn translation_mask(mut self, mask: Vec3<f32>) -> Self {
+        self.translation_mask = mask;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: bool) -> Self {
+        self.rotation = rotation;
+        self
+    }
+}
+
+/// Root motion produced by last animations apply, in root node parent space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GltfRootMotionDelta {
+    pub translation: Vec3<f32>,
+    pub rotation: Quaternion<f32>,
+}
+
+impl Default for GltfRootMotionDelta {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::zero(),
+            rotation: Quaternion::identity(),
+        }
+    }
+}
+
+impl GltfRootMotionDelta {
+    /// Moves object transform (for example of `Transformed` scene) by this delta.
+    pub fn apply_to(&self, transform: &mut Transform<f32, f32, f32>) {
+        transform.position += transform.orientation * (self.translation * transform.scale);
+        transform.orientation = (transform.orientation * self.rotation).normalized();
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GltfSceneAnimationHandle(Arc<RwLock<GltfSceneAnimation>>);
 
@@ -603,6 +876,22 @@ impl GltfAnimationNode for () {
     fn update(&mut self, _: f32) {}
 }
 
+impl GltfAnimationNode for Box<dyn GltfAnimationNode> {
+    fn produce_weights(
+        &self,
+        instance: &GltfSceneInstance,
+        master_weight: f32,
+        output: &mut GltfAnimationBlender,
+    ) {
+        self.as_ref()
+            .produce_weights(instance, master_weight, output);
+    }
+
+    fn update(&mut self, delta_time: f32) {
+        self.as_mut().update(delta_time);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GltfAnimationTarget(pub String);
 
@@ -949,6 +1238,18 @@ pub struct GltfSceneInstance {
     animations: HashMap<String, GltfSceneAnimationHandle>,
     parameters: HashMap<String, GltfAnimationParameter>,
     animation_node: Option<Box<dyn GltfAnimationNode>>,
+    root_motion: Option<GltfRootMotion>,
+    root_motion_delta: RwLock<GltfRootMotionDelta>,
+    /// When set, animation update frequency is chosen automatically while drawing.
+    pub lod_policy: Option<LodPolicy>,
+    /// Reset before and solved after every `apply_animations` call.
+    pub ik_rig: Option<IkRig>,
+    lod_timer: f32,
+    lod_refresh_delay: f32,
+    lod_suspended: bool,
+    /// Increased with every matrices recompute.
+    pose_revision: AtomicU64,
+    lod_renderables: Option<(u64, GltfSceneRenderables)>,
 }
 
 impl GltfSceneInstance {
@@ -976,6 +1277,21 @@ impl GltfSceneInstance {
         self
     }
 
+    pub fn with_root_motion(mut self, root_motion: GltfRootMotion) -> Self {
+        self.set_root_motion(root_motion);
+        self
+    }
+
+    pub fn lod_policy(mut self, value: LodPolicy) -> Self {
+        self.lod_policy = Some(value);
+        self
+    }
+
+    pub fn ik_rig(mut self, value: IkRig) -> Self {
+        self.ik_rig = Some(value);
+        self
+    }
+
     pub fn animation(&self, name: &str) -> Option<&GltfSceneAnimationHandle> {
         self.animations.get(name)
     }
@@ -1072,10 +1388,129 @@ impl GltfSceneInstance {
         self.animation_node = Some(Box::new(animation_node));
     }
 
+    pub fn root_motion(&self) -> Option<&GltfRootMotion> {
+        self.root_motion.as_ref()
+    }
+
+    pub fn set_root_motion(&mut self, root_motion: GltfRootMotion) {
+        self.root_motion = Some(root_motion);
+        self.reset_root_motion();
+    }
+
+    pub fn unset_root_motion(&mut self) {
+        self.root_motion = None;
+        self.reset_root_motion();
+    }
+
+    /// Root motion extracted by last `apply_animations` call, or zero when
+    /// last `try_update_and_apply_animations` call skipped the update.
+    pub fn root_motion_delta(&self) -> GltfRootMotionDelta {
+        self.root_motion_delta
+            .read()
+            .map(|delta| *delta)
+            .unwrap_or_default()
+    }
+
+    fn clear_root_motion_delta(&self) {
+        if let Ok(mut delta) = self.root_motion_delta.write() {
+            *delta = Default::default();
+        }
+    }
+
+    fn reset_root_motion(&mut self) {
+        for handle in self.animations.values() {
+            if let Some(mut animation) = handle.write() {
+                animation.reset_root_motion();
+            }
+        }
+        self.clear_root_motion_delta();
+    }
+
     pub fn unset_animation_node(&mut self) {
         self.animation_node = None;
     }
 
+    /// (node, camera, world matrix) of all camera nodes.
+    pub fn cameras(&self) -> Vec<(AnyIndex, GltfCamera, Mat4<f32>)> {
+        self.graph
+            .iter::<GltfNodeId>()
+            .filter_map(|(index, _)| {
+                let (camera, transform) = self
+                    .graph
+                    .query::<(
+                        Related<GltfSceneAttribute, &GltfSceneCamera>,
+                        Related<GltfSceneAttribute, &GltfSceneTransform>,
+                    )>(index)
+                    .next()?;
+                Some((index, camera.0.clone(), transform.global_matrix))
+            })
+            .collect()
+    }
+
+    /// (node, light, world matrix) of all light nodes.
+    pub fn lights(&self) -> Vec<(AnyIndex, GltfLight, Mat4<f32>)> {
+        self.graph
+            .iter::<GltfNodeId>()
+            .filter_map(|(index, _)| {
+                let (light, transform) = self
+                    .graph
+                    .query::<(
+                        Related<GltfSceneAttribute, &GltfSceneLight>,
+                        Related<GltfSceneAttribute, &GltfSceneTransform>,
+                    )>(index)
+                    .next()?;
+                Some((index, light.0.clone(), transform.global_matrix))
+            })
+            .collect()
+    }
+
+    /// Moves 2D camera to camera node projected with renderables axes, and fits
+    /// its view to camera projection. Returns false if node has no camera.
+    pub fn apply_camera(
+        &self,
+        node: AnyIndex,
+        options: &GltfRenderablesOptions,
+        camera: &mut Camera,
+    ) -> bool {
+        let Some((scene_camera, transform)) = self
+            .graph
+            .query::<(
+                Related<GltfSceneAttribute, &GltfSceneCamera>,
+                Related<GltfSceneAttribute, &GltfSceneTransform>,
+            )>(node)
+            .next()
+        else {
+            return false;
+        };
+        let position = transform
+            .global_matrix
+            .mul_point(Vec3::<f32>::zero())
+            .into_array();
+        let mut position2d = Vec2::new(position[options.axes[0]], position[options.axes[1]]);
+        if options.flip_axes[0] {
+            position2d.x = -position2d.x;
+        }
+        if options.flip_axes[1] {
+            position2d.y = -position2d.y;
+        }
+        camera.transform.position = position2d.into();
+        match scene_camera.0.projection {
+            GltfCameraProjection::Orthographic { xmag, ymag, .. } => {
+                camera.scaling = CameraScaling::Stretch(Vec2::new(xmag, ymag) * 2.0);
+            }
+            GltfCameraProjection::Perspective { yfov, .. } => {
+                let depth_axis = (0..3).find(|axis| !options.axes.contains(axis));
+                let distance = depth_axis
+                    .and_then(|axis| position.get(axis))
+                    .copied()
+                    .unwrap_or_default()
+                    .abs();
+                camera.scaling = CameraScaling::FitVertical(2.0 * distance * (yfov * 0.5).tan());
+            }
+        }
+        true
+    }
+
     pub fn update_animations(&mut self, delta_time: f32) {
         for handle in self.animations.values() {
             if let Some(mut animation) = handle.write() {
@@ -1112,6 +1547,9 @@ impl GltfSceneInstance {
     }
 
     pub fn apply_animations(&self, database: &AssetDatabase) {
+        if let Some(rig) = &self.ik_rig {
+            rig.reset(self);
+        }
         let mut delta_changes = HashMap::<
             AnyIndex,
             (
@@ -1120,8 +1558,11 @@ impl GltfSceneInstance {
                 Vec<(Vec3<f32>, f32)>,
             ),
         >::default();
+        let mut morph_changes = HashMap::<AnyIndex, Vec<(Vec<f32>, f32)>>::default();
+        let mut root_translations = Vec::<(Vec3<f32>, f32)>::default();
+        let mut root_rotations = Vec::<(Quaternion<f32>, f32)>::default();
         for handle in self.animations.values() {
-            let Some(animation) = handle.read() else {
+            let Some(mut animation) = handle.write() else {
                 continue;
             };
             let Some(asset) = animation
@@ -1130,16 +1571,37 @@ impl GltfSceneInstance {
             else {
                 continue;
             };
+            let max_time = asset.duration;
+            let time_to_sample = if animation.looped {
+                animation.time % max_time
+            } else {
+                animation.time.min(max_time)
+            };
+            let root_motion_wrapped = animation.looped
+                && animation.root_motion_sample.time.is_some_and(|time| {
+                    if animation.speed >= 0.0 {
+                        time_to_sample < time
+                    } else {
+                        time_to_sample > time
+                    }
+                });
             for channel in &asset.channels {
                 if channel.times.len() < 2 {
                     continue;
                 }
-                let Some(node_index) = self
-                    .graph
-                    .iter::<GltfNodeId>()
-                    .find(|(_, id)| **id == channel.target_node)
-                    .map(|(index, _)| index)
-                else {
+                let retarget_bone = match animation.retarget.as_ref() {
+                    Some(retarget) => match retarget.bones.get(&channel.target_node) {
+                        Some(bone) => Some(*bone),
+                        None => continue,
+                    },
+                    None => None,
+                };
+                let Some(node_index) = retarget_bone.map(|bone| bone.target).or_else(|| {
+                    self.graph
+                        .iter::<GltfNodeId>()
+                        .find(|(_, id)| **id == channel.target_node)
+                        .map(|(index, _)| index)
+                }) else {
                     continue;
                 };
                 if !animation.affected_nodes.is_empty()
@@ -1147,12 +1609,6 @@ impl GltfSceneInstance {
                 {
                     continue;
                 }
-                let max_time = asset.duration;
-                let time_to_sample = if animation.looped {
-                    animation.time % max_time
-                } else {
-                    animation.time.min(max_time)
-                };
                 let result_index = channel
                     .times
                     .iter()
@@ -1178,123 +1634,236 @@ impl GltfSceneInstance {
                 else {
                     continue;
                 };
+                let (bind, translation_scale) = match retarget_bone {
+                    Some(bone) => (bone.source_bind_transform, bone.translation_scale),
+                    None => (transform.bind_transform, 1.0),
+                };
+                let weight = animation
+                    .nodes_weight_override
+                    .get(&node_index)
+                    .copied()
+                    .unwrap_or(animation.weight);
+                let is_root_motion = self
+                    .root_motion
+                    .as_ref()
+                    .is_some_and(|root_motion| root_motion.node == node_index);
                 match &channel.values {
-                    GltfAnimationValues::Translation(values) => match channel.interpolation {
-                        Interpolation::Linear => {
-                            let t0 = channel.times[i0];
-                            let t1 = channel.times[i1];
-                            let v0 = values[i0] - transform.bind_transform.position;
-                            let v1 = values[i1] - transform.bind_transform.position;
-                            let factor = if (t0 - t1).abs() < f32::EPSILON {
-                                0.0
-                            } else {
-                                (time_to_sample - t0) / (t1 - t0)
-                            };
-                            let value = v0 + (v1 - v0) * factor;
-                            delta_changes.entry(node_index).or_default().0.push((
-                                value,
-                                animation
-                                    .nodes_weight_override
-                                    .get(&node_index)
-                                    .copied()
-                                    .unwrap_or(animation.weight),
-                            ));
-                        }
-                        Interpolation::Step => {
-                            let value = if i1 == 0 { values[0] } else { values[i0] }
-                                - transform.bind_transform.position;
-                            delta_changes.entry(node_index).or_default().0.push((
-                                value,
-                                animation
-                                    .nodes_weight_override
-                                    .get(&node_index)
-                                    .copied()
-                                    .unwrap_or(animation.weight),
-                            ));
-                        }
-                        Interpolation::CubicSpline => {
-                            println!("CubicSpline interpolation not implemented yet.");
-                        }
-                    },
-                    GltfAnimationValues::Rotation(values) => match channel.interpolation {
-                        Interpolation::Linear => {
-                            let t0 = channel.times[i0];
-                            let t1 = channel.times[i1];
-                            let v0 = (values[i0] * transform.bind_transform.orientation.inverse())
-                                .normalized();
-                            let v1 = (values[i1] * transform.bind_transform.orientation.inverse())
-                                .normalized();
-                            let factor = if (t0 - t1).abs() < f32::EPSILON {
-                                0.0
-                            } else {
-                                (time_to_sample - t0) / (t1 - t0)
+                    GltfAnimationValues::Translation(values) => {
+                        let value = match channel.interpolation {
+                            Interpolation::Linear => {
+                                let t0 = channel.times[i0];
+                                let t1 = channel.times[i1];
+                                let v0 = values[i0] - bind.position;
+                                let v1 = values[i1] - bind.position;
+                                let factor = if (t0 - t1).abs() < f32::EPSILON {
+                                    0.0
+                                } else {
+                                    (time_to_sample - t0) / (t1 - t0)
+                                };
+                                v0 + (v1 - v0) * factor
+                            }
+                            Interpolation::Step => {
+                                (if i1 == 0 { values[0] } else { values[i0] }) - bind.position
+                            }
+                            Interpolation::CubicSpline => {
+                                let Some((
+                                    GltfAnimationValues::Translation(in_tangents),
+                                    GltfAnimationValues::Translation(out_tangents),
+                                )) = &channel.tangents
+                                else {
+                                    continue;
+                                };
+                                cubic_spline(
+                                    &channel.times,
+                                    i0,
+                                    i1,
+                                    time_to_sample,
+                                    values,
+                                    in_tangents,
+                                    out_tangents,
+                                ) - bind.position
+                            }
+                        } * translation_scale;
+                        if is_root_motion {
+                            let first = (values[0] - bind.position) * translation_scale;
+                            let last =
+                                (values[values.len() - 1] - bind.position) * translation_scale;
+                            let delta = match animation.root_motion_sample.translation {
+                                Some(previous) if root_motion_wrapped => {
+                                    if animation.speed >= 0.0 {
+                                        (last - previous) + (value - first)
+                                    } else {
+                                        (first - previous) + (value - last)
+                                    }
+                                }
+                                Some(previous) => value - previous,
+                                None => Vec3::zero(),
                             };
-                            let value = shortest_slerp(v0, v1, factor);
-                            delta_changes.entry(node_index).or_default().1.push((
-                                value,
-                                animation
-                                    .nodes_weight_override
-                                    .get(&node_index)
-                                    .copied()
-                                    .unwrap_or(animation.weight),
-                            ));
+                            root_translations.push((delta, weight));
+                            animation.root_motion_sample.translation = Some(value);
                         }
-                        Interpolation::Step => {
-                            let value = if i1 == 0 { values[0] } else { values[i0] };
-                            let value = (value * transform.bind_transform.orientation.inverse())
+                        delta_changes
+                            .entry(node_index)
+                            .or_default()
+                            .0
+                            .push((value, weight));
+                    }
+                    GltfAnimationValues::Rotation(values) => {
+                        let value = match channel.interpolation {
+                            Interpolation::Linear => {
+                                let t0 = channel.times[i0];
+                                let t1 = channel.times[i1];
+                                let v0 = (values[i0] * bind.orientation.inverse()).normalized();
+                                let v1 = (values[i1] * bind.orientation.inverse()).normalized();
+                                let factor = if (t0 - t1).abs() < f32::EPSILON {
+                                    0.0
+                                } else {
+                                    (time_to_sample - t0) / (t1 - t0)
+                                };
+                                shortest_slerp(v0, v1, factor)
+                            }
+                            Interpolation::Step => {
+                                let value = if i1 == 0 { values[0] } else { values[i0] };
+                                (value * bind.orientation.inverse()).normalized()
+                            }
+                            Interpolation::CubicSpline => {
+                                let Some((
+                                    GltfAnimationValues::Rotation(in_tangents),
+                                    GltfAnimationValues::Rotation(out_tangents),
+                                )) = &channel.tangents
+                                else {
+                                    continue;
+                                };
+                                let value = cubic_spline(
+                                    &channel.times,
+                                    i0,
+                                    i1,
+                                    time_to_sample,
+                                    values,
+                                    in_tangents,
+                                    out_tangents,
+                                );
+                                (value.normalized() * bind.orientation.inverse()).normalized()
+                            }
+                        };
+                        if is_root_motion {
+                            let first = (values[0] * bind.orientation.inverse()).normalized();
+                            let last = (values[values.len() - 1] * bind.orientation.inverse())
                                 .normalized();
-                            delta_changes.entry(node_index).or_default().1.push((
-                                value,
-                                animation
-                                    .nodes_weight_override
-                                    .get(&node_index)
-                                    .copied()
-                                    .unwrap_or(animation.weight),
-                            ));
-                        }
-                        Interpolation::CubicSpline => {
-                            println!("CubicSpline interpolation not implemented yet.");
-                        }
-                    },
-                    GltfAnimationValues::Scale(values) => match channel.interpolation {
-                        Interpolation::Linear => {
-                            let t0 = channel.times[i0];
-                            let t1 = channel.times[i1];
-                            let v0 = values[i0] / transform.bind_transform.scale;
-                            let v1 = values[i1] / transform.bind_transform.scale;
-                            let factor = if (t0 - t1).abs() < f32::EPSILON {
-                                0.0
-                            } else {
-                                (time_to_sample - t0) / (t1 - t0)
+                            let delta = match animation.root_motion_sample.rotation {
+                                Some(previous) if root_motion_wrapped => {
+                                    if animation.speed >= 0.0 {
+                                        (value * first.inverse()) * (last * previous.inverse())
+                                    } else {
+                                        (value * last.inverse()) * (first * previous.inverse())
+                                    }
+                                }
+                                Some(previous) => value * previous.inverse(),
+                                None => Quaternion::identity(),
                             };
-                            let value = v0 + (v1 - v0) * factor;
-                            delta_changes.entry(node_index).or_default().2.push((
-                                value,
-                                animation
-                                    .nodes_weight_override
-                                    .get(&node_index)
-                                    .copied()
-                                    .unwrap_or(animation.weight),
-                            ));
+                            root_rotations.push((delta.normalized(), weight));
+                            animation.root_motion_sample.rotation = Some(value);
                         }
-                        Interpolation::Step => {
-                            let value = if i1 == 0 { values[0] } else { values[i0] }
-                                / transform.bind_transform.scale;
-                            delta_changes.entry(node_index).or_default().2.push((
-                                value,
-                                animation
-                                    .nodes_weight_override
-                                    .get(&node_index)
-                                    .copied()
-                                    .unwrap_or(animation.weight),
-                            ));
-                        }
-                        Interpolation::CubicSpline => {
-                            println!("CubicSpline interpolation not implemented yet.");
-                        }
-                    },
+                        delta_changes
+                            .entry(node_index)
+                            .or_default()
+                            .1
+                            .push((value, weight));
+                    }
+                    GltfAnimationValues::Scale(values) => {
+                        let value = match channel.interpolation {
+                            Interpolation::Linear => {
+                                let t0 = channel.times[i0];
+                                let t1 = channel.times[i1];
+                                let v0 = values[i0] / bind.scale;
+                                let v1 = values[i1] / bind.scale;
+                                let factor = if (t0 - t1).abs() < f32::EPSILON {
+                                    0.0
+                                } else {
+                                    (time_to_sample - t0) / (t1 - t0)
+                                };
+                                v0 + (v1 - v0) * factor
+                            }
+                            Interpolation::Step => {
+                                (if i1 == 0 { values[0] } else { values[i0] }) / bind.scale
+                            }
+                            Interpolation::CubicSpline => {
+                                let Some((
+                                    GltfAnimationValues::Scale(in_tangents),
+                                    GltfAnimationValues::Scale(out_tangents),
+                                )) = &channel.tangents
+                                else {
+                                    continue;
+                                };
+                                cubic_spline(
+                                    &channel.times,
+                                    i0,
+                                    i1,
+                                    time_to_sample,
+                                    values,
+                                    in_tangents,
+                                    out_tangents,
+                                ) / bind.scale
+                            }
+                        };
+                        delta_changes
+                            .entry(node_index)
+                            .or_default()
+                            .2
+                            .push((value, weight));
+                    }
+                    GltfAnimationValues::MorphTargetWeights(values) => {
+                        let value = match channel.interpolation {
+                            Interpolation::Linear => {
+                                let t0 = channel.times[i0];
+                                let t1 = channel.times[i1];
+                                let factor = if (t0 - t1).abs() < f32::EPSILON {
+                                    0.0
+                                } else {
+                                    (time_to_sample - t0) / (t1 - t0)
+                                };
+                                values[i0]
+                                    .iter()
+                                    .zip(values[i1].iter())
+                                    .map(|(v0, v1)| v0 + (v1 - v0) * factor)
+                                    .collect::<Vec<_>>()
+                            }
+                            Interpolation::Step => {
+                                if i1 == 0 {
+                                    values[0].clone()
+                                } else {
+                                    values[i0].clone()
+                                }
+                            }
+                            Interpolation::CubicSpline => {
+                                let Some((
+                                    GltfAnimationValues::MorphTargetWeights(in_tangents),
+                                    GltfAnimationValues::MorphTargetWeights(out_tangents),
+                                )) = &channel.tangents
+                                else {
+                                    continue;
+                                };
+                                match cubic_spline_factors(&channel.times, i0, i1, time_to_sample) {
+                                    Some([h0, m0, h1, m1]) => (0..values[i0].len())
+                                        .map(|index| {
+                                            values[i0][index] * h0
+                                                + out_tangents[i0][index] * m0
+                                                + values[i1][index] * h1
+                                                + in_tangents[i1][index] * m1
+                                        })
+                                        .collect(),
+                                    None => values[i0].clone(),
+                                }
+                            }
+                        };
+                        morph_changes
+                            .entry(node_index)
+                            .or_default()
+                            .push((value, weight));
+                    }
                 }
             }
+            animation.root_motion_sample.time = Some(time_to_sample);
         }
         for (node_index, (translations, rotations, scales)) in delta_changes {
             let Some(mut transform) = self
@@ -1353,7 +1922,69 @@ impl GltfSceneInstance {
                 }
             }
         }
+        for (node_index, changes) in morph_changes {
+            let Some(mut morph_weights) = self
+                .graph
+                .query::<Related<GltfSceneAttribute, &mut GltfSceneMorphWeights>>(node_index)
+                .next()
+            else {
+                continue;
+            };
+            let total_weight = changes.iter().map(|(_, w)| *w).sum::<f32>();
+            if changes.len() == 1 {
+                morph_weights.weights = changes[0].0.clone();
+            } else if total_weight > f32::EPSILON {
+                let mut accumulated = vec![0.0; morph_weights.weights.len()];
+                for (values, weight) in changes {
+                    for (target, value) in accumulated.iter_mut().zip(values) {
+                        *target += value * weight;
+                    }
+                }
+                for value in &mut accumulated {
+                    *value /= total_weight;
+                }
+                morph_weights.weights = accumulated;
+            }
+        }
+        if let Some(root_motion) = self.root_motion.as_ref() {
+            let mut delta = GltfRootMotionDelta::default();
+            let total_weight = root_translations.iter().map(|(_, w)| *w).sum::<f32>();
+            if total_weight > f32::EPSILON {
+                let mut accumulated = Vec3::zero();
+                for (value, weight) in root_translations {
+                    accumulated += value * weight;
+                }
+                delta.translation = (accumulated / total_weight) * root_motion.translation_mask;
+            }
+            if root_motion.rotation {
+                let total_weight = root_rotations.iter().map(|(_, w)| *w).sum::<f32>();
+                if total_weight > f32::EPSILON {
+                    for (value, weight) in root_rotations {
+                        delta.rotation =
+                            shortest_slerp(delta.rotation, value, weight / total_weight);
+                    }
+                }
+            }
+            if let Some(mut transform) = self
+                .graph
+                .query::<Related<GltfSceneAttribute, &mut GltfSceneTransform>>(root_motion.node)
+                .next()
+            {
+                let offset = transform.transform.position - transform.bind_transform.position;
+                transform.transform.position = transform.bind_transform.position
+                    + offset * (Vec3::one() - root_motion.translation_mask);
+                if root_motion.rotation {
+                    transform.transform.orientation = transform.bind_transform.orientation;
+                }
+            }
+            if let Ok(mut output) = self.root_motion_delta.write() {
+                *output = delta;
+            }
+        }
         self.recompute_matrices();
+        if let Some(rig) = &self.ik_rig {
+            rig.solve(self);
+        }
     }
 
     pub fn update_and_apply_animations(&mut self, delta_time: f32, database: &AssetDatabase) {
@@ -1361,6 +1992,44 @@ impl GltfSceneInstance {
         self.apply_animations(database);
     }
 
+    /// Chooses animation refresh rate using LOD policy and returns selected LOD index.
+    pub fn apply_lod_metrics(&mut self, metrics: LodMetrics) -> Option<usize> {
+        let (lod, refresh_delay) = self.lod_policy.as_ref()?.select(metrics).unzip();
+        self.lod_suspended = refresh_delay.is_none();
+        self.lod_refresh_delay = refresh_delay.unwrap_or_default();
+        lod
+    }
+
+    /// Accumulates time and updates animations only when refresh is due and budget allows it.
+    /// Skipped frames report zero root motion delta, the next refresh covers accumulated time.
+    pub fn try_update_and_apply_animations(
+        &mut self,
+        delta_time: f32,
+        database: &AssetDatabase,
+        budget: Option<&mut LodBudgetSubsystem>,
+    ) -> bool {
+        if self.lod_suspended {
+            // Hidden period is skipped, not replayed at once on resume.
+            self.lod_timer = 0.0;
+            self.clear_root_motion_delta();
+            return false;
+        }
+        self.lod_timer += delta_time;
+        if self.lod_timer < self.lod_refresh_delay {
+            self.clear_root_motion_delta();
+            return false;
+        }
+        if let Some(budget) = budget
+            && !budget.try_acquire()
+        {
+            self.clear_root_motion_delta();
+            return false;
+        }
+        let delta_time = std::mem::take(&mut self.lod_timer);
+        self.update_and_apply_animations(delta_time, database);
+        true
+    }
+
     pub fn visit_tree(
         &self,
         f: &mut impl FnMut(
@@ -1438,11 +2107,29 @@ impl GltfSceneInstance {
     }
 
     pub fn recompute_matrices(&self) {
+        self.pose_revision.fetch_add(1, AtomicOrdering::Relaxed);
         for root_index in self.roots() {
             self.recompute_matrix(Default::default(), root_index);
         }
     }
 
+    /// Recomputes matrices of node and its descendants, keeping the rest.
+    pub fn recompute_subtree_matrices(&self, index: AnyIndex) {
+        self.pose_revision.fetch_add(1, AtomicOrdering::Relaxed);
+        let parent_matrix = self
+            .graph
+            .query::<Related<GltfSceneParent, Node<GltfNodeId>>>(index)
+            .next()
+            .and_then(|parent| {
+                self.graph
+                    .query::<Related<GltfSceneAttribute, &GltfSceneTransform>>(parent)
+                    .next()
+                    .map(|transform| transform.global_matrix)
+            })
+            .unwrap_or_default();
+        self.recompute_matrix(parent_matrix, index);
+    }
+
     fn recompute_matrix(&self, parent_matrix: Mat4<f32>, index: AnyIndex) {
         let matrix = if let Some(mut transform) = self
             .graph
@@ -1589,12 +2276,17 @@ impl GltfSceneInstance {
                 .next();
             let skin_asset =
                 skin.and_then(|skin| skin.handle().access_checked::<&GltfSkin>(database));
+            let morph_weights = self
+                .graph
+                .query::<Related<GltfSceneAttribute, &GltfSceneMorphWeights>>(index)
+                .next();
 
             for primitive in &mesh_asset.primitives {
                 let mut renderable = Self::produce_primitive_renderable(
                     options,
                     final_bone_matrices,
                     skin_asset,
+                    morph_weights.as_ref().map(|v| v.weights.as_slice()),
                     primitive,
                     &transform,
                 );
@@ -1670,12 +2362,17 @@ impl GltfSceneInstance {
                 .next();
             let skin_asset =
                 skin.and_then(|skin| skin.handle().access_checked::<&GltfSkin>(database));
+            let morph_weights = self
+                .graph
+                .query::<Related<GltfSceneAttribute, &GltfSceneMorphWeights>>(index)
+                .next();
 
             for primitive in &mesh_asset.primitives {
                 let renderable = Self::produce_primitive_renderable(
                     options,
                     final_bone_matrices,
                     skin_asset,
+                    morph_weights.as_ref().map(|v| v.weights.as_slice()),
                     primitive,
                     &transform,
                 );
@@ -1697,6 +2394,7 @@ impl GltfSceneInstance {
         options: &GltfRenderablesOptions,
         final_bone_matrices: &HashMap<GltfNodeId, Mat4<f32>>,
         skin_asset: Option<&GltfSkin>,
+        morph_weights: Option<&[f32]>,
         primitive: &GltfPrimitive,
         transform: &GltfSceneTransform,
     ) -> GltfSceneRenderable {
@@ -1704,7 +2402,18 @@ impl GltfSceneInstance {
         let vertices = primitive
             .vertices
             .iter()
-            .map(|v| {
+            .enumerate()
+            .map(|(vertex_index, v)| {
+                let mut v = *v;
+                if let Some(morph_weights) = morph_weights {
+                    for (target, weight) in primitive.morph_targets.iter().zip(morph_weights) {
+                        if weight.abs() > f32::EPSILON
+                            && let Some(delta) = target.positions.get(vertex_index)
+                        {
+                            v.position += *delta * *weight;
+                        }
+                    }
+                }
                 let position = if let Some(skin_asset) = skin_asset {
                     if let (Some(joints), Some(weights)) = (v.joints, v.weights) {
                         let mut skinned_position = Vec3::zero();
@@ -1832,11 +2541,50 @@ impl GltfSceneInstance {
 }
 
 impl GameObject for GltfSceneInstance {
+    fn process(&mut self, context: &mut GameContext, delta_time: f32) {
+        if self.lod_policy.is_some() {
+            let budget = context.subsystems.get_mut::<LodBudgetSubsystem>();
+            self.try_update_and_apply_animations(delta_time, context.assets, budget);
+        }
+    }
+
     fn draw(&mut self, context: &mut GameContext) {
-        if let Ok(renderables) = self.build_renderables(
-            context.assets,
-            &GltfRenderablesOptions::default().sort_triangles_by_max_positive_z(),
-        ) {
+        let options = GltfRenderablesOptions::default().sort_triangles_by_max_positive_z();
+        if self.lod_policy.is_none() {
+            if let Ok(renderables) = self.build_renderables(context.assets, &options) {
+                renderables.draw(context.draw, context.graphics);
+            }
+            return;
+        }
+        // Renderables are rebuilt only after pose changed, so suspended and
+        // rarely refreshed instances skip skinning and morphing.
+        let revision = self.pose_revision.load(AtomicOrdering::Relaxed);
+        if self
+            .lod_renderables
+            .as_ref()
+            .is_none_or(|(cached, _)| *cached != revision)
+        {
+            match self.build_renderables(context.assets, &options) {
+                Ok(renderables) => self.lod_renderables = Some((revision, renderables)),
+                Err(_) => return,
+            }
+        }
+        let Some(bounds) = self
+            .lod_renderables
+            .as_ref()
+            .and_then(|(_, renderables)| renderables.bounds())
+        else {
+            return;
+        };
+        let metrics = LodMetrics::from_local_bounds(
+            bounds,
+            context.draw.top_transform(),
+            &context.graphics.state.main_camera,
+        );
+        self.apply_lod_metrics(metrics);
+        if metrics.visible
+            && let Some((_, renderables)) = &self.lod_renderables
+        {
             renderables.draw(context.draw, context.graphics);
         }
     }
@@ -2004,6 +2752,22 @@ pub struct GltfSceneRenderables {
     pub bones_triangles: Vec<Triangle>,
 }
 
+impl GltfSceneRenderables {
+    /// Bounds of renderables vertices, in scene space.
+    pub fn bounds(&self) -> Option<Rect<f32, f32>> {
+        let mut points = self
+            .renderables
+            .iter()
+            .flat_map(|renderable| renderable.vertices.iter())
+            .map(|vertex| Vec2::from(vertex.position));
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), point| {
+            (Vec2::partial_min(min, point), Vec2::partial_max(max, point))
+        });
+        Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+    }
+}
+
 impl Drawable for GltfSceneRenderables {
     fn draw(&self, context: &mut DrawContext, graphics: &mut dyn GraphicsTarget<Vertex>) {
         let matrix = context.top_transform();
@@ -2076,6 +2840,44 @@ impl Drawable for GltfSceneRenderables {
     }
 }
 
+/// Cubic Hermite spline sampling between keyframes, as defined by glTF spec.
+fn cubic_spline<T>(
+    times: &[f32],
+    i0: usize,
+    i1: usize,
+    time: f32,
+    values: &[T],
+    in_tangents: &[T],
+    out_tangents: &[T],
+) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    let Some([h0, m0, h1, m1]) = cubic_spline_factors(times, i0, i1, time) else {
+        return values[i0];
+    };
+    values[i0] * h0 + out_tangents[i0] * m0 + values[i1] * h1 + in_tangents[i1] * m1
+}
+
+/// Factors of (value 0, out tangent 0, value 1, in tangent 1) for cubic Hermite spline.
+fn cubic_spline_factors(times: &[f32], i0: usize, i1: usize, time: f32) -> Option<[f32; 4]> {
+    let t0 = times[i0];
+    let t1 = times[i1];
+    let delta = t1 - t0;
+    if delta <= f32::EPSILON {
+        return None;
+    }
+    let t = ((time - t0) / delta).clamp(0.0, 1.0);
+    let t2 = t * t;
+    let t3 = t2 * t;
+    Some([
+        2.0 * t3 - 3.0 * t2 + 1.0,
+        (t3 - 2.0 * t2 + t) * delta,
+        -2.0 * t3 + 3.0 * t2,
+        (t3 - t2) * delta,
+    ])
+}
+
 fn shortest_slerp(a: Quaternion<f32>, mut b: Quaternion<f32>, t: f32) -> Quaternion<f32> {
     if a.dot(b) < 0.0 {
         b = -b;
@@ -0,0 +1,651 @@
+use crate::animation::{
+    gltf::{
+        GltfNodeId, GltfSceneAttribute, GltfSceneInstance, GltfSceneParent, GltfSceneTransform,
+    },
+    spine::SpineSkeleton,
+};
+use nodio::query::{Node, Related};
+use rusty_spine::Physics;
+use std::f32::consts::PI;
+use vek::{Quaternion, Vec3};
+
+/// Skeleton bones access required by IK solvers.
+/// Positions and directions are in skeleton space.
+pub trait IkSkeleton {
+    fn ik_bone_position(&self, bone: &str) -> Option<Vec3<f32>>;
+
+    fn ik_bone_direction(&self, bone: &str, local_direction: Vec3<f32>) -> Option<Vec3<f32>>;
+
+    /// Rotates bone so its `from` direction points towards `to` direction.
+    fn ik_rotate_bone(&self, bone: &str, from: Vec3<f32>, to: Vec3<f32>, weight: f32);
+
+    /// Restores bone local rotation to bind (or setup) pose.
+    fn ik_reset_bone(&self, bone: &str);
+}
+
+impl IkSkeleton for GltfSceneInstance {
+    fn ik_bone_position(&self, bone: &str) -> Option<Vec3<f32>> {
+        let transform =
+            self.query_bone_by_name::<Related<GltfSceneAttribute, &GltfSceneTransform>>(bone)?;
+        Some(transform.world_matrix().mul_point(Vec3::zero()))
+    }
+
+    fn ik_bone_direction(&self, bone: &str, local_direction: Vec3<f32>) -> Option<Vec3<f32>> {
+        let transform =
+            self.query_bone_by_name::<Related<GltfSceneAttribute, &GltfSceneTransform>>(bone)?;
+        Some(
+            transform
+                .world_matrix()
+                .mul_direction(local_direction)
+                .try_normalized()
+                .unwrap_or(local_direction),
+        )
+    }
+
+    fn ik_rotate_bone(&self, bone: &str, from: Vec3<f32>, to: Vec3<f32>, weight: f32) {
+        let Some(index) = self.find_bone_by_name(bone) else {
+            return;
+        };
+        let parent_inverse_matrix = self
+            .graph()
+            .query::<Related<GltfSceneParent, Node<GltfNodeId>>>(index)
+            .next()
+            .and_then(|parent| {
+                self.graph()
+                    .query::<Related<GltfSceneAttribute, &GltfSceneTransform>>(parent)
+                    .next()
+                    .map(|transform| transform.world_inverse_matrix())
+            })
+            .unwrap_or_default();
+        let (Some(from), Some(to)) = (
+            parent_inverse_matrix.mul_direction(from).try_normalized(),
+            parent_inverse_matrix.mul_direction(to).try_normalized(),
+        ) else {
+            return;
+        };
+        let rotation = Quaternion::slerp(
+            Quaternion::identity(),
+            Quaternion::rotation_from_to_3d(from, to),
+            weight.clamp(0.0, 1.0),
+        );
+        if let Some(mut transform) = self
+            .graph()
+            .query::<Related<GltfSceneAttribute, &mut GltfSceneTransform>>(index)
+            .next()
+        {
+            transform.transform.orientation =
+                (rotation * transform.transform.orientation).normalized();
+        }
+        self.recompute_subtree_matrices(index);
+    }
+
+    fn ik_reset_bone(&self, bone: &str) {
+        let Some(index) = self.find_bone_by_name(bone) else {
+            return;
+        };
+        if let Some(mut transform) = self
+            .graph()
+            .query::<Related<GltfSceneAttribute, &mut GltfSceneTransform>>(index)
+            .next()
+        {
+            transform.transform.orientation = transform.bind_transform().orientation;
+        }
+    }
+}
+
+impl IkSkeleton for SpineSkeleton {
+    fn ik_bone_position(&self, bone: &str) -> Option<Vec3<f32>> {
+        let controller = self.read()?;
+        let bone = controller.skeleton.find_bone(bone)?;
+        Some(Vec3::new(bone.world_x(), -bone.world_y(), 0.0))
+    }
+
+    fn ik_bone_direction(&self, bone: &str, local_direction: Vec3<f32>) -> Option<Vec3<f32>> {
+        let controller = self.read()?;
+        let bone = controller.skeleton.find_bone(bone)?;
+        let x = local_direction.x;
+        let y = -local_direction.y;
+        let result = Vec3::new(
+            bone.a() * x + bone.b() * y,
+            -(bone.c() * x + bone.d() * y),
+            0.0,
+        );
+        Some(result.try_normalized().unwrap_or(local_direction))
+    }
+
+    fn ik_rotate_bone(&self, bone: &str, from: Vec3<f32>, to: Vec3<f32>, weight: f32) {
+        let Some(mut controller) = self.write() else {
+            return;
+        };
+        let mut angle = to.y.atan2(to.x) - from.y.atan2(from.x);
+        if angle > PI {
+            angle -= 2.0 * PI;
+        } else if angle < -PI {
+            angle += 2.0 * PI;
+        }
+        // Skeleton space is Y down, while Spine works in Y up space.
+        let angle = -angle.to_degrees() * weight.clamp(0.0, 1.0);
+        {
+            let Some(mut bone) = controller.skeleton.find_bone_mut(bone) else {
+                return;
+            };
+            let sign = bone
+                .parent()
+                .map(|parent| (parent.a() * parent.d() - parent.b() * parent.c()).signum())
+                .unwrap_or(1.0);
+            let rotation = bone.rotation() + angle * sign;
+            bone.set_rotation(rotation);
+        }
+        controller.skeleton.update_world_transform(Physics::None);
+    }
+
+    fn ik_reset_bone(&self, bone: &str) {
+        let Some(mut controller) = self.write() else {
+            return;
+        };
+        if let Some(mut bone) = controller.skeleton.find_bone_mut(bone) {
+            let rotation = bone.data().rotation();
+            bone.set_rotation(rotation);
+        }
+    }
+}
+
+/// Solves two bone chain, returns new (middle, end) joint positions.
+/// Pole position decides which side chain bends to.
+pub fn solve_two_bone(
+    root: Vec3<f32>,
+    middle: Vec3<f32>,
+    end: Vec3<f32>,
+    target: Vec3<f32>,
+    pole: Option<Vec3<f32>>,
+) -> (Vec3<f32>, Vec3<f32>) {
+    let upper_length = root.distance(middle);
+    let lower_length = middle.distance(end);
+    let Some(direction) = (target - root).try_normalized() else {
+        return (middle, end);
+    };
+    let distance = root.distance(target).clamp(
+        (upper_length - lower_length).abs() + 1.0e-4,
+        upper_length + lower_length - 1.0e-4,
+    );
+    let bend = pole.unwrap_or(middle) - root;
+    let bend = (bend - direction * bend.dot(direction))
+        .try_normalized()
+        .or_else(|| direction.cross(Vec3::unit_z()).try_normalized())
+        .or_else(|| direction.cross(Vec3::unit_x()).try_normalized())
+        .unwrap_or_default();
+    let cos = ((upper_length * upper_length + distance * distance - lower_length * lower_length)
+        / (2.0 * upper_length * distance))
+        .clamp(-1.0, 1.0);
+    let sin = (1.0 - cos * cos).sqrt();
+    let middle = root + direction * upper_length * cos + bend * upper_length * sin;
+    let end = root + direction * distance;
+    (middle, end)
+}
+
+/// Solves chain of joints in place with FABRIK algorithm, first joint stays fixed.
+pub fn solve_fabrik(
+    joints: &mut [Vec3<f32>],
+    target: Vec3<f32>,
+    pole: Option<Vec3<f32>>,
+    iterations: usize,
+    tolerance: f32,
+) {
+    if joints.len() < 2 {
+        return;
+    }
+    let lengths = joints
+        .windows(2)
+        .map(|pair| pair[0].distance(pair[1]))
+        .collect::<Vec<_>>();
+    let root = joints[0];
+    if root.distance(target) >= lengths.iter().sum::<f32>() {
+        let direction = (target - root).try_normalized().unwrap_or_default();
+        for index in 1..joints.len() {
+            joints[index] = joints[index - 1] + direction * lengths[index - 1];
+        }
+        return;
+    }
+    if let Some(pole) = pole {
+        for index in 1..(joints.len() - 1) {
+            let start = joints[index - 1];
+            let Some(axis) = (joints[index + 1] - start).try_normalized() else {
+                continue;
+            };
+            let center = start + axis * (joints[index] - start).dot(axis);
+            let radius = joints[index].distance(center);
+            let offset = pole - center;
+            if let Some(offset) = (offset - axis * offset.dot(axis)).try_normalized() {
+                joints[index] = center + offset * radius;
+            }
+        }
+    }
+    let last = joints.len() - 1;
+    for _ in 0..iterations {
+        if joints[last].distance(target) <= tolerance {
+            break;
+        }
+        joints[last] = target;
+        for index in (0..last).rev() {
+            let direction = (joints[index] - joints[index + 1])
+                .try_normalized()
+                .unwrap_or_default();
+            joints[index] = joints[index + 1] + direction * lengths[index];
+        }
+        joints[0] = root;
+        for index in 0..last {
+            let direction = (joints[index + 1] - joints[index])
+                .try_normalized()
+                .unwrap_or_default();
+            joints[index + 1] = joints[index] + direction * lengths[index];
+        }
+    }
+}
+
+fn apply_chain(skeleton: &impl IkSkeleton, bones: &[String], joints: &[Vec3<f32>], weight: f32) {
+    for (index, pair) in bones.windows(2).enumerate() {
+        let (Some(from), Some(to)) = (
+            skeleton.ik_bone_position(&pair[0]),
+            skeleton.ik_bone_position(&pair[1]),
+        ) else {
+            return;
+        };
+        skeleton.ik_rotate_bone(&pair[0], to - from, joints[index + 1] - from, weight);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IkTwoBone {
+    pub root: String,
+    pub middle: String,
+    pub end: String,
+    pub target: Vec3<f32>,
+    pub pole: Option<Vec3<f32>>,
+    pub weight: f32,
+}
+
+impl IkTwoBone {
+    pub fn new(root: impl ToString, middle: impl ToString, end: impl ToString) -> Self {
+        Self {
+            root: root.to_string(),
+            middle: middle.to_string(),
+            end: end.to_string(),
+            target: Vec3::zero(),
+            pole: None,
+            weight: 1.0,
+        }
+    }
+
+    pub fn target(mut self, target: impl Into<Vec3<f32>>) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    pub fn pole(mut self, pole: impl Into<Vec3<f32>>) -> Self {
+        self.pole = Some(pole.into());
+        self
+    }
+
+    pub fn weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn solve(&self, skeleton: &impl IkSkeleton) {
+        let (Some(root), Some(middle), Some(end)) = (
+            skeleton.ik_bone_position(&self.root),
+            skeleton.ik_bone_position(&self.middle),
+            skeleton.ik_bone_position(&self.end),
+        ) else {
+            return;
+        };
+        let (middle, end) = solve_two_bone(root, middle, end, self.target, self.pole);
+        apply_chain(
+            skeleton,
+            &[self.root.clone(), self.middle.clone(), self.end.clone()],
+            &[root, middle, end],
+            self.weight,
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IkLookAt {
+    pub bone: String,
+    /// Bone local direction that should point at target.
+    pub forward: Vec3<f32>,
+    pub target: Vec3<f32>,
+    pub weight: f32,
+}
+
+impl IkLookAt {
+    pub fn new(bone: impl ToString) -> Self {
+        Self {
+            bone: bone.to_string(),
+            forward: Vec3::unit_x(),
+            target: Vec3::zero(),
+            weight: 1.0,
+        }
+    }
+
+    pub fn forward(mut self, forward: impl Into<Vec3<f32>>) -> Self {
+        self.forward = forward.into();
+        self
+    }
+
+    pub fn target(mut self, target: impl Into<Vec3<f32>>) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    pub fn weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn solve(&self, skeleton: &impl IkSkeleton) {
+        let (Some(position), Some(direction)) = (
+            skeleton.ik_bone_position(&self.bone),
+            skeleton.ik_bone_direction(&self.bone, self.forward),
+        ) else {
+            return;
+        };
+        skeleton.ik_rotate_bone(&self.bone, direction, self.target - position, self.weight);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IkFabrik {
+    /// Chain bones from root to end effector.
+    pub bones: Vec<String>,
+    pub target: Vec3<f32>,
+    pub pole: Option<Vec3<f32>>,
+    pub weight: f32,
+    pub iterations: usize,
+    pub tolerance: f32,
+}
+
+impl IkFabrik {
+    pub fn new(bones: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self {
+            bones: bones.into_iter().map(|bone| bone.to_string()).collect(),
+            target: Vec3::zero(),
+            pole: None,
+            weight: 1.0,
+            iterations: 10,
+            tolerance: 1.0e-3,
+        }
+    }
+
+    pub fn target(mut self, target: impl Into<Vec3<f32>>) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    pub fn pole(mut self, pole: impl Into<Vec3<f32>>) -> Self {
+        self.pole = Some(pole.into());
+        self
+    }
+
+    pub fn weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn solve(&self, skeleton: &impl IkSkeleton) {
+        let Some(mut joints) = self
+            .bones
+            .iter()
+            .map(|bone| skeleton.ik_bone_position(bone))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        solve_fabrik(
+            &mut joints,
+            self.target,
+            self.pole,
+            self.iterations,
+            self.tolerance,
+        );
+        apply_chain(skeleton, &self.bones, &joints, self.weight);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IkConstraint {
+    TwoBone(IkTwoBone),
+    LookAt(IkLookAt),
+    Fabrik(IkFabrik),
+}
+
+impl IkConstraint {
+    pub fn solve(&self, skeleton: &impl IkSkeleton) {
+        match self {
+            Self::TwoBone(constraint) => constraint.solve(skeleton),
+            Self::LookAt(constraint) => constraint.solve(skeleton),
+            Self::Fabrik(constraint) => constraint.solve(skeleton),
+        }
+    }
+
+    /// Bones rotated by solver.
+    pub fn bones(&self) -> Vec<&str> {
+        match self {
+            Self::TwoBone(constraint) => vec![&constraint.root, &constraint.middle],
+            Self::LookAt(constraint) => vec![&constraint.bone],
+            Self::Fabrik(constraint) => constraint
+                .bones
+                .iter()
+                .take(constraint.bones.len().saturating_sub(1))
+                .map(|bone| bone.as_str())
+                .collect(),
+        }
+    }
+}
+
+impl From<IkTwoBone> for IkConstraint {
+    fn from(value: IkTwoBone) -> Self {
+        Self::TwoBone(value)
+    }
+}
+
+impl From<IkLookAt> for IkConstraint {
+    fn from(value: IkLookAt) -> Self {
+        Self::LookAt(value)
+    }
+}
+
+impl From<IkFabrik> for IkConstraint {
+    fn from(value: IkFabrik) -> Self {
+        Self::Fabrik(value)
+    }
+}
+
+/// Named IK constraints solved in order, after animations got applied.
+/// Set it as `ik_rig` of GLTF scene instance or Spine skeleton to run it,
+/// those reset rig bones before applying animations.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IkRig {
+    pub constraints: Vec<(String, IkConstraint)>,
+}
+
+impl IkRig {
+    pub fn constraint(mut self, name: impl ToString, constraint: impl Into<IkConstraint>) -> Self {
+        self.add_constraint(name, constraint);
+        self
+    }
+
+    pub fn add_constraint(&mut self, name: impl ToString, constraint: impl Into<IkConstraint>) {
+        let name = name.to_string();
+        let constraint = constraint.into();
+        if let Some((_, item)) = self.constraints.iter_mut().find(|(n, _)| *n == name) {
+            *item = constraint;
+        } else {
+            self.constraints.push((name, constraint));
+        }
+    }
+
+    pub fn remove_constraint(&mut self, name: &str) {
+        self.constraints.retain(|(n, _)| n != name);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&IkConstraint> {
+        self.constraints
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, constraint)| constraint)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut IkConstraint> {
+        self.constraints
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, constraint)| constraint)
+    }
+
+    /// Restores bones rotated by constraints to bind pose, so solving starts
+    /// from stable pose. Call it before animations get applied.
+    pub fn reset(&self, skeleton: &impl IkSkeleton) {
+        for (_, constraint) in &self.constraints {
+            for bone in constraint.bones() {
+                skeleton.ik_reset_bone(bone);
+            }
+        }
+    }
+
+    pub fn solve(&self, skeleton: &impl IkSkeleton) {
+        for (_, constraint) in &self.constraints {
+            constraint.solve(skeleton);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Planar chain of unit length bones, as (name, setup angle, angle).
+    struct Chain(RefCell<Vec<(&'static str, f32, f32)>>);
+
+    impl Chain {
+        fn joint(&self, bone: &str) -> Option<(Vec3<f32>, f32)> {
+            let mut position = Vec3::zero();
+            let mut angle = 0.0;
+            for (name, _, local) in self.0.borrow().iter() {
+                angle += local;
+                if *name == bone {
+                    return Some((position, angle));
+                }
+                position += Vec3::new(angle.cos(), angle.sin(), 0.0);
+            }
+            None
+        }
+    }
+
+    impl IkSkeleton for Chain {
+        fn ik_bone_position(&self, bone: &str) -> Option<Vec3<f32>> {
+            self.joint(bone).map(|(position, _)| position)
+        }
+
+        fn ik_bone_direction(&self, bone: &str, local_direction: Vec3<f32>) -> Option<Vec3<f32>> {
+            let (_, angle) = self.joint(bone)?;
+            let (sin, cos) = angle.sin_cos();
+            Some(Vec3::new(
+                local_direction.x * cos - local_direction.y * sin,
+                local_direction.x * sin + local_direction.y * cos,
+                0.0,
+            ))
+        }
+
+        fn ik_rotate_bone(&self, bone: &str, from: Vec3<f32>, to: Vec3<f32>, weight: f32) {
+            let mut angle = to.y.atan2(to.x) - from.y.atan2(from.x);
+            if angle > PI {
+                angle -= 2.0 * PI;
+            } else if angle < -PI {
+                angle += 2.0 * PI;
+            }
+            if let Some(item) = self.0.borrow_mut().iter_mut().find(|item| item.0 == bone) {
+                item.2 += angle * weight;
+            }
+        }
+
+        fn ik_reset_bone(&self, bone: &str) {
+            if let Some(item) = self.0.borrow_mut().iter_mut().find(|item| item.0 == bone) {
+                item.2 = item.1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_ik_solvers() {
+        let root = Vec3::new(0.0, 0.0, 0.0);
+        let middle = Vec3::new(1.0, 0.0, 0.0);
+        let end = Vec3::new(2.0, 0.0, 0.0);
+        let target = Vec3::new(1.0, 1.0, 0.0);
+        let (m, e) = solve_two_bone(root, middle, end, target, Some(Vec3::new(0.0, 2.0, 0.0)));
+        assert!(e.distance(target) < 1.0e-3);
+        assert!((root.distance(m) - 1.0).abs() < 1.0e-3);
+        assert!((m.distance(e) - 1.0).abs() < 1.0e-3);
+        assert!(m.y > 0.5);
+        let (m, _) = solve_two_bone(root, middle, end, target, Some(Vec3::new(2.0, 0.0, 0.0)));
+        assert!(m.x > 0.5);
+
+        let mut joints = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(3.0, 0.0, 0.0),
+        ];
+        let target = Vec3::new(1.0, 1.5, 0.0);
+        solve_fabrik(&mut joints, target, None, 20, 1.0e-4);
+        assert!(joints[3].distance(target) < 1.0e-2);
+        assert_eq!(joints[0], Vec3::zero());
+        for pair in joints.windows(2) {
+            assert!((pair[0].distance(pair[1]) - 1.0).abs() < 1.0e-3);
+        }
+
+        let mut joints = vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)];
+        solve_fabrik(&mut joints, Vec3::new(0.0, 5.0, 0.0), None, 10, 1.0e-4);
+        assert!(joints[1].distance(Vec3::new(0.0, 1.0, 0.0)) < 1.0e-4);
+    }
+
+    #[test]
+    fn test_ik_rig_stable_pose() {
+        let chain = Chain(RefCell::new(vec![
+            ("root", 0.0, 0.0),
+            ("middle", 0.0, 0.0),
+            ("end", 0.0, 0.0),
+        ]));
+        let rig = IkRig::default()
+            .constraint(
+                "arm",
+                IkTwoBone::new("root", "middle", "end")
+                    .target(Vec3::new(0.5, 1.0, 0.0))
+                    .weight(0.5),
+            )
+            .constraint(
+                "reach",
+                IkLookAt::new("root")
+                    .target(Vec3::new(-5.0, 5.0, 0.0))
+                    .weight(0.5),
+            );
+        rig.reset(&chain);
+        rig.solve(&chain);
+        let first = chain.0.borrow().clone();
+        rig.reset(&chain);
+        rig.solve(&chain);
+        assert_eq!(*chain.0.borrow(), first);
+        assert!(first[0].2.abs() > 1.0e-3);
+    }
+}
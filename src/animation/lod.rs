@@ -0,0 +1,191 @@
+use crate::{context::GameContext, game::GameSubsystem};
+use spitfire_glow::graphics::Camera;
+use std::any::Any;
+use vek::{Mat4, Rect, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodLevel {
+    /// Minimal height of object relative to camera view height.
+    pub min_screen_size: f32,
+    /// Maximal distance of object center to camera center.
+    pub max_distance: f32,
+    /// Seconds between animation refreshes.
+    pub refresh_delay: f32,
+}
+
+impl LodLevel {
+    pub fn new(refresh_delay: f32) -> Self {
+        Self {
+            min_screen_size: 0.0,
+            max_distance: f32::INFINITY,
+            refresh_delay,
+        }
+    }
+
+    pub fn min_screen_size(mut self, value: f32) -> Self {
+        self.min_screen_size = value;
+        self
+    }
+
+    pub fn max_distance(mut self, value: f32) -> Self {
+        self.max_distance = value;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodMetrics {
+    pub screen_size: f32,
+    pub distance: f32,
+    pub visible: bool,
+}
+
+impl LodMetrics {
+    /// Measures world space bounds against camera view.
+    pub fn from_bounds(bounds: Rect<f32, f32>, camera: &Camera) -> Self {
+        let view = camera.world_rectangle();
+        let view_height = view.h.abs().max(f32::EPSILON);
+        let center = Vec2::new(bounds.x + bounds.w * 0.5, bounds.y + bounds.h * 0.5);
+        let view_center = Vec2::new(view.x + view.w * 0.5, view.y + view.h * 0.5);
+        Self {
+            screen_size: bounds.h.abs() / view_height,
+            distance: center.distance(view_center),
+            visible: view.collides_with_rect(bounds),
+        }
+    }
+
+    /// Measures local space bounds transformed into world space against camera view.
+    pub fn from_local_bounds(bounds: Rect<f32, f32>, matrix: Mat4<f32>, camera: &Camera) -> Self {
+        let corners = [
+            matrix.mul_point(Vec2::new(bounds.x, bounds.y)),
+            matrix.mul_point(Vec2::new(bounds.x + bounds.w, bounds.y)),
+            matrix.mul_point(Vec2::new(bounds.x + bounds.w, bounds.y + bounds.h)),
+            matrix.mul_point(Vec2::new(bounds.x, bounds.y + bounds.h)),
+        ];
+        let min = corners.into_iter().reduce(Vec2::partial_min).unwrap();
+        let max = corners.into_iter().reduce(Vec2::partial_max).unwrap();
+        Self::from_bounds(
+            Rect::new(min.x, min.y, max.x - min.x, max.y - min.y),
+            camera,
+        )
+    }
+}
+
+/// Selects LOD level and refresh rate from object metrics.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LodPolicy {
+    /// Levels from most to least detailed.
+    pub levels: Vec<LodLevel>,
+    /// Refresh delay for objects outside of camera view, `None` stops refreshing.
+    pub hidden_refresh_delay: Option<f32>,
+}
+
+impl LodPolicy {
+    pub fn level(mut self, level: LodLevel) -> Self {
+        self.levels.push(level);
+        self
+    }
+
+    pub fn hidden_refresh_delay(mut self, value: Option<f32>) -> Self {
+        self.hidden_refresh_delay = value;
+        self
+    }
+
+    /// Returns (LOD index, refresh delay), or `None` when object should not refresh.
+    pub fn select(&self, metrics: LodMetrics) -> Option<(usize, f32)> {
+        let last = self.levels.len().saturating_sub(1);
+        if !metrics.visible {
+            return self.hidden_refresh_delay.map(|delay| (last, delay));
+        }
+        self.levels
+            .iter()
+            .position(|level| {
+                metrics.screen_size >= level.min_screen_size
+                    && metrics.distance <= level.max_distance
+            })
+            .or_else(|| (!self.levels.is_empty()).then_some(last))
+            .map(|index| (index, self.levels[index].refresh_delay))
+    }
+}
+
+/// Shared per-frame limit of animation refreshes across all LOD driven objects.
+#[derive(Debug, Default)]
+pub struct LodBudgetSubsystem {
+    pub refreshes_per_frame: Option<usize>,
+    used: usize,
+}
+
+impl LodBudgetSubsystem {
+    pub fn new(refreshes_per_frame: usize) -> Self {
+        Self {
+            refreshes_per_frame: Some(refreshes_per_frame),
+            used: 0,
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn try_acquire(&mut self) -> bool {
+        if self
+            .refreshes_per_frame
+            .map(|limit| self.used < limit)
+            .unwrap_or(true)
+        {
+            self.used += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.used = 0;
+    }
+}
+
+impl GameSubsystem for LodBudgetSubsystem {
+    fn update(&mut self, _: GameContext, _: f32) {
+        self.reset();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lod_policy() {
+        let policy = LodPolicy::default()
+            .level(LodLevel::new(0.0).min_screen_size(0.5))
+            .level(LodLevel::new(0.1).max_distance(100.0))
+            .level(LodLevel::new(0.5));
+        let metrics = |screen_size, distance, visible| LodMetrics {
+            screen_size,
+            distance,
+            visible,
+        };
+        assert_eq!(policy.select(metrics(0.6, 500.0, true)), Some((0, 0.0)));
+        assert_eq!(policy.select(metrics(0.2, 50.0, true)), Some((1, 0.1)));
+        assert_eq!(policy.select(metrics(0.2, 500.0, true)), Some((2, 0.5)));
+        assert_eq!(policy.select(metrics(0.6, 0.0, false)), None);
+        let policy = policy.hidden_refresh_delay(Some(1.0));
+        assert_eq!(policy.select(metrics(0.6, 0.0, false)), Some((2, 1.0)));
+
+        let mut budget = LodBudgetSubsystem::new(2);
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+        budget.reset();
+        assert!(budget.try_acquire());
+    }
+}
@@ -1,3 +1,6 @@
 pub mod frame;
 pub mod gltf;
+pub mod ik;
+pub mod lod;
 pub mod spine;
+pub mod tween;
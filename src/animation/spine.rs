@@ -1,6 +1,14 @@
-use crate::{assets::spine::SpineAsset, context::GameContext, game::GameObject};
+use crate::{
+    animation::{
+        ik::IkRig,
+        lod::{LodBudgetSubsystem, LodMetrics, LodPolicy},
+    },
+    assets::spine::SpineAsset,
+    context::GameContext,
+    game::GameObject,
+};
 use rusty_spine::{
-    AnimationEvent, AnimationStateData, BlendMode, Physics,
+    AnimationEvent, AnimationStateData, BlendMode, Color, Physics,
     controller::{SkeletonCombinedRenderable, SkeletonController},
 };
 use spitfire_core::Triangle;
@@ -22,7 +30,7 @@ use std::{
         mpsc::{Receiver, channel},
     },
 };
-use vek::{Mat4, Vec2};
+use vek::{Mat4, Rect, Rgba, Vec2};
 
 pub enum SpineEvent {
     Start,
@@ -54,6 +62,8 @@ pub enum SpineEvent {
 pub struct SpineSkeleton {
     pub shader: Option<ShaderRef>,
     pub uniforms: HashMap<Cow<'static, str>, GlowUniformValue>,
+    /// Reset before and solved after every `update` call.
+    pub ik_rig: Option<IkRig>,
     textures: HashMap<String, SpriteTexture>,
     controller: RwLock<SkeletonController>,
     animation_events: Receiver<SpineEvent>,
@@ -114,6 +124,7 @@ impl SpineSkeleton {
         Self {
             shader: None,
             uniforms: Default::default(),
+            ik_rig: None,
             textures,
             controller: RwLock::new(controller),
             animation_events: receiver,
@@ -130,6 +141,11 @@ impl SpineSkeleton {
         self
     }
 
+    pub fn ik_rig(mut self, value: IkRig) -> Self {
+        self.ik_rig = Some(value);
+        self
+    }
+
     pub fn read(&'_ self) -> Option<RwLockReadGuard<'_, SkeletonController>> {
         self.controller.try_read().ok()
     }
@@ -192,9 +208,15 @@ impl SpineSkeleton {
     }
 
     pub fn update(&self, delta_time: f32) {
+        if let Some(rig) = &self.ik_rig {
+            rig.reset(self);
+        }
         if let Ok(mut controller) = self.controller.try_write() {
             controller.update(delta_time, Physics::Update);
         }
+        if let Some(rig) = &self.ik_rig {
+            rig.solve(self);
+        }
     }
 
     pub fn bone_names(&self) -> HashSet<String> {
@@ -307,6 +329,162 @@ impl SpineSkeleton {
             .map(|matrix| matrix.inverted())
     }
 
+    pub fn skin_names(&self) -> Vec<String> {
+        if let Ok(controller) = self.controller.try_read() {
+            controller
+                .skeleton
+                .data()
+                .skins()
+                .map(|skin| skin.name().to_owned())
+                .collect()
+        } else {
+            Default::default()
+        }
+    }
+
+    pub fn slot_names(&self) -> Vec<String> {
+        if let Ok(controller) = self.controller.try_read() {
+            controller
+                .skeleton
+                .slots()
+                .map(|slot| slot.data().name().to_owned())
+                .collect()
+        } else {
+            Default::default()
+        }
+    }
+
+    /// Names of attachments that skin provides for given slot.
+    pub fn skin_attachment_names(&self, skin: &str, slot: &str) -> Vec<String> {
+        let Ok(controller) = self.controller.try_read() else {
+            return Default::default();
+        };
+        let data = controller.skeleton.data();
+        let (Some(skin), Some(slot_index)) = (
+            data.find_skin(skin),
+            data.slots().position(|item| item.name() == slot),
+        ) else {
+            return Default::default();
+        };
+        skin.attachments()
+            .into_iter()
+            .filter(|entry| entry.slot_index as usize == slot_index)
+            .map(|entry| entry.attachment.name().to_owned())
+            .collect()
+    }
+
+    pub fn attachment_name(&self, slot: &str) -> Option<String> {
+        let controller = self.controller.try_read().ok()?;
+        let slot = controller.skeleton.find_slot(slot)?;
+        let attachment = slot.attachment()?;
+        Some(attachment.name().to_owned())
+    }
+
+    /// Replaces skeleton skin with composition of given skins, in order.
+    /// Slots get reset to setup pose, so earlier `set_attachment` and
+    /// `set_slot_color` overrides have to be applied again.
+    pub fn set_skins(
+        &self,
+        skins: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut controller = self
+            .controller
+            .try_write()
+            .map_err(|_| "Spine skeleton is locked")?;
+        let skins = skins
+            .into_iter()
+            .map(|skin| skin.as_ref().to_owned())
+            .collect::<Vec<_>>();
+        controller
+            .skeleton
+            .set_skins_by_name(&skins.join("+"), skins.iter().map(|skin| skin.as_str()))?;
+        controller.skeleton.set_slots_to_setup_pose();
+        controller.skeleton.update_world_transform(Physics::None);
+        Ok(())
+    }
+
+    /// Checks that all skins exist, without changing skeleton.
+    pub fn check_skins(
+        &self,
+        skins: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let controller = self
+            .controller
+            .try_read()
+            .map_err(|_| "Spine skeleton is locked")?;
+        let data = controller.skeleton.data();
+        for skin in skins {
+            let skin = skin.as_ref();
+            if data.find_skin(skin).is_none() {
+                return Err(format!("Spine skin not found: {skin}").into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_skin(&self, skin: &str) -> Result<(), Box<dyn Error>> {
+        self.set_skins([skin])
+    }
+
+    /// Swaps slot attachment with one found in current or default skin,
+    /// `None` clears slot attachment.
+    pub fn set_attachment(
+        &self,
+        slot: &str,
+        attachment: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut controller = self
+            .controller
+            .try_write()
+            .map_err(|_| "Spine skeleton is locked")?;
+        if !controller.skeleton.set_attachment(slot, attachment) {
+            return Err(
+                format!("Spine attachment {attachment:?} not found for slot: {slot}").into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that attachment can be set to slot, without changing skeleton.
+    pub fn check_attachment(
+        &self,
+        slot: &str,
+        attachment: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut controller = self
+            .controller
+            .try_write()
+            .map_err(|_| "Spine skeleton is locked")?;
+        let found = match attachment {
+            Some(attachment) => controller
+                .skeleton
+                .get_attachment_for_slot_name(slot, attachment)
+                .is_some(),
+            None => controller.skeleton.find_slot(slot).is_some(),
+        };
+        if !found {
+            return Err(
+                format!("Spine attachment {attachment:?} not found for slot: {slot}").into(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn slot_color(&self, slot: &str) -> Option<Rgba<f32>> {
+        let controller = self.controller.try_read().ok()?;
+        let slot = controller.skeleton.find_slot(slot)?;
+        let color = slot.color();
+        Some(Rgba::new(color.r, color.g, color.b, color.a))
+    }
+
+    pub fn set_slot_color(&self, slot: &str, color: Rgba<f32>) {
+        if let Ok(mut controller) = self.controller.try_write()
+            && let Some(mut slot) = controller.skeleton.find_slot_mut(slot)
+        {
+            *slot.color_mut() = Color::from(color.into_array());
+        }
+    }
+
     fn draw_renderables(
         &self,
         renderables: &[SkeletonCombinedRenderable],
@@ -417,6 +595,10 @@ pub struct BudgetedSpineSkeletonLodSwitchStrategy {
 #[derive(Debug, Default)]
 pub struct BudgetedSpineSkeleton {
     pub lod_switch_strategy: BudgetedSpineSkeletonLodSwitchStrategy,
+    /// When set, LOD and refresh rate are chosen automatically while drawing.
+    pub lod_policy: Option<LodPolicy>,
+    lod_refresh_delay: Option<f32>,
+    lod_suspended: bool,
     refresh_timer: f32,
     lod: usize,
     lods: Vec<LodSpineSkeleton>,
@@ -427,6 +609,9 @@ impl BudgetedSpineSkeleton {
     pub fn new(lods: impl IntoIterator<Item = LodSpineSkeleton>) -> Self {
         Self {
             lod_switch_strategy: Default::default(),
+            lod_policy: None,
+            lod_refresh_delay: None,
+            lod_suspended: false,
             lod: 0,
             lods: lods.into_iter().collect(),
             refresh_timer: 0.0,
@@ -439,6 +624,11 @@ impl BudgetedSpineSkeleton {
         self
     }
 
+    pub fn lod_policy(mut self, value: LodPolicy) -> Self {
+        self.lod_policy = Some(value);
+        self
+    }
+
     pub fn with_lod(mut self, lod: LodSpineSkeleton) -> Self {
         self.lods.push(lod);
         self
@@ -543,6 +733,48 @@ impl BudgetedSpineSkeleton {
         self.refresh_timer = 0.0;
     }
 
+    pub fn set_skins(
+        &self,
+        skins: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let skins = skins
+            .into_iter()
+            .map(|skin| skin.as_ref().to_owned())
+            .collect::<Vec<_>>();
+        // Validate all LODs first, so they are not left half-updated.
+        for lod in &self.lods {
+            lod.skeleton.check_skins(&skins)?;
+        }
+        for lod in &self.lods {
+            lod.skeleton.set_skins(&skins)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_skin(&self, skin: &str) -> Result<(), Box<dyn Error>> {
+        self.set_skins([skin])
+    }
+
+    pub fn set_attachment(
+        &self,
+        slot: &str,
+        attachment: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        for lod in &self.lods {
+            lod.skeleton.check_attachment(slot, attachment)?;
+        }
+        for lod in &self.lods {
+            lod.skeleton.set_attachment(slot, attachment)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_slot_color(&self, slot: &str, color: Rgba<f32>) {
+        for lod in &self.lods {
+            lod.skeleton.set_slot_color(slot, color);
+        }
+    }
+
     pub fn lod_skeleton(&self) -> Option<&LodSpineSkeleton> {
         self.lods.get(self.lod)
     }
@@ -551,12 +783,61 @@ impl BudgetedSpineSkeleton {
         self.lods.get_mut(self.lod)
     }
 
+    /// Chooses LOD and refresh rate using LOD policy.
+    pub fn apply_lod_metrics(&mut self, metrics: LodMetrics) {
+        let Some(policy) = self.lod_policy.as_ref() else {
+            return;
+        };
+        if let Some((lod, refresh_delay)) = policy.select(metrics) {
+            self.set_lod(lod.min(self.lods.len().saturating_sub(1)));
+            self.lod_refresh_delay = Some(refresh_delay);
+            self.lod_suspended = false;
+        } else {
+            self.lod_suspended = true;
+        }
+    }
+
+    /// Bounds of last refreshed renderables, in skeleton space.
+    pub fn bounds(&self) -> Option<Rect<f32, f32>> {
+        let mut points = self.cached_renderables.iter().flat_map(|renderable| {
+            renderable
+                .vertices
+                .iter()
+                .map(|position| Vec2::new(position[0], -position[1]))
+        });
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), point| {
+            (Vec2::partial_min(min, point), Vec2::partial_max(max, point))
+        });
+        Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+    }
+
     pub fn try_refresh(&mut self, delta_time: f32) -> bool {
+        self.try_refresh_budgeted(delta_time, None)
+    }
+
+    /// Refreshes only if shared budget allows it, otherwise refresh gets postponed.
+    pub fn try_refresh_budgeted(
+        &mut self,
+        delta_time: f32,
+        budget: Option<&mut LodBudgetSubsystem>,
+    ) -> bool {
         let Some(lod) = self.lods.get_mut(self.lod) else {
             return false;
         };
+        if self.lod_suspended {
+            // Hidden period is skipped, not replayed at once on resume.
+            self.refresh_timer = 0.0;
+            return false;
+        }
         self.refresh_timer += delta_time;
-        if self.refresh_timer >= lod.refresh_delay {
+        let refresh_delay = self.lod_refresh_delay.unwrap_or(lod.refresh_delay);
+        if self.refresh_timer >= refresh_delay {
+            if let Some(budget) = budget
+                && !budget.try_acquire()
+            {
+                return false;
+            }
             if let Some(lod) = self.lod_skeleton() {
                 lod.skeleton.update(self.refresh_timer);
                 let renderables = lod
@@ -584,7 +865,23 @@ impl Drawable for BudgetedSpineSkeleton {
 }
 
 impl GameObject for BudgetedSpineSkeleton {
+    fn process(&mut self, context: &mut GameContext, delta_time: f32) {
+        if self.lod_policy.is_some() {
+            let budget = context.subsystems.get_mut::<LodBudgetSubsystem>();
+            self.try_refresh_budgeted(delta_time, budget);
+        }
+    }
+
     fn draw(&mut self, context: &mut GameContext) {
+        if self.lod_policy.is_some()
+            && let Some(bounds) = self.bounds()
+        {
+            self.apply_lod_metrics(LodMetrics::from_local_bounds(
+                bounds,
+                context.draw.top_transform(),
+                &context.graphics.state.main_camera,
+            ));
+        }
         let this: &mut dyn Drawable = self;
         this.draw(context.draw, context.graphics);
     }
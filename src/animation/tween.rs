@@ -0,0 +1,570 @@
+use crate::{context::GameContext, game::GameSubsystem, gc::Gc};
+use anim8::phase::{Ease, Phase};
+use std::any::Any;
+use typid::ID;
+use vek::{Quaternion, Rgba, Transform, Vec2, Vec3, Vec4};
+
+pub type TweenId = ID<Box<dyn TweenNode>>;
+
+pub trait Tweenable: Clone + Send + Sync + 'static {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        from + (to - from) * factor
+    }
+}
+
+impl<const N: usize> Tweenable for [f32; N] {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        std::array::from_fn(|index| from[index] + (to[index] - from[index]) * factor)
+    }
+}
+
+impl Tweenable for Vec2<f32> {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        *from + (*to - *from) * factor
+    }
+}
+
+impl Tweenable for Vec3<f32> {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        *from + (*to - *from) * factor
+    }
+}
+
+impl Tweenable for Vec4<f32> {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        *from + (*to - *from) * factor
+    }
+}
+
+impl Tweenable for Rgba<f32> {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        *from + (*to - *from) * factor
+    }
+}
+
+impl Tweenable for Quaternion<f32> {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        let to = if from.dot(*to) < 0.0 { -*to } else { *to };
+        Quaternion::slerp_unclamped(*from, to, factor).normalized()
+    }
+}
+
+impl Tweenable for Transform<f32, f32, f32> {
+    fn tween(from: &Self, to: &Self, factor: f32) -> Self {
+        Self {
+            position: Tweenable::tween(&from.position, &to.position, factor),
+            orientation: Tweenable::tween(&from.orientation, &to.orientation, factor),
+            scale: Tweenable::tween(&from.scale, &to.scale, factor),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub enum TweenEasing {
+    #[default]
+    Linear,
+    /// Phase mapping normalized time to normalized factor.
+    Phase(Phase),
+    Function(fn(f32) -> f32),
+}
+
+impl TweenEasing {
+    pub fn ease(ease: Ease) -> Self {
+        Phase::ease(ease, 0.0..1.0, 0.0..1.0)
+            .map(Self::Phase)
+            .unwrap_or_default()
+    }
+
+    pub fn sample(&self, factor: f32) -> f32 {
+        let factor = factor.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => factor,
+            Self::Phase(phase) => phase.sample(factor),
+            Self::Function(function) => function(factor),
+        }
+    }
+}
+
+impl From<Ease> for TweenEasing {
+    fn from(value: Ease) -> Self {
+        Self::ease(value)
+    }
+}
+
+pub trait TweenNode: Send + Sync {
+    /// Advances tween by given time and returns time left unused after it
+    /// has completed.
+    fn advance(&mut self, delta_time: f32) -> f32;
+
+    fn is_completed(&self) -> bool;
+
+    fn reset(&mut self);
+}
+
+#[allow(clippy::type_complexity)]
+pub struct Tween<T: Tweenable> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    pub easing: TweenEasing,
+    pub looping: bool,
+    /// Number of cycles to play when looping, or infinite if `None`.
+    pub loop_count: Option<usize>,
+    /// Every other cycle plays backward.
+    pub yoyo: bool,
+    time: f32,
+    completed_cycles: usize,
+    completed: bool,
+    setter: Box<dyn FnMut(T) + Send + Sync>,
+    on_complete: Option<Box<dyn FnMut() + Send + Sync>>,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(
+        from: T,
+        to: T,
+        duration: f32,
+        setter: impl FnMut(T) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: Default::default(),
+            looping: false,
+            loop_count: None,
+            yoyo: false,
+            time: 0.0,
+            completed_cycles: 0,
+            completed: false,
+            setter: Box::new(setter),
+            on_complete: None,
+        }
+    }
+
+    /// Tweens property of shared object, like position of `Gc<Transformed<T>>`.
+    pub fn property<O: 'static>(
+        from: T,
+        to: T,
+        duration: f32,
+        mut object: Gc<O>,
+        property: fn(&mut O) -> &mut T,
+    ) -> Self {
+        Self::new(from, to, duration, move |value| {
+            *property(&mut object.write()) = value;
+        })
+    }
+
+    pub fn easing(mut self, easing: impl Into<TweenEasing>) -> Self {
+        self.easing = easing.into();
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn loops(mut self, count: usize) -> Self {
+        self.looping = true;
+        self.loop_count = Some(count);
+        self
+    }
+
+    pub fn yoyo(mut self) -> Self {
+        self.yoyo = true;
+        self
+    }
+
+    pub fn on_complete(mut self, f: impl FnMut() + Send + Sync + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn completed_cycles(&self) -> usize {
+        self.completed_cycles
+    }
+
+    pub fn value(&self) -> T {
+        let factor = if self.duration > f32::EPSILON {
+            self.time / self.duration
+        } else {
+            1.0
+        };
+        let factor = if self.yoyo && self.completed_cycles % 2 == 1 {
+            1.0 - factor
+        } else {
+            factor
+        };
+        T::tween(&self.from, &self.to, self.easing.sample(factor))
+    }
+}
+
+impl<T: Tweenable> TweenNode for Tween<T> {
+    fn advance(&mut self, delta_time: f32) -> f32 {
+        if self.completed {
+            return delta_time;
+        }
+        let mut left = delta_time.max(0.0);
+        loop {
+            let remaining = self.duration - self.time;
+            if left < remaining {
+                self.time += left;
+                let value = self.value();
+                (self.setter)(value);
+                return 0.0;
+            }
+            left -= remaining.max(0.0);
+            self.time = self.duration;
+            let value = self.value();
+            (self.setter)(value);
+            self.completed_cycles += 1;
+            if !self.looping
+                || self
+                    .loop_count
+                    .map(|count| self.completed_cycles >= count)
+                    .unwrap_or_default()
+            {
+                self.completed = true;
+                if let Some(on_complete) = self.on_complete.as_mut() {
+                    on_complete();
+                }
+                return left;
+            }
+            self.time = 0.0;
+            if self.duration <= f32::EPSILON {
+                return 0.0;
+            }
+        }
+    }
+
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn reset(&mut self) {
+        self.time = 0.0;
+        self.completed_cycles = 0;
+        self.completed = false;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TweenDelay {
+    pub duration: f32,
+    time: f32,
+}
+
+impl TweenDelay {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            time: 0.0,
+        }
+    }
+}
+
+impl TweenNode for TweenDelay {
+    fn advance(&mut self, delta_time: f32) -> f32 {
+        let remaining = (self.duration - self.time).max(0.0);
+        if delta_time < remaining {
+            self.time += delta_time;
+            0.0
+        } else {
+            self.time = self.duration;
+            delta_time - remaining
+        }
+    }
+
+    fn is_completed(&self) -> bool {
+        self.time >= self.duration
+    }
+
+    fn reset(&mut self) {
+        self.time = 0.0;
+    }
+}
+
+pub struct TweenCallback {
+    callback: Box<dyn FnMut() + Send + Sync>,
+    called: bool,
+}
+
+impl TweenCallback {
+    pub fn new(f: impl FnMut() + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Box::new(f),
+            called: false,
+        }
+    }
+}
+
+impl TweenNode for TweenCallback {
+    fn advance(&mut self, delta_time: f32) -> f32 {
+        if !self.called {
+            self.called = true;
+            (self.callback)();
+        }
+        delta_time
+    }
+
+    fn is_completed(&self) -> bool {
+        self.called
+    }
+
+    fn reset(&mut self) {
+        self.called = false;
+    }
+}
+
+/// Plays nodes one after another.
+#[derive(Default)]
+pub struct TweenSequence {
+    pub nodes: Vec<Box<dyn TweenNode>>,
+    current: usize,
+}
+
+impl TweenSequence {
+    pub fn node(mut self, node: impl TweenNode + 'static) -> Self {
+        self.nodes.push(Box::new(node));
+        self
+    }
+
+    pub fn delay(self, duration: f32) -> Self {
+        self.node(TweenDelay::new(duration))
+    }
+
+    pub fn callback(self, f: impl FnMut() + Send + Sync + 'static) -> Self {
+        self.node(TweenCallback::new(f))
+    }
+}
+
+impl TweenNode for TweenSequence {
+    fn advance(&mut self, mut delta_time: f32) -> f32 {
+        while let Some(node) = self.nodes.get_mut(self.current) {
+            delta_time = node.advance(delta_time);
+            if !node.is_completed() {
+                return 0.0;
+            }
+            self.current += 1;
+        }
+        delta_time
+    }
+
+    fn is_completed(&self) -> bool {
+        self.current >= self.nodes.len()
+    }
+
+    fn reset(&mut self) {
+        self.current = 0;
+        for node in &mut self.nodes {
+            node.reset();
+        }
+    }
+}
+
+/// Plays nodes at the same time, completes when all of them complete.
+#[derive(Default)]
+pub struct TweenParallel {
+    pub nodes: Vec<Box<dyn TweenNode>>,
+}
+
+impl TweenParallel {
+    pub fn node(mut self, node: impl TweenNode + 'static) -> Self {
+        self.nodes.push(Box::new(node));
+        self
+    }
+}
+
+impl TweenNode for TweenParallel {
+    fn advance(&mut self, delta_time: f32) -> f32 {
+        let mut left = delta_time;
+        for node in &mut self.nodes {
+            if !node.is_completed() {
+                left = left.min(node.advance(delta_time));
+            }
+        }
+        left
+    }
+
+    fn is_completed(&self) -> bool {
+        self.nodes.iter().all(|node| node.is_completed())
+    }
+
+    fn reset(&mut self) {
+        for node in &mut self.nodes {
+            node.reset();
+        }
+    }
+}
+
+/// Replays node given number of times, or infinitely if `None`.
+pub struct TweenRepeat {
+    pub node: Box<dyn TweenNode>,
+    pub loop_count: Option<usize>,
+    completed_cycles: usize,
+}
+
+impl TweenRepeat {
+    pub fn new(node: impl TweenNode + 'static, loop_count: Option<usize>) -> Self {
+        Self {
+            node: Box::new(node),
+            loop_count,
+            completed_cycles: 0,
+        }
+    }
+}
+
+impl TweenNode for TweenRepeat {
+    fn advance(&mut self, mut delta_time: f32) -> f32 {
+        while !self.is_completed() {
+            let left = self.node.advance(delta_time);
+            if !self.node.is_completed() {
+                return 0.0;
+            }
+            self.completed_cycles += 1;
+            if self.is_completed() {
+                return left;
+            }
+            self.node.reset();
+            // Prevents spinning forever on zero-length nodes.
+            if left >= delta_time {
+                return 0.0;
+            }
+            delta_time = left;
+        }
+        delta_time
+    }
+
+    fn is_completed(&self) -> bool {
+        self.loop_count
+            .map(|count| self.completed_cycles >= count)
+            .unwrap_or_default()
+    }
+
+    fn reset(&mut self) {
+        self.completed_cycles = 0;
+        self.node.reset();
+    }
+}
+
+/// Advances registered tweens every frame and drops completed ones.
+#[derive(Default)]
+pub struct TweenSubsystem {
+    tweens: Vec<(TweenId, Box<dyn TweenNode>)>,
+    pub paused: bool,
+    pub time_scale: Option<f32>,
+}
+
+impl TweenSubsystem {
+    pub fn add(&mut self, node: impl TweenNode + 'static) -> TweenId {
+        let id = TweenId::new();
+        self.tweens.push((id, Box::new(node)));
+        id
+    }
+
+    pub fn remove(&mut self, id: TweenId) -> bool {
+        let count = self.tweens.len();
+        self.tweens.retain(|(tween_id, _)| *tween_id != id);
+        self.tweens.len() != count
+    }
+
+    pub fn contains(&self, id: TweenId) -> bool {
+        self.tweens.iter().any(|(tween_id, _)| *tween_id == id)
+    }
+
+    pub fn clear(&mut self) {
+        self.tweens.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.tweens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tweens.is_empty()
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        for (_, node) in &mut self.tweens {
+            node.advance(delta_time);
+        }
+        self.tweens.retain(|(_, node)| !node.is_completed());
+    }
+}
+
+impl GameSubsystem for TweenSubsystem {
+    fn update(&mut self, _: GameContext, delta_time: f32) {
+        if !self.paused {
+            self.advance(delta_time * self.time_scale.unwrap_or(1.0));
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[test]
+    fn test_tween() {
+        let value = Gc::new(0.0f32);
+        let mut tween = Tween::property(0.0, 10.0, 1.0, value.reference(), |value| value)
+            .yoyo()
+            .loops(2);
+        assert_eq!(tween.advance(0.5), 0.0);
+        assert_eq!(*value.read(), 5.0);
+        assert_eq!(tween.advance(0.75), 0.0);
+        assert_eq!(*value.read(), 7.5);
+        assert_eq!(tween.advance(1.0), 0.25);
+        assert_eq!(*value.read(), 0.0);
+        assert!(tween.is_completed());
+
+        let completed = Arc::new(AtomicUsize::new(0));
+        let completed2 = completed.clone();
+        let mut sequence = TweenParallel::default().node(TweenDelay::new(2.0)).node(
+            TweenSequence::default()
+                .node(Tween::property(0.0, 1.0, 1.0, value.reference(), |value| {
+                    value
+                }))
+                .delay(0.5)
+                .callback(move || {
+                    completed2.fetch_add(1, Ordering::SeqCst);
+                }),
+        );
+        sequence.advance(1.25);
+        assert_eq!(*value.read(), 1.0);
+        assert_eq!(completed.load(Ordering::SeqCst), 0);
+        assert!(!sequence.is_completed());
+        sequence.advance(0.5);
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+        assert!(!sequence.is_completed());
+        assert_eq!(sequence.advance(0.5), 0.25);
+        assert!(sequence.is_completed());
+
+        let mut repeat = TweenRepeat::new(TweenDelay::new(1.0), Some(3));
+        assert_eq!(repeat.advance(2.5), 0.0);
+        assert!(!repeat.is_completed());
+        assert_eq!(repeat.advance(1.0), 0.5);
+        assert!(repeat.is_completed());
+    }
+}
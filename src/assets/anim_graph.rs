@@ -0,0 +1,425 @@
+use crate::animation::gltf::{
+    GltfAnimationBlendSpace, GltfAnimationBlendSpacePoint, GltfAnimationMixer,
+    GltfAnimationMixerLayer, GltfAnimationNode, GltfAnimationParameter, GltfAnimationTarget,
+    GltfAnimationTransition, GltfAnimationTransitionController, GltfAnimationTransitionLayer,
+    GltfAnimationWeight, GltfSceneAnimation, GltfSceneInstance,
+};
+use anput::bundle::DynamicBundle;
+use keket::{
+    database::{AssetDatabase, handle::AssetHandle, path::AssetPathStatic},
+    protocol::future::{FutureAssetProtocol, FutureStorageAccess},
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashMap, error::Error};
+
+/// Animation graph description, instantiated onto GLTF scene instance.
+///
+/// Referenced `gltf-anim://` assets have to be loaded before instantiation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GltfAnimationGraphAsset {
+    #[serde(default)]
+    pub blend_only_affected_animations: Option<bool>,
+    /// {name: animation}
+    #[serde(default)]
+    pub animations: HashMap<String, GltfAnimationGraphClip>,
+    /// {name: initial value}
+    #[serde(default)]
+    pub parameters: HashMap<String, f32>,
+    /// Named nodes that can be referenced from other nodes.
+    #[serde(default)]
+    pub nodes: HashMap<String, GltfAnimationGraphNode>,
+    pub root: GltfAnimationGraphNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GltfAnimationGraphClip {
+    pub animation: AssetPathStatic,
+    #[serde(default)]
+    pub looped: bool,
+    #[serde(default = "GltfAnimationGraphClip::default_playing")]
+    pub playing: bool,
+    #[serde(default = "GltfAnimationGraphClip::default_speed")]
+    pub speed: f32,
+    #[serde(default = "GltfAnimationGraphClip::default_weight")]
+    pub weight: f32,
+}
+
+impl GltfAnimationGraphClip {
+    fn default_playing() -> bool {
+        true
+    }
+
+    fn default_speed() -> f32 {
+        1.0
+    }
+
+    fn default_weight() -> f32 {
+        1.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GltfAnimationGraphWeight {
+    Fixed(f32),
+    Parameter(String),
+}
+
+impl Default for GltfAnimationGraphWeight {
+    fn default() -> Self {
+        Self::Fixed(1.0)
+    }
+}
+
+impl From<&GltfAnimationGraphWeight> for GltfAnimationWeight {
+    fn from(value: &GltfAnimationGraphWeight) -> Self {
+        match value {
+            GltfAnimationGraphWeight::Fixed(weight) => Self::Fixed(*weight),
+            GltfAnimationGraphWeight::Parameter(name) => Self::Parameter(name.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GltfAnimationGraphNode {
+    Empty,
+    Target {
+        animation: String,
+    },
+    Reference {
+        node: String,
+    },
+    Mixer {
+        layers: Vec<GltfAnimationGraphMixerLayer>,
+    },
+    Transition {
+        /// Name of controller used by gameplay code to change active layers.
+        controller: String,
+        #[serde(default)]
+        default_layer: Option<String>,
+        #[serde(default)]
+        change_speed: Option<f32>,
+        layers: Vec<GltfAnimationGraphTransitionLayer>,
+    },
+    BlendSpace {
+        parameters: Vec<String>,
+        points: Vec<GltfAnimationGraphBlendSpacePoint>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GltfAnimationGraphMixerLayer {
+    #[serde(default)]
+    pub weight: GltfAnimationGraphWeight,
+    pub node: GltfAnimationGraphNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GltfAnimationGraphTransitionLayer {
+    pub name: String,
+    #[serde(default)]
+    pub change_speed: Option<f32>,
+    pub node: GltfAnimationGraphNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GltfAnimationGraphBlendSpacePoint {
+    pub parameters: Vec<f32>,
+    pub node: GltfAnimationGraphNode,
+}
+
+/// Transition controllers created by graph instantiation, by their names.
+pub type GltfAnimationGraphControllers = HashMap<String, GltfAnimationTransitionController>;
+
+impl GltfAnimationGraphAsset {
+    pub fn from_json(content: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Adds graph animations and parameters to scene instance and sets its
+    /// animation node. Parameters already present in instance are kept.
+    pub fn instantiate(
+        &self,
+        instance: &mut GltfSceneInstance,
+        database: &AssetDatabase,
+    ) -> Result<GltfAnimationGraphControllers, Box<dyn Error>> {
+        let mut controllers = GltfAnimationGraphControllers::default();
+        let node = self.build_node(&mut controllers)?;
+        for (name, clip) in &self.animations {
+            let handle = database
+                .find(clip.animation.clone())
+                .ok_or_else(|| format!("Animation asset not found: {}", clip.animation))?;
+            let animation = GltfSceneAnimation::new(handle, database)
+                .ok_or_else(|| format!("Asset is not GLTF animation: {}", clip.animation))?
+                .looped(clip.looped)
+                .playing(clip.playing)
+                .speed(clip.speed)
+                .weight(clip.weight);
+            instance.add_animation(name, animation);
+        }
+        for (name, value) in &self.parameters {
+            if instance.parameter(name).is_none() {
+                instance.add_parameter(name, GltfAnimationParameter::new(*value));
+            }
+        }
+        if let Some(value) = self.blend_only_affected_animations {
+            instance.blend_only_affected_animations = value;
+        }
+        instance.set_animation_node(node);
+        Ok(controllers)
+    }
+
+    pub fn build_node(
+        &self,
+        controllers: &mut GltfAnimationGraphControllers,
+    ) -> Result<Box<dyn GltfAnimationNode>, Box<dyn Error>> {
+        self.build_node_inner(&self.root, controllers, &mut Vec::default())
+    }
+
+    fn build_node_inner<'a>(
+        &'a self,
+        node: &'a GltfAnimationGraphNode,
+        controllers: &mut GltfAnimationGraphControllers,
+        references: &mut Vec<&'a str>,
+    ) -> Result<Box<dyn GltfAnimationNode>, Box<dyn Error>> {
+        Ok(match node {
+            GltfAnimationGraphNode::Empty => Box::new(()),
+            GltfAnimationGraphNode::Target { animation } => {
+                if !self.animations.contains_key(animation) {
+                    return Err(format!("Animation graph target not found: {animation}").into());
+                }
+                Box::new(GltfAnimationTarget::new(animation))
+            }
+            GltfAnimationGraphNode::Reference { node } => {
+                if references.contains(&node.as_str()) {
+                    return Err(format!("Animation graph node references itself: {node}").into());
+                }
+                let target = self
+                    .nodes
+                    .get(node)
+                    .ok_or_else(|| format!("Animation graph node not found: {node}"))?;
+                references.push(node);
+                let result = self.build_node_inner(target, controllers, references)?;
+                references.pop();
+                result
+            }
+            GltfAnimationGraphNode::Mixer { layers } => {
+                let mut result = GltfAnimationMixer::default();
+                for layer in layers {
+                    result = result.layer(GltfAnimationMixerLayer::new(
+                        (&layer.weight).into(),
+                        self.build_node_inner(&layer.node, controllers, references)?,
+                    ));
+                }
+                Box::new(result)
+            }
+            GltfAnimationGraphNode::Transition {
+                controller,
+                default_layer,
+                change_speed,
+                layers,
+            } => {
+                let controller = controllers.entry(controller.to_owned()).or_default();
+                let mut result = GltfAnimationTransition::new(controller.clone());
+                if let Some(name) = default_layer {
+                    result = result.default_layer(name);
+                }
+                if let Some(speed) = change_speed {
+                    result = result.change_speed(*speed);
+                }
+                for layer in layers {
+                    let mut item = GltfAnimationTransitionLayer::new(
+                        &layer.name,
+                        self.build_node_inner(&layer.node, controllers, references)?,
+                    );
+                    if let Some(speed) = layer.change_speed {
+                        item = item.change_speed(speed);
+                    }
+                    result = result.layer(item);
+                }
+                Box::new(result)
+            }
+            GltfAnimationGraphNode::BlendSpace { parameters, points } => match parameters.len() {
+                1 => self.build_blend_space::<1>(parameters, points, controllers, references)?,
+                2 => self.build_blend_space::<2>(parameters, points, controllers, references)?,
+                3 => self.build_blend_space::<3>(parameters, points, controllers, references)?,
+                count => {
+                    return Err(format!(
+                        "Animation graph blend space supports 1 to 3 parameters, got: {count}"
+                    )
+                    .into());
+                }
+            },
+        })
+    }
+
+    fn build_blend_space<'a, const N: usize>(
+        &'a self,
+        parameters: &[String],
+        points: &'a [GltfAnimationGraphBlendSpacePoint],
+        controllers: &mut GltfAnimationGraphControllers,
+        references: &mut Vec<&'a str>,
+    ) -> Result<Box<dyn GltfAnimationNode>, Box<dyn Error>> {
+        let mut result = GltfAnimationBlendSpace::<N>::new(std::array::from_fn(|index| {
+            Cow::Owned(parameters[index].to_owned())
+        }));
+        for point in points {
+            let values = <[f32; N]>::try_from(point.parameters.as_slice()).map_err(|_| {
+                format!(
+                    "Animation graph blend space point expects {} parameters, got: {}",
+                    N,
+                    point.parameters.len()
+                )
+            })?;
+            result = result.point(GltfAnimationBlendSpacePoint::new(
+                values,
+                self.build_node_inner(&point.node, controllers, references)?,
+            ));
+        }
+        Ok(Box::new(result))
+    }
+}
+
+pub fn make_anim_graph_asset_protocol() -> FutureAssetProtocol {
+    FutureAssetProtocol::new("animgraph").process(process_bytes)
+}
+
+async fn process_bytes(
+    handle: AssetHandle,
+    access: FutureStorageAccess,
+    bytes: Vec<u8>,
+) -> Result<DynamicBundle, Box<dyn Error>> {
+    let path = access
+        .access()?
+        .read()
+        .unwrap()
+        .component::<true, AssetPathStatic>(handle.entity())?
+        .clone();
+    let content = std::str::from_utf8(&bytes)?;
+    let asset = if path.path().ends_with(".toml") {
+        GltfAnimationGraphAsset::from_toml(content)?
+    } else {
+        GltfAnimationGraphAsset::from_json(content)?
+    };
+    Ok(DynamicBundle::new(asset).ok().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anim_graph_format() {
+        let json = GltfAnimationGraphAsset::from_json(
+            r#"{
+                "animations": {
+                    "idle": { "animation": "gltf-anim://stickman.glb/TPose", "looped": true },
+                    "walk": { "animation": "gltf-anim://stickman.glb/Walk", "looped": true },
+                    "jump": { "animation": "gltf-anim://stickman.glb/Jump", "weight": 0.25 }
+                },
+                "parameters": { "move-x": 0.0 },
+                "nodes": {
+                    "locomotion": {
+                        "type": "blend_space",
+                        "parameters": ["move-x"],
+                        "points": [
+                            { "parameters": [0.0], "node": { "type": "target", "animation": "idle" } },
+                            { "parameters": [1.0], "node": { "type": "target", "animation": "walk" } }
+                        ]
+                    }
+                },
+                "root": {
+                    "type": "transition",
+                    "controller": "main",
+                    "default_layer": "ground",
+                    "change_speed": 5.0,
+                    "layers": [
+                        { "name": "ground", "node": { "type": "reference", "node": "locomotion" } },
+                        {
+                            "name": "air",
+                            "node": {
+                                "type": "mixer",
+                                "layers": [
+                                    { "weight": "air-weight", "node": { "type": "target", "animation": "jump" } },
+                                    { "weight": 0.5, "node": { "type": "empty" } }
+                                ]
+                            }
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let toml = GltfAnimationGraphAsset::from_toml(
+            r#"
+            [animations.idle]
+            animation = "gltf-anim://stickman.glb/TPose"
+            looped = true
+
+            [animations.walk]
+            animation = "gltf-anim://stickman.glb/Walk"
+            looped = true
+
+            [animations.jump]
+            animation = "gltf-anim://stickman.glb/Jump"
+            weight = 0.25
+
+            [parameters]
+            move-x = 0.0
+
+            [nodes.locomotion]
+            type = "blend_space"
+            parameters = ["move-x"]
+            points = [
+                { parameters = [0.0], node = { type = "target", animation = "idle" } },
+                { parameters = [1.0], node = { type = "target", animation = "walk" } },
+            ]
+
+            [root]
+            type = "transition"
+            controller = "main"
+            default_layer = "ground"
+            change_speed = 5.0
+            layers = [
+                { name = "ground", node = { type = "reference", node = "locomotion" } },
+                { name = "air", node = { type = "mixer", layers = [
+                    { weight = "air-weight", node = { type = "target", animation = "jump" } },
+                    { weight = 0.5, node = { type = "empty" } },
+                ] } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(json, toml);
+        assert!(json.animations["idle"].playing);
+        assert_eq!(json.animations["idle"].speed, 1.0);
+        assert_eq!(json.animations["idle"].weight, 1.0);
+        assert_eq!(json.animations["jump"].weight, 0.25);
+
+        let mut controllers = Default::default();
+        assert!(json.build_node(&mut controllers).is_ok());
+        assert!(controllers.contains_key("main"));
+
+        let mut invalid = json.clone();
+        invalid.nodes.insert(
+            "locomotion".to_owned(),
+            GltfAnimationGraphNode::Reference {
+                node: "locomotion".to_owned(),
+            },
+        );
+        assert!(invalid.build_node(&mut Default::default()).is_err());
+
+        let mut invalid = json.clone();
+        invalid.root = GltfAnimationGraphNode::Target {
+            animation: "run".to_owned(),
+        };
+        assert!(invalid.build_node(&mut Default::default()).is_err());
+    }
+}
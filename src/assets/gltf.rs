@@ -1,7 +1,8 @@
 use crate::{
     animation::gltf::{
-        GltfAnimation, GltfAnimationChannel, GltfAnimationValues, GltfMesh, GltfNode, GltfNodeId,
-        GltfPrimitive, GltfSceneTemplate, GltfSkeletonBone, GltfSkin, GltfVertex,
+        GltfAnimation, GltfAnimationChannel, GltfAnimationValues, GltfCamera, GltfCameraProjection,
+        GltfLight, GltfLightKind, GltfMesh, GltfMorphTarget, GltfNode, GltfNodeId, GltfPrimitive,
+        GltfSceneTemplate, GltfSkeletonBone, GltfSkin, GltfVertex,
     },
     assets::name_from_path,
     context::GameContext,
@@ -12,9 +13,11 @@ use anput::bundle::DynamicBundle;
 use base64::{Engine, prelude::BASE64_STANDARD};
 use gltf::{
     Animation, Glb, Gltf, Mesh, Node, Scene, Skin, Texture,
-    animation::util::ReadOutputs,
+    animation::{Interpolation, util::ReadOutputs},
     buffer::{Source as BufferSource, View as BufferView},
+    camera::Projection,
     image::Source as ImageSource,
+    khr_lights_punctual::Kind,
     material::AlphaMode,
 };
 use keket::{
@@ -33,7 +36,7 @@ use spitfire_core::Triangle;
 use spitfire_draw::{sprite::SpriteTexture, utils::TextureRef};
 use spitfire_glow::renderer::{GlowBlending, GlowTextureFiltering};
 use std::{any::Any, collections::HashMap, error::Error};
-use vek::{Mat4, Quaternion, Transform, Vec3};
+use vek::{Mat4, Quaternion, Rgb, Transform, Vec3};
 
 pub struct GltfAsset {
     pub gltf: Gltf,
@@ -293,6 +296,14 @@ fn process_mesh(
             weights,
         }
         .collect::<Vec<_>>();
+        let morph_targets = reader
+            .read_morph_targets()
+            .map(|(positions, _, _)| GltfMorphTarget {
+                positions: positions
+                    .map(|iter| iter.map(|v| v.into()).collect())
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
         let main_texture = primitive
             .material()
             .pbr_metallic_roughness()
@@ -319,9 +330,13 @@ fn process_mesh(
             blending,
             triangles,
             vertices,
+            morph_targets,
         });
     }
-    let mesh = GltfMesh { primitives };
+    let mesh = GltfMesh {
+        primitives,
+        morph_weights: mesh.weights().map(|v| v.to_vec()).unwrap_or_default(),
+    };
     let entity = access
         .access()?
         .write()
@@ -389,6 +404,20 @@ fn process_skin(
     Ok(AssetHandle::new(entity))
 }
 
+/// Cubic spline sampler output stores (in tangent, value, out tangent) per keyframe.
+fn split_cubic_spline<T: Clone>(values: Vec<T>) -> (Vec<T>, Vec<T>, Vec<T>) {
+    let count = values.len() / 3;
+    let mut in_tangents = Vec::with_capacity(count);
+    let mut result = Vec::with_capacity(count);
+    let mut out_tangents = Vec::with_capacity(count);
+    for chunk in values.chunks_exact(3) {
+        in_tangents.push(chunk[0].clone());
+        result.push(chunk[1].clone());
+        out_tangents.push(chunk[2].clone());
+    }
+    (in_tangents, result, out_tangents)
+}
+
 fn process_animation(
     animation: Animation,
     path: &AssetPathStatic,
@@ -404,31 +433,97 @@ fn process_animation(
     let mut channels = Vec::default();
     for channel in animation.channels() {
         let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(|v| v.as_slice()));
-        let values = match reader
+        let times = reader
+            .read_inputs()
+            .ok_or("Animation sampler is missing input times")?
+            .collect::<Vec<f32>>();
+        let interpolation = channel.sampler().interpolation();
+        let cubic = interpolation == Interpolation::CubicSpline;
+        let (values, tangents) = match reader
             .read_outputs()
             .ok_or("Animation sampler is missing output values")?
         {
             ReadOutputs::Translations(iter) => {
                 let translations = iter.map(|v| v.into()).collect::<Vec<Vec3<f32>>>();
-                GltfAnimationValues::Translation(translations)
+                if cubic {
+                    let (in_tangents, translations, out_tangents) =
+                        split_cubic_spline(translations);
+                    (
+                        GltfAnimationValues::Translation(translations),
+                        Some((
+                            GltfAnimationValues::Translation(in_tangents),
+                            GltfAnimationValues::Translation(out_tangents),
+                        )),
+                    )
+                } else {
+                    (GltfAnimationValues::Translation(translations), None)
+                }
             }
             ReadOutputs::Rotations(iter) => {
                 let rotations = iter
                     .into_f32()
-                    .map(|v| Quaternion::from_vec4(v.into()).normalized())
+                    .map(|v| Quaternion::from_vec4(v.into()))
                     .collect::<Vec<Quaternion<f32>>>();
-                GltfAnimationValues::Rotation(rotations)
+                if cubic {
+                    let (in_tangents, rotations, out_tangents) = split_cubic_spline(rotations);
+                    (
+                        GltfAnimationValues::Rotation(
+                            rotations.into_iter().map(|v| v.normalized()).collect(),
+                        ),
+                        Some((
+                            GltfAnimationValues::Rotation(in_tangents),
+                            GltfAnimationValues::Rotation(out_tangents),
+                        )),
+                    )
+                } else {
+                    (
+                        GltfAnimationValues::Rotation(
+                            rotations.into_iter().map(|v| v.normalized()).collect(),
+                        ),
+                        None,
+                    )
+                }
+            }
+            ReadOutputs::MorphTargetWeights(iter) => {
+                let weights = iter.into_f32().collect::<Vec<f32>>();
+                let count = if cubic {
+                    weights.len() / times.len().max(1) / 3
+                } else {
+                    weights.len() / times.len().max(1)
+                };
+                let weights = weights
+                    .chunks_exact(count.max(1))
+                    .map(|chunk| chunk.to_vec())
+                    .collect::<Vec<_>>();
+                if cubic {
+                    let (in_tangents, weights, out_tangents) = split_cubic_spline(weights);
+                    (
+                        GltfAnimationValues::MorphTargetWeights(weights),
+                        Some((
+                            GltfAnimationValues::MorphTargetWeights(in_tangents),
+                            GltfAnimationValues::MorphTargetWeights(out_tangents),
+                        )),
+                    )
+                } else {
+                    (GltfAnimationValues::MorphTargetWeights(weights), None)
+                }
             }
             ReadOutputs::Scales(iter) => {
                 let scales = iter.map(|v| v.into()).collect::<Vec<Vec3<f32>>>();
-                GltfAnimationValues::Scale(scales)
+                if cubic {
+                    let (in_tangents, scales, out_tangents) = split_cubic_spline(scales);
+                    (
+                        GltfAnimationValues::Scale(scales),
+                        Some((
+                            GltfAnimationValues::Scale(in_tangents),
+                            GltfAnimationValues::Scale(out_tangents),
+                        )),
+                    )
+                } else {
+                    (GltfAnimationValues::Scale(scales), None)
+                }
             }
-            _ => continue,
         };
-        let times = reader
-            .read_inputs()
-            .ok_or("Animation sampler is missing input times")?
-            .collect::<Vec<f32>>();
         let target_node = GltfNodeId {
             container_handle: handle,
             node_index: channel.target().node().index(),
@@ -439,7 +534,8 @@ fn process_animation(
             times,
             duration,
             values,
-            interpolation: channel.sampler().interpolation(),
+            tangents,
+            interpolation,
         });
     }
     let duration = channels
@@ -534,6 +630,47 @@ fn process_node(
         },
         mesh_handle,
         skin_handle,
+        morph_weights: node.weights().map(|v| v.to_vec()),
+        camera: node.camera().map(|camera| GltfCamera {
+            name: camera
+                .name()
+                .map(sanitize_name)
+                .unwrap_or_else(|| camera.index().to_string()),
+            projection: match camera.projection() {
+                Projection::Orthographic(projection) => GltfCameraProjection::Orthographic {
+                    xmag: projection.xmag(),
+                    ymag: projection.ymag(),
+                    znear: projection.znear(),
+                    zfar: projection.zfar(),
+                },
+                Projection::Perspective(projection) => GltfCameraProjection::Perspective {
+                    aspect_ratio: projection.aspect_ratio(),
+                    yfov: projection.yfov(),
+                    znear: projection.znear(),
+                    zfar: projection.zfar(),
+                },
+            },
+        }),
+        light: node.light().map(|light| GltfLight {
+            name: light
+                .name()
+                .map(sanitize_name)
+                .unwrap_or_else(|| light.index().to_string()),
+            kind: match light.kind() {
+                Kind::Directional => GltfLightKind::Directional,
+                Kind::Point => GltfLightKind::Point,
+                Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => GltfLightKind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                },
+            },
+            color: Rgb::from(light.color()).into(),
+            intensity: light.intensity(),
+            range: light.range(),
+        }),
         children,
     }
 }
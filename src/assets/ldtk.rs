@@ -1,8 +1,12 @@
 use crate::{
     assets::texture::TextureAsset,
+    game::GameObject,
     map::{
         LdtkMapBuilder, Map,
-        ldtk::{EntityInstance, LayerInstance, Ldtk, Level},
+        ldtk::{
+            EntityInstance, FieldInstance, GridPoint, LayerInstance, Ldtk, Level,
+            ReferenceToAnEntityInstance, TilesetRectangle,
+        },
     },
 };
 use anput::world::World;
@@ -13,22 +17,29 @@ use keket::{
     },
     protocol::AssetProtocol,
 };
+use serde_json::Value;
 use std::{
     collections::HashMap,
     error::Error,
     io::{Cursor, Read},
 };
-use vek::{Rect, Vec2};
+use vek::{Rect, Rgba, Vec2};
 use zip::ZipArchive;
 
 #[derive(Debug)]
 pub struct LdtkAsset {
     pub world: Ldtk,
     pub tilesets: HashMap<String, AssetPathStatic>,
+    pub image_sizes: HashMap<String, Vec2<u32>>,
 }
 
 impl LdtkAsset {
-    pub fn build_map<'a>(&'a self, mut builder: LdtkMapBuilder<'a>) -> Map {
+    pub fn build_map<'a>(&'a self, builder: LdtkMapBuilder<'a>) -> Map {
+        self.prepare_builder(builder).build(&self.world)
+    }
+
+    /// Fills builder texture resolvers with this asset images.
+    pub fn prepare_builder<'a>(&'a self, mut builder: LdtkMapBuilder<'a>) -> LdtkMapBuilder<'a> {
         if builder.tileset_reference_extractor.is_none() {
             builder.tileset_reference_extractor = Some(Box::new(|name| {
                 self.tilesets
@@ -38,11 +49,44 @@ impl LdtkAsset {
                     .to_owned()
             }));
         }
-        builder.build(&self.world)
+        if builder.image_size_extractor.is_none() {
+            builder.image_size_extractor = Some(Box::new(|name| {
+                self.image_sizes
+                    .get(name)
+                    .map(|size| size.numcast().unwrap())
+            }));
+        }
+        builder
+    }
+
+    /// Identifiers of worlds in multi-world project.
+    pub fn world_names(&self) -> impl Iterator<Item = &str> {
+        self.world
+            .worlds
+            .iter()
+            .map(|world| world.identifier.as_str())
+    }
+
+    /// Levels of project root and all of its worlds.
+    pub fn levels(&self) -> impl Iterator<Item = &Level> {
+        self.world.levels.iter().chain(
+            self.world
+                .worlds
+                .iter()
+                .flat_map(|world| world.levels.iter()),
+        )
+    }
+
+    pub fn world_levels(&self, world: &str) -> impl Iterator<Item = &Level> {
+        self.world
+            .worlds
+            .iter()
+            .filter(move |item| item.identifier == world)
+            .flat_map(|world| world.levels.iter())
     }
 
     pub fn layers(&self) -> impl Iterator<Item = (&Level, &LayerInstance)> {
-        self.world.levels.iter().flat_map(|level| {
+        self.levels().flat_map(|level| {
             level
                 .layer_instances
                 .iter()
@@ -56,9 +100,7 @@ impl LdtkAsset {
         only_levels: Option<&[&str]>,
         only_layers: Option<&[&str]>,
     ) -> impl Iterator<Item = (&Level, &LayerInstance, &EntityInstance)> {
-        self.world
-            .levels
-            .iter()
+        self.levels()
             .filter(move |level| {
                 only_levels
                     .as_ref()
@@ -83,6 +125,34 @@ impl LdtkAsset {
             })
     }
 
+    /// Finds entity pointed by entity reference field, across all levels.
+    pub fn find_entity(
+        &self,
+        reference: &ReferenceToAnEntityInstance,
+    ) -> Option<(&Level, &LayerInstance, &EntityInstance)> {
+        let level = self
+            .levels()
+            .find(|level| level.iid == reference.level_iid)?;
+        let layer = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .find(|layer| layer.iid == reference.layer_iid)?;
+        let entity = layer
+            .entity_instances
+            .iter()
+            .find(|entity| entity.iid == reference.entity_iid)?;
+        Some((level, layer, entity))
+    }
+
+    pub fn find_entity_by_iid(
+        &self,
+        iid: &str,
+    ) -> Option<(&Level, &LayerInstance, &EntityInstance)> {
+        self.entities(None, None)
+            .find(|(_, _, entity)| entity.iid == iid)
+    }
+
     pub fn extract_entities<R>(
         &self,
         only_levels: Option<&[&str]>,
@@ -109,9 +179,7 @@ impl LdtkAsset {
             Option<&str>,
         ),
     > {
-        self.world
-            .levels
-            .iter()
+        self.levels()
             .filter(move |level| {
                 only_levels
                     .as_ref()
@@ -187,6 +255,314 @@ impl LdtkAsset {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum LdtkFieldValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Color(Rgba<f32>),
+    Point(Vec2<i64>),
+    Enum(String),
+    EntityRef(ReferenceToAnEntityInstance),
+    Tile(TilesetRectangle),
+    Array(Vec<LdtkFieldValue>),
+}
+
+impl LdtkFieldValue {
+    /// Converts raw field value using LDtk field type name, e.g. `Int` or `Array<Point>`.
+    pub fn parse(type_name: &str, value: Option<&Value>) -> Self {
+        let Some(value) = value.filter(|value| !value.is_null()) else {
+            return Self::Null;
+        };
+        if let Some(item_type) = type_name
+            .strip_prefix("Array<")
+            .and_then(|type_name| type_name.strip_suffix('>'))
+        {
+            return value
+                .as_array()
+                .map(|items| {
+                    Self::Array(
+                        items
+                            .iter()
+                            .map(|item| Self::parse(item_type, Some(item)))
+                            .collect(),
+                    )
+                })
+                .unwrap_or(Self::Null);
+        }
+        let result = match type_name {
+            "Int" => value.as_i64().map(Self::Int),
+            "Float" => value.as_f64().map(Self::Float),
+            "Bool" => value.as_bool().map(Self::Bool),
+            "String" | "Multilines" | "FilePath" => {
+                value.as_str().map(|value| Self::String(value.to_owned()))
+            }
+            "Color" => value
+                .as_str()
+                .map(|value| Self::Color(LdtkMapBuilder::parse_color(value))),
+            "Point" => serde_json::from_value::<GridPoint>(value.clone())
+                .ok()
+                .map(|point| Self::Point(Vec2::new(point.cx, point.cy))),
+            "EntityRef" => serde_json::from_value(value.clone())
+                .ok()
+                .map(Self::EntityRef),
+            "Tile" => serde_json::from_value(value.clone()).ok().map(Self::Tile),
+            _ if type_name.starts_with("LocalEnum.") || type_name.starts_with("ExternEnum.") => {
+                value.as_str().map(|value| Self::Enum(value.to_owned()))
+            }
+            _ => None,
+        };
+        result.unwrap_or(Self::Null)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Ints are converted to floats.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<Rgba<f32>> {
+        match self {
+            Self::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_point(&self) -> Option<Vec2<i64>> {
+        match self {
+            Self::Point(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_enum(&self) -> Option<&str> {
+        match self {
+            Self::Enum(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_entity_ref(&self) -> Option<&ReferenceToAnEntityInstance> {
+        match self {
+            Self::EntityRef(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_tile(&self) -> Option<&TilesetRectangle> {
+        match self {
+            Self::Tile(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[LdtkFieldValue]> {
+        match self {
+            Self::Array(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Typed access to custom fields of LDtk entities and levels.
+pub trait LdtkFields {
+    fn field_instances(&self) -> &[FieldInstance];
+
+    fn field_instance(&self, name: &str) -> Option<&FieldInstance> {
+        self.field_instances()
+            .iter()
+            .find(|field| field.identifier == name)
+    }
+
+    fn field(&self, name: &str) -> Option<LdtkFieldValue> {
+        let field = self.field_instance(name)?;
+        Some(LdtkFieldValue::parse(
+            &field.field_instance_type,
+            field.value.as_ref(),
+        ))
+    }
+
+    fn field_int(&self, name: &str) -> Option<i64> {
+        self.field(name)?.as_int()
+    }
+
+    fn field_float(&self, name: &str) -> Option<f64> {
+        self.field(name)?.as_float()
+    }
+
+    fn field_bool(&self, name: &str) -> Option<bool> {
+        self.field(name)?.as_bool()
+    }
+
+    fn field_string(&self, name: &str) -> Option<String> {
+        self.field(name)?.as_str().map(|value| value.to_owned())
+    }
+
+    fn field_color(&self, name: &str) -> Option<Rgba<f32>> {
+        self.field(name)?.as_color()
+    }
+
+    fn field_point(&self, name: &str) -> Option<Vec2<i64>> {
+        self.field(name)?.as_point()
+    }
+
+    fn field_enum(&self, name: &str) -> Option<String> {
+        self.field(name)?.as_enum().map(|value| value.to_owned())
+    }
+
+    fn field_entity_ref(&self, name: &str) -> Option<ReferenceToAnEntityInstance> {
+        match self.field(name)? {
+            LdtkFieldValue::EntityRef(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn field_array(&self, name: &str) -> Option<Vec<LdtkFieldValue>> {
+        match self.field(name)? {
+            LdtkFieldValue::Array(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl LdtkFields for EntityInstance {
+    fn field_instances(&self) -> &[FieldInstance] {
+        &self.field_instances
+    }
+}
+
+impl LdtkFields for Level {
+    fn field_instances(&self) -> &[FieldInstance] {
+        &self.field_instances
+    }
+}
+
+pub struct LdtkSpawnContext<'a> {
+    pub asset: &'a LdtkAsset,
+    pub level: &'a Level,
+    pub layer: &'a LayerInstance,
+    pub entity: &'a EntityInstance,
+    /// Entity pivot position in world space, scaled by pixel world scale.
+    pub position: Vec2<f32>,
+}
+
+pub type LdtkSpawnFunction = Box<dyn Fn(&LdtkSpawnContext) -> Option<Box<dyn GameObject>>>;
+
+/// Maps LDtk entity identifiers to functions spawning game objects.
+pub struct LdtkSpawnRegistry {
+    pub pixel_world_scale: f32,
+    spawners: HashMap<String, LdtkSpawnFunction>,
+}
+
+impl Default for LdtkSpawnRegistry {
+    fn default() -> Self {
+        Self {
+            pixel_world_scale: 1.0,
+            spawners: Default::default(),
+        }
+    }
+}
+
+impl LdtkSpawnRegistry {
+    pub fn pixel_world_scale(mut self, scale: f32) -> Self {
+        self.pixel_world_scale = scale;
+        self
+    }
+
+    pub fn with<T: GameObject + 'static>(
+        mut self,
+        identifier: impl ToString,
+        spawner: impl Fn(&LdtkSpawnContext) -> Option<T> + 'static,
+    ) -> Self {
+        self.register(identifier, spawner);
+        self
+    }
+
+    pub fn register<T: GameObject + 'static>(
+        &mut self,
+        identifier: impl ToString,
+        spawner: impl Fn(&LdtkSpawnContext) -> Option<T> + 'static,
+    ) {
+        self.spawners.insert(
+            identifier.to_string(),
+            Box::new(move |context| {
+                spawner(context).map(|object| Box::new(object) as Box<dyn GameObject>)
+            }),
+        );
+    }
+
+    pub fn unregister(&mut self, identifier: &str) {
+        self.spawners.remove(identifier);
+    }
+
+    pub fn contains(&self, identifier: &str) -> bool {
+        self.spawners.contains_key(identifier)
+    }
+
+    pub fn spawn(
+        &self,
+        asset: &LdtkAsset,
+        only_levels: Option<&[&str]>,
+        only_layers: Option<&[&str]>,
+    ) -> Vec<Box<dyn GameObject>> {
+        asset
+            .entities(only_levels, only_layers)
+            .filter_map(|(level, layer, entity)| {
+                let spawner = self.spawners.get(&entity.identifier)?;
+                let position = match (entity.world_x, entity.world_y) {
+                    (Some(x), Some(y)) => Vec2::new(x, y),
+                    _ => Vec2::new(
+                        level.world_x
+                            + layer.px_total_offset_x
+                            + entity.px.first().copied().unwrap_or_default(),
+                        level.world_y
+                            + layer.px_total_offset_y
+                            + entity.px.get(1).copied().unwrap_or_default(),
+                    ),
+                };
+                spawner(&LdtkSpawnContext {
+                    asset,
+                    level,
+                    layer,
+                    entity,
+                    position: Vec2::new(position.x as f32, position.y as f32)
+                        * self.pixel_world_scale,
+                })
+            })
+            .collect()
+    }
+}
+
 pub trait LdtkTileExtractor {
     type Tile;
 
@@ -385,13 +761,20 @@ impl AssetProtocol for LdtkAssetProtocol {
         for file_name in archive.file_names() {
             if file_name.ends_with(".ldtk") {
                 world_name = Some(file_name.to_string());
-            } else if file_name.ends_with(".png") {
+            } else if [".png", ".jpg", ".jpeg", ".bmp"]
+                .iter()
+                .any(|extension| file_name.to_lowercase().ends_with(extension))
+            {
                 tileset_names.push(file_name.to_string());
             }
         }
         let Some(world) = world_name else {
             return Err("No world file found in LDTK package".into());
         };
+        let directory = world
+            .rsplit_once('/')
+            .map(|(directory, _)| format!("{directory}/"))
+            .unwrap_or_default();
         let path_part = storage
             .component::<true, AssetPathStatic>(handle.entity())?
             .path()
@@ -399,27 +782,145 @@ impl AssetProtocol for LdtkAssetProtocol {
 
         let mut bytes = vec![];
         archive.by_name(&world)?.read_to_end(&mut bytes)?;
-        let world = serde_json::from_slice::<Ldtk>(&bytes)?;
+        let mut world = serde_json::from_slice::<Ldtk>(&bytes)?;
+
+        resolve_external_levels(&mut world, |level_path| {
+            let level_path = format!("{directory}{level_path}");
+            let mut bytes = vec![];
+            archive
+                .by_name(&level_path)
+                .map_err(|_| {
+                    format!("LDTK external level file `{level_path}` not found in package")
+                })?
+                .read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })?;
 
         bytes.clear();
         let mut tilesets = HashMap::new();
+        let mut image_sizes = HashMap::new();
         for tileset_name in tileset_names {
             let mut bytes = vec![];
             archive.by_name(&tileset_name)?.read_to_end(&mut bytes)?;
             let image = image::load_from_memory(&bytes)?.into_rgba8();
             let path = AssetPathStatic::new(format!("texture://{path_part}/{tileset_name}"));
+            let name = tileset_name
+                .strip_prefix(&directory)
+                .unwrap_or(&tileset_name)
+                .to_owned();
+            image_sizes.insert(name.clone(), Vec2::from(image.dimensions()));
             let asset = TextureAsset {
                 image,
                 cols: 1,
                 rows: 1,
             };
             let entity = storage.spawn((path.clone(), asset))?;
-            tilesets.insert(tileset_name, path);
+            tilesets.insert(name, path);
             storage.relate::<true, _>(AssetDependency, handle.entity(), entity)?;
         }
 
-        storage.insert(handle.entity(), (LdtkAsset { world, tilesets },))?;
+        storage.insert(
+            handle.entity(),
+            (LdtkAsset {
+                world,
+                tilesets,
+                image_sizes,
+            },),
+        )?;
 
         Ok(())
     }
 }
+
+/// Replaces levels saved in separate files with their content, read by
+/// path relative to project file.
+fn resolve_external_levels(
+    world: &mut Ldtk,
+    mut read: impl FnMut(&str) -> Result<Vec<u8>, Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let levels = world.levels.iter_mut().chain(
+        world
+            .worlds
+            .iter_mut()
+            .flat_map(|world| world.levels.iter_mut()),
+    );
+    for level in levels {
+        if level.layer_instances.is_some() {
+            continue;
+        }
+        let Some(level_path) = level.external_rel_path.as_deref() else {
+            continue;
+        };
+        *level = serde_json::from_slice::<Level>(&read(level_path)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ldtk_field_value() {
+        assert_eq!(
+            LdtkFieldValue::parse("Int", Some(&json!(42))),
+            LdtkFieldValue::Int(42)
+        );
+        assert_eq!(LdtkFieldValue::parse("Float", None), LdtkFieldValue::Null);
+        assert_eq!(
+            LdtkFieldValue::parse("Color", Some(&json!("#ff0000"))).as_color(),
+            Some(Rgba::new(1.0, 0.0, 0.0, 1.0))
+        );
+        assert_eq!(
+            LdtkFieldValue::parse("LocalEnum.Kind", Some(&json!("Boss"))).as_enum(),
+            Some("Boss")
+        );
+        let points = LdtkFieldValue::parse(
+            "Array<Point>",
+            Some(&json!([{"cx": 1, "cy": 2}, {"cx": 3, "cy": 4}])),
+        );
+        assert_eq!(
+            points
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|point| point.as_point())
+                .collect::<Vec<_>>(),
+            vec![Vec2::new(1, 2), Vec2::new(3, 4)]
+        );
+        let reference = LdtkFieldValue::parse(
+            "EntityRef",
+            Some(&json!({
+                "entityIid": "a",
+                "layerIid": "b",
+                "levelIid": "c",
+                "worldIid": "d",
+            })),
+        );
+        assert_eq!(reference.as_entity_ref().unwrap().entity_iid, "a");
+    }
+
+    #[test]
+    fn test_ldtk_external_levels() {
+        let mut world =
+            serde_json::from_str::<Ldtk>(include_str!("../../resources/ldtk/world.ldtk")).unwrap();
+        let level = world.levels[1].clone();
+        let stub = &mut world.levels[1];
+        stub.layer_instances = None;
+        stub.external_rel_path = Some("world/level.ldtkl".to_owned());
+
+        let mut requested = vec![];
+        resolve_external_levels(&mut world, |path| {
+            requested.push(path.to_owned());
+            Ok(serde_json::to_vec(&level)?)
+        })
+        .unwrap();
+        assert_eq!(requested, vec!["world/level.ldtkl"]);
+        assert_eq!(world.levels[1], level);
+
+        world.levels[1].layer_instances = None;
+        world.levels[1].external_rel_path = Some("missing.ldtkl".to_owned());
+        assert!(resolve_external_levels(&mut world, |_| Err("missing".into())).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod anim_graph;
 pub mod anim_texture;
 pub mod atlas_texture;
 pub mod font;
@@ -7,12 +8,14 @@ pub mod shader;
 pub mod sound;
 pub mod spine;
 pub mod texture;
+pub mod tiled;
 
 use crate::assets::{
-    anim_texture::make_anim_texture_asset_protocol,
+    anim_graph::make_anim_graph_asset_protocol, anim_texture::make_anim_texture_asset_protocol,
     atlas_texture::make_atlas_texture_asset_protocol, font::FontAssetProtocol,
     gltf::make_gltf_asset_protocol, ldtk::LdtkAssetProtocol, shader::ShaderAssetProtocol,
     sound::SoundAssetProtocol, spine::SpineAssetProtocol, texture::TextureAssetProtocol,
+    tiled::TiledAssetProtocol,
 };
 use keket::{
     database::{
@@ -63,8 +66,10 @@ pub fn make_database_protocols() -> AssetDatabase {
         .with_protocol(SoundAssetProtocol)
         .with_protocol(SpineAssetProtocol)
         .with_protocol(LdtkAssetProtocol)
+        .with_protocol(TiledAssetProtocol)
         .with_protocol(make_gltf_asset_protocol())
         .with_protocol(make_atlas_texture_asset_protocol())
+        .with_protocol(make_anim_graph_asset_protocol())
 }
 
 pub fn make_database(fetch: impl AssetFetch) -> AssetDatabase {
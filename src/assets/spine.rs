@@ -7,7 +7,7 @@ use keket::{
     },
     protocol::AssetProtocol,
 };
-use rusty_spine::{Atlas, SkeletonData, SkeletonJson};
+use rusty_spine::{Atlas, SkeletonBinary, SkeletonData, SkeletonJson};
 use std::{
     collections::HashMap,
     error::Error,
@@ -36,54 +36,123 @@ impl AssetProtocol for SpineAssetProtocol {
         storage: &mut World,
         bytes: Vec<u8>,
     ) -> Result<(), Box<dyn Error>> {
-        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
-        let mut atlas = None;
-        let mut skeleton_data = None;
-        let mut atlas_page_names = Vec::new();
+        let path = storage.component::<true, AssetPathStatic>(handle.entity())?;
+        let path_part = path.path().to_owned();
+        let mut skeleton_meta = None;
+        let mut atlas_meta = None;
+        let mut force_pma = false;
+        for (key, value) in path.meta_items() {
+            if key == "skeleton" {
+                skeleton_meta = Some(value.to_owned());
+            } else if key == "atlas" {
+                atlas_meta = Some(value.to_owned());
+            } else if key == "pma" {
+                force_pma = value != "false";
+            }
+        }
+        drop(path);
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes))
+            .map_err(|error| format!("Spine package `{path_part}` is not a valid zip: {error}"))?;
+        let mut atlases = Vec::new();
+        let mut skeletons = Vec::new();
         for file_name in archive.file_names() {
             if file_name.ends_with(".atlas") {
-                atlas = Some(file_name.to_string());
-            } else if file_name.ends_with(".json") {
-                skeleton_data = Some(file_name.to_string());
-            } else if file_name.ends_with(".png") {
-                atlas_page_names.push(file_name.to_string());
+                atlases.push(file_name.to_string());
+            } else if file_name.ends_with(".json") || file_name.ends_with(".skel") {
+                skeletons.push(file_name.to_string());
             }
         }
-        let Some(atlas_name) = atlas else {
-            return Err("No atlas file found in Spine package".into());
-        };
-        let Some(skeleton_data_name) = skeleton_data else {
-            return Err("No skeleton data file found in Spine package".into());
-        };
-        let path_part = storage
-            .component::<true, AssetPathStatic>(handle.entity())?
-            .path()
-            .to_owned();
+        atlases.sort();
+        skeletons.sort();
+
+        let skeleton_data_name = select_file(
+            "skeleton",
+            &path_part,
+            &skeletons,
+            skeleton_meta.as_deref(),
+            None,
+        )?;
+        let atlas_name = select_file(
+            "atlas",
+            &path_part,
+            &atlases,
+            atlas_meta.as_deref(),
+            Some(file_stem(&skeleton_data_name)),
+        )?;
 
         let mut bytes = vec![];
         archive.by_name(&atlas_name)?.read_to_end(&mut bytes)?;
-        let atlas = Arc::new(Atlas::new(&bytes, "")?);
+        let atlas = Arc::new(Atlas::new(&bytes, "").map_err(|error| {
+            format!("Failed to read Spine atlas `{atlas_name}` in `{path_part}`: {error}")
+        })?);
 
         bytes.clear();
         archive
             .by_name(&skeleton_data_name)?
             .read_to_end(&mut bytes)?;
-        let skeleton_data = Arc::new(SkeletonJson::new(atlas.clone()).read_skeleton_data(&bytes)?);
+        let skeleton_data = if skeleton_data_name.ends_with(".skel") {
+            SkeletonBinary::new(atlas.clone()).read_skeleton_data(&bytes)
+        } else {
+            SkeletonJson::new(atlas.clone()).read_skeleton_data(&bytes)
+        }
+        .map_err(|error| {
+            format!(
+                "Failed to read Spine skeleton `{skeleton_data_name}` in `{path_part}`: {error}"
+            )
+        })?;
+        let skeleton_data = Arc::new(skeleton_data);
 
-        bytes.clear();
+        let atlas_directory = atlas_name
+            .rfind('/')
+            .map(|index| &atlas_name[..=index])
+            .unwrap_or_default();
         let mut textures = HashMap::new();
-        for atlas_page_name in atlas_page_names {
+        for page in atlas.pages() {
+            let page_name = page.name().to_owned();
+            let file_name = format!("{atlas_directory}{page_name}");
+            // Skeletons selected from the same package share atlas page textures.
+            let meta = if force_pma && !page.pma() { "?pma" } else { "" };
+            let path = AssetPathStatic::new(format!("texture://{path_part}/{file_name}{meta}"));
+            if let Some(entity) = storage.find_by::<true, _>(&path) {
+                textures.insert(page_name, path);
+                storage.relate::<true, _>(AssetDependency, handle.entity(), entity)?;
+                continue;
+            }
             let mut bytes = vec![];
-            archive.by_name(&atlas_page_name)?.read_to_end(&mut bytes)?;
-            let image = image::load_from_memory(&bytes)?.into_rgba8();
-            let path = AssetPathStatic::new(format!("texture://{path_part}/{atlas_page_name}"));
+            archive
+                .by_name(&file_name)
+                .map_err(|_| {
+                    format!(
+                        "Spine atlas `{atlas_name}` page `{page_name}` not found in `{path_part}`"
+                    )
+                })?
+                .read_to_end(&mut bytes)?;
+            let mut image = image::load_from_memory(&bytes)
+                .map_err(|error| {
+                    format!(
+                        "Failed to load Spine atlas page `{file_name}` in `{path_part}`: {error}"
+                    )
+                })?
+                .into_rgba8();
+            if force_pma || page.pma() {
+                // Renderer blends straight alpha, so premultiplied pages get restored.
+                for pixel in image.pixels_mut() {
+                    let alpha = pixel[3] as u32;
+                    if alpha > 0 && alpha < 255 {
+                        for channel in 0..3 {
+                            pixel[channel] = ((pixel[channel] as u32 * 255) / alpha).min(255) as u8;
+                        }
+                    }
+                }
+            }
             let asset = TextureAsset {
                 image,
                 cols: 1,
                 rows: 1,
             };
             let entity = storage.spawn((path.clone(), asset))?;
-            textures.insert(atlas_page_name, path);
+            textures.insert(page_name, path);
             storage.relate::<true, _>(AssetDependency, handle.entity(), entity)?;
         }
 
@@ -99,3 +168,49 @@ impl AssetProtocol for SpineAssetProtocol {
         Ok(())
     }
 }
+
+fn file_stem(file_name: &str) -> &str {
+    let name = file_name.rsplit('/').next().unwrap_or(file_name);
+    name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name)
+}
+
+/// Picks package file by meta name, by preferred stem or as the only candidate.
+fn select_file(
+    kind: &str,
+    package: &str,
+    candidates: &[String],
+    name: Option<&str>,
+    preferred: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    if let Some(name) = name {
+        return candidates
+            .iter()
+            .find(|file_name| file_name.as_str() == name || file_stem(file_name) == name)
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Spine package `{package}` has no {kind} `{name}`, available: {}",
+                    candidates.join(", ")
+                )
+                .into()
+            });
+    }
+    match candidates {
+        [] => Err(format!("No {kind} file found in Spine package `{package}`").into()),
+        [file_name] => Ok(file_name.to_owned()),
+        _ => preferred
+            .and_then(|preferred| {
+                candidates
+                    .iter()
+                    .find(|file_name| file_stem(file_name) == preferred)
+            })
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Spine package `{package}` contains multiple {kind} files ({}), select one with `{kind}` asset path meta",
+                    candidates.join(", ")
+                )
+                .into()
+            }),
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    assets::texture::TextureAsset,
+    map::{
+        Map,
+        tiled::{TiledLayer, TiledLayerKind, TiledMap, TiledMapBuilder, TiledObject, TiledTileset},
+    },
+};
+use anput::world::World;
+use keket::{
+    database::{
+        handle::{AssetDependency, AssetHandle},
+        path::AssetPathStatic,
+    },
+    protocol::AssetProtocol,
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::{Cursor, Read},
+};
+use zip::ZipArchive;
+
+#[derive(Debug)]
+pub struct TiledAsset {
+    pub map: TiledMap,
+    /// Images keyed by their path inside package.
+    pub images: HashMap<String, AssetPathStatic>,
+}
+
+impl TiledAsset {
+    pub fn build_map<'a>(&'a self, mut builder: TiledMapBuilder<'a>) -> Map {
+        if builder.tileset_reference_extractor.is_none() {
+            builder.tileset_reference_extractor = Some(Box::new(|name| {
+                self.images
+                    .get(name)
+                    .map(|path| path.path())
+                    .unwrap_or(name)
+                    .to_owned()
+            }));
+        }
+        builder.build(&self.map)
+    }
+
+    /// Object layers with their objects, including ones nested in groups.
+    pub fn objects(
+        &self,
+        only_layers: Option<&[&str]>,
+    ) -> impl Iterator<Item = (&TiledLayer, &TiledObject)> {
+        fn flatten<'a>(layers: &'a [TiledLayer], result: &mut Vec<&'a TiledLayer>) {
+            for layer in layers {
+                if layer.kind == TiledLayerKind::Group {
+                    flatten(&layer.layers, result);
+                } else {
+                    result.push(layer);
+                }
+            }
+        }
+
+        let mut layers = vec![];
+        flatten(&self.map.layers, &mut layers);
+        layers
+            .into_iter()
+            .filter(move |layer| {
+                layer.kind == TiledLayerKind::ObjectGroup
+                    && only_layers
+                        .as_ref()
+                        .is_none_or(|only_layers| only_layers.contains(&layer.name.as_str()))
+            })
+            .flat_map(|layer| layer.objects.iter().map(move |object| (layer, object)))
+    }
+
+    pub fn find_object(&self, id: u32) -> Option<(&TiledLayer, &TiledObject)> {
+        self.objects(None).find(|(_, object)| object.id == id)
+    }
+
+    pub fn extract_objects<R>(
+        &self,
+        only_layers: Option<&[&str]>,
+        extractor: &dyn TiledObjectExtractor<Object = R>,
+    ) -> impl Iterator<Item = R> {
+        self.objects(only_layers)
+            .filter_map(move |(_, object)| extractor.extract(object))
+    }
+}
+
+pub trait TiledObjectExtractor {
+    type Object;
+
+    fn extract(&self, object: &TiledObject) -> Option<Self::Object>;
+}
+
+impl<F, R> TiledObjectExtractor for F
+where
+    F: Fn(&TiledObject) -> Option<R>,
+{
+    type Object = R;
+
+    fn extract(&self, object: &TiledObject) -> Option<Self::Object> {
+        self(object)
+    }
+}
+
+/// Loads zip package with single `.tmx` or `.tmj` map, its external tilesets and images.
+pub struct TiledAssetProtocol;
+
+impl AssetProtocol for TiledAssetProtocol {
+    fn name(&self) -> &str {
+        "tiled"
+    }
+
+    fn process_bytes(
+        &mut self,
+        handle: AssetHandle,
+        storage: &mut World,
+        bytes: Vec<u8>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+        let Some(map_name) = archive
+            .file_names()
+            .find(|name| name.ends_with(".tmx") || name.ends_with(".tmj"))
+            .map(|name| name.to_owned())
+        else {
+            return Err("No map file found in Tiled package".into());
+        };
+        let path_part = storage
+            .component::<true, AssetPathStatic>(handle.entity())?
+            .path()
+            .to_owned();
+
+        let mut bytes = vec![];
+        archive.by_name(&map_name)?.read_to_end(&mut bytes)?;
+        let mut map = if map_name.ends_with(".tmx") {
+            TiledMap::from_tmx(&bytes)?
+        } else {
+            TiledMap::from_tmj(&bytes)?
+        };
+        map.decode_layers()?;
+
+        for tileset in &mut map.tilesets {
+            if let Some(source) = tileset.source.take() {
+                let tileset_name = resolve_path(&map_name, &source);
+                bytes.clear();
+                archive
+                    .by_name(&tileset_name)
+                    .map_err(|_| {
+                        format!("Tiled external tileset file `{tileset_name}` not found in package")
+                    })?
+                    .read_to_end(&mut bytes)?;
+                let firstgid = tileset.firstgid;
+                *tileset = if tileset_name.ends_with(".tsx") {
+                    TiledTileset::from_tsx(&bytes)?
+                } else {
+                    TiledTileset::from_tsj(&bytes)?
+                };
+                tileset.firstgid = firstgid;
+                if let Some(image) = tileset.image.as_mut() {
+                    *image = resolve_path(&tileset_name, image);
+                }
+            } else if let Some(image) = tileset.image.as_mut() {
+                *image = resolve_path(&map_name, image);
+            }
+        }
+        resolve_layer_images(&map_name, &mut map.layers);
+
+        let mut image_names = map
+            .tilesets
+            .iter()
+            .filter_map(|tileset| tileset.image.clone())
+            .collect::<Vec<_>>();
+        collect_layer_images(&map.layers, &mut image_names);
+        image_names.sort();
+        image_names.dedup();
+
+        let mut images = HashMap::new();
+        for image_name in image_names {
+            bytes.clear();
+            archive
+                .by_name(&image_name)
+                .map_err(|_| format!("Tiled image file `{image_name}` not found in package"))?
+                .read_to_end(&mut bytes)?;
+            let image = image::load_from_memory(&bytes)?.into_rgba8();
+            let path = AssetPathStatic::new(format!("texture://{path_part}/{image_name}"));
+            let asset = TextureAsset {
+                image,
+                cols: 1,
+                rows: 1,
+            };
+            let entity = storage.spawn((path.clone(), asset))?;
+            images.insert(image_name, path);
+            storage.relate::<true, _>(AssetDependency, handle.entity(), entity)?;
+        }
+
+        storage.insert(handle.entity(), (TiledAsset { map, images },))?;
+
+        Ok(())
+    }
+}
+
+fn resolve_layer_images(owner: &str, layers: &mut [TiledLayer]) {
+    for layer in layers {
+        if let Some(image) = layer.image.as_mut() {
+            *image = resolve_path(owner, image);
+        }
+        resolve_layer_images(owner, &mut layer.layers);
+    }
+}
+
+fn collect_layer_images(layers: &[TiledLayer], result: &mut Vec<String>) {
+    for layer in layers {
+        result.extend(layer.image.iter().cloned());
+        collect_layer_images(&layer.layers, result);
+    }
+}
+
+/// Resolves path relative to owner file into package path.
+fn resolve_path(owner: &str, relative: &str) -> String {
+    let mut parts = owner.split('/').collect::<Vec<_>>();
+    parts.pop();
+    for part in relative.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
@@ -1,4 +1,5 @@
 use crate::{
+    animation::tween::TweenNode,
     context::GameContext,
     game::{CONTEXT_META, DELTA_TIME_META, NEXT_FRAME_QUEUE_META},
     gc::{DynGc, Heartbeat},
@@ -83,6 +84,17 @@ pub async fn async_next_frame() {
     move_to(JobLocation::Queue(queue)).await;
 }
 
+pub async fn async_tween(mut tween: impl TweenNode) {
+    while !tween.is_completed() {
+        let delta = async_delta_time().await;
+        tween.advance(delta);
+        if tween.is_completed() {
+            break;
+        }
+        async_next_frame().await;
+    }
+}
+
 pub async fn async_wait_for_asset(handle: AssetHandle) {
     loop {
         let context = async_game_context().await.unwrap();
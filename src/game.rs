@@ -1,6 +1,7 @@
 #[cfg(feature = "editor")]
 use crate::editor::EditorInput;
 use crate::{
+    animation::{lod::LodBudgetSubsystem, tween::TweenSubsystem},
     assets::{
         anim_texture::AnimTextureAssetSubsystem, font::FontAssetSubsystem,
         gltf::GltfAssetSubsystem, shader::ShaderAssetSubsystem, sound::SoundAssetSubsystem,
@@ -369,6 +370,8 @@ impl Default for GameInstance {
                 Box::new(FontAssetSubsystem),
                 Box::new(SoundAssetSubsystem),
                 Box::new(GltfAssetSubsystem),
+                Box::new(TweenSubsystem::default()),
+                Box::new(LodBudgetSubsystem::default()),
             ],
             globals: Default::default(),
             jobs: Default::default(),
@@ -75,8 +75,10 @@ pub mod game;
 pub mod gamepad;
 pub mod gc;
 pub mod interactible;
+pub mod lighting;
 pub mod map;
 pub mod multiplayer;
+pub mod render_queue;
 pub mod scripting;
 pub mod tag;
 pub mod transformed;
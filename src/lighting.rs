@@ -0,0 +1,579 @@
+use crate::{
+    context::GameContext,
+    game::GameSubsystem,
+    interactible::Interactible,
+    map::{Map, colliders::MapColliderGrid, grid_world::GridWorld},
+};
+use spitfire_core::Triangle;
+use spitfire_draw::{
+    canvas::Canvas,
+    sprite::Sprite,
+    utils::{Drawable, ShaderRef, Vertex, transform_to_matrix},
+};
+use spitfire_glow::{
+    graphics::{GraphicsBatch, Shader},
+    renderer::{GlowBlending, GlowTextureFiltering, GlowTextureFormat, GlowUniformValue},
+};
+use std::{any::Any, collections::HashMap, f32::consts::PI};
+use vek::{Rect, Rgba, Vec2};
+
+pub const LIGHT_SHADER: &str = "lighting-light";
+pub const COMPOSITE_SHADER: &str = "lighting-composite";
+
+pub const LIGHT_VERTEX: &str = r#"#version 300 es
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in vec3 a_uv;
+layout(location = 2) in vec4 a_color;
+out vec4 v_color;
+out vec3 v_uv;
+uniform mat4 u_projection_view;
+
+void main() {
+    gl_Position = u_projection_view * vec4(a_position, 0.0, 1.0);
+    v_color = a_color;
+    v_uv = a_uv;
+}
+"#;
+
+pub const LIGHT_FRAGMENT: &str = r#"#version 300 es
+precision highp float;
+precision highp int;
+in vec4 v_color;
+in vec3 v_uv;
+out vec4 o_color;
+uniform vec2 u_direction;
+uniform float u_cone;
+uniform float u_falloff;
+
+void main() {
+    float distance = length(v_uv.xy);
+    float factor = pow(clamp(1.0 - distance, 0.0, 1.0), u_falloff);
+    if (u_cone < 3.14159 && distance > 0.0) {
+        float angle = acos(clamp(dot(v_uv.xy / distance, u_direction), -1.0, 1.0));
+        factor *= 1.0 - smoothstep(u_cone * 0.8, u_cone, angle);
+    }
+    o_color = vec4(v_color.rgb * v_color.a * factor, 1.0);
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light2dKind {
+    Point,
+    Spot {
+        direction: Vec2<f32>,
+        /// Half angle of cone in radians.
+        angle: f32,
+    },
+    /// Lights everything uniformly, ignores position, radius and shadows.
+    Ambient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light2d {
+    pub kind: Light2dKind,
+    pub position: Vec2<f32>,
+    pub radius: f32,
+    pub color: Rgba<f32>,
+    pub intensity: f32,
+    pub falloff: f32,
+    /// Radius of light source, used to soften shadows.
+    pub softness: f32,
+    pub cast_shadows: bool,
+}
+
+impl Default for Light2d {
+    fn default() -> Self {
+        Self {
+            kind: Light2dKind::Point,
+            position: Default::default(),
+            radius: 100.0,
+            color: Rgba::white(),
+            intensity: 1.0,
+            falloff: 1.0,
+            softness: 0.0,
+            cast_shadows: true,
+        }
+    }
+}
+
+impl Light2d {
+    pub fn point(position: impl Into<Vec2<f32>>, radius: f32) -> Self {
+        Self {
+            position: position.into(),
+            radius,
+            ..Default::default()
+        }
+    }
+
+    pub fn spot(
+        position: impl Into<Vec2<f32>>,
+        radius: f32,
+        direction: impl Into<Vec2<f32>>,
+        angle: f32,
+    ) -> Self {
+        Self {
+            kind: Light2dKind::Spot {
+                direction: direction.into(),
+                angle,
+            },
+            position: position.into(),
+            radius,
+            ..Default::default()
+        }
+    }
+
+    pub fn ambient(color: Rgba<f32>) -> Self {
+        Self {
+            kind: Light2dKind::Ambient,
+            color,
+            cast_shadows: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, value: Rgba<f32>) -> Self {
+        self.color = value;
+        self
+    }
+
+    pub fn intensity(mut self, value: f32) -> Self {
+        self.intensity = value;
+        self
+    }
+
+    pub fn falloff(mut self, value: f32) -> Self {
+        self.falloff = value;
+        self
+    }
+
+    pub fn softness(mut self, value: f32) -> Self {
+        self.softness = value;
+        self
+    }
+
+    pub fn cast_shadows(mut self, value: bool) -> Self {
+        self.cast_shadows = value;
+        self
+    }
+
+    pub fn bounding_box(&self) -> Rect<f32, f32> {
+        Rect::new(
+            self.position.x - self.radius,
+            self.position.y - self.radius,
+            self.radius * 2.0,
+            self.radius * 2.0,
+        )
+    }
+}
+
+/// Set of world space segments blocking light.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Occluder2d {
+    pub segments: Vec<[Vec2<f32>; 2]>,
+}
+
+impl Occluder2d {
+    pub fn segment(mut self, from: impl Into<Vec2<f32>>, to: impl Into<Vec2<f32>>) -> Self {
+        self.segments.push([from.into(), to.into()]);
+        self
+    }
+
+    pub fn from_polygon(points: impl IntoIterator<Item = Vec2<f32>>) -> Self {
+        let points = points.into_iter().collect::<Vec<_>>();
+        Self {
+            segments: (0..points.len())
+                .map(|index| [points[index], points[(index + 1) % points.len()]])
+                .collect(),
+        }
+    }
+
+    pub fn from_rect(rect: Rect<f32, f32>) -> Self {
+        Self::from_polygon([
+            Vec2::new(rect.x, rect.y),
+            Vec2::new(rect.x + rect.w, rect.y),
+            Vec2::new(rect.x + rect.w, rect.y + rect.h),
+            Vec2::new(rect.x, rect.y + rect.h),
+        ])
+    }
+
+    /// Collects enabled colliders of visible levels matching mask.
+    pub fn from_map(map: &Map, mask: u32) -> Self {
+        let map_matrix = transform_to_matrix(map.transform);
+        let mut result = Self::default();
+        for level in map.levels.iter().filter(|level| level.visible) {
+            let matrix = map_matrix * transform_to_matrix(level.transform);
+            for collider in &level.colliders {
+                if !collider.enabled || collider.mask & mask == 0 {
+                    continue;
+                }
+                let rect = collider.rectangle;
+                result.segments.extend(
+                    Self::from_polygon(
+                        [
+                            Vec2::new(rect.x, rect.y),
+                            Vec2::new(rect.x + rect.w, rect.y),
+                            Vec2::new(rect.x + rect.w, rect.y + rect.h),
+                            Vec2::new(rect.x, rect.y + rect.h),
+                        ]
+                        .map(|point| matrix.mul_point(point)),
+                    )
+                    .segments,
+                );
+            }
+        }
+        result
+    }
+
+    /// Traced outlines of collider grid cells matching mask.
+    pub fn from_collider_grid(grid: &MapColliderGrid, mask: u32) -> Self {
+        Self {
+            segments: grid
+                .outlines(mask)
+                .into_iter()
+                .flat_map(|outline| Self::from_polygon(outline).segments)
+                .collect(),
+        }
+    }
+
+    /// Traced outlines of collider cells, with cell shapes of grid world layout.
+    pub fn from_grid_world(world: &GridWorld) -> Self {
+        match MapColliderGrid::from_grid_world(world) {
+            Some(grid) => Self::from_collider_grid(&grid, u32::MAX),
+            None => Self {
+                segments: world.collider_edges(),
+            },
+        }
+    }
+
+    /// Outline edges of interactible mesh.
+    pub fn from_interactible(interactible: &Interactible) -> Self {
+        let mut edges = HashMap::<(u32, u32), usize>::default();
+        for [a, b, c] in &interactible.triangles {
+            for (from, to) in [(*a, *b), (*b, *c), (*c, *a)] {
+                *edges.entry((from.min(to), from.max(to))).or_default() += 1;
+            }
+        }
+        Self {
+            segments: edges
+                .into_iter()
+                .filter(|(_, count)| *count == 1)
+                .filter_map(|((from, to), _)| {
+                    Some([
+                        *interactible.vertices.get(from as usize)?,
+                        *interactible.vertices.get(to as usize)?,
+                    ])
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Renders lights into light map and multiplies it over game world before GUI.
+///
+/// Lights are consumed every frame, occluders stay until cleared.
+pub struct Lighting2dSubsystem {
+    pub enabled: bool,
+    pub ambient: Rgba<f32>,
+    /// Number of jittered light samples used for soft shadows.
+    pub shadow_samples: usize,
+    pub lights: Vec<Light2d>,
+    pub occluders: Vec<Occluder2d>,
+    canvas: Option<Canvas>,
+}
+
+impl Default for Lighting2dSubsystem {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ambient: Rgba::new(0.0, 0.0, 0.0, 1.0),
+            shadow_samples: 4,
+            lights: Default::default(),
+            occluders: Default::default(),
+            canvas: None,
+        }
+    }
+}
+
+impl Lighting2dSubsystem {
+    pub fn ambient(mut self, value: Rgba<f32>) -> Self {
+        self.ambient = value;
+        self
+    }
+
+    pub fn shadow_samples(mut self, value: usize) -> Self {
+        self.shadow_samples = value;
+        self
+    }
+
+    pub fn occluder(mut self, occluder: Occluder2d) -> Self {
+        self.occluders.push(occluder);
+        self
+    }
+
+    pub fn add_light(&mut self, light: Light2d) {
+        self.lights.push(light);
+    }
+
+    pub fn add_occluder(&mut self, occluder: Occluder2d) {
+        self.occluders.push(occluder);
+    }
+
+    /// Lit area as (origin, outline) triangle fans, one per shadow sample.
+    pub fn light_polygons(&self, light: &Light2d) -> Vec<(Vec2<f32>, Vec<Vec2<f32>>)> {
+        let bounds = light.bounding_box();
+        let segments = if light.cast_shadows {
+            self.occluders
+                .iter()
+                .flat_map(|occluder| occluder.segments.iter())
+                .filter_map(|[from, to]| clip_segment(*from, *to, bounds))
+                .collect::<Vec<_>>()
+        } else {
+            vec![]
+        };
+        let samples = if segments.is_empty() || light.softness <= 0.0 {
+            1
+        } else {
+            self.shadow_samples.max(1)
+        };
+        (0..samples)
+            .map(|index| {
+                let origin = if samples > 1 {
+                    let angle = index as f32 / samples as f32 * PI * 2.0;
+                    light.position + Vec2::new(angle.cos(), angle.sin()) * light.softness
+                } else {
+                    light.position
+                };
+                (origin, visibility_polygon(origin, bounds, &segments))
+            })
+            .collect()
+    }
+}
+
+impl GameSubsystem for Lighting2dSubsystem {
+    fn draw(&mut self, context: GameContext) {
+        let lights = std::mem::take(&mut self.lights);
+        if !self.enabled {
+            return;
+        }
+        if !context.draw.shaders.contains_key(LIGHT_SHADER)
+            && let Ok(shader) = context.graphics.shader(LIGHT_VERTEX, LIGHT_FRAGMENT)
+        {
+            context.draw.shaders.insert(LIGHT_SHADER.into(), shader);
+        }
+        if !context.draw.shaders.contains_key(COMPOSITE_SHADER)
+            && let Ok(shader) = context
+                .graphics
+                .shader(Shader::TEXTURED_VERTEX_2D, Shader::TEXTURED_FRAGMENT)
+        {
+            context.draw.shaders.insert(COMPOSITE_SHADER.into(), shader);
+        }
+        if self.canvas.is_none() {
+            self.canvas = Canvas::from_screen(vec![GlowTextureFormat::Rgba], context.graphics).ok();
+        }
+        let polygons = lights
+            .iter()
+            .map(|light| self.light_polygons(light))
+            .collect::<Vec<_>>();
+        let Some(canvas) = self.canvas.as_mut() else {
+            return;
+        };
+        let _ = canvas.match_to_screen(context.graphics);
+        let ambient = lights
+            .iter()
+            .filter(|light| light.kind == Light2dKind::Ambient)
+            .fold(self.ambient, |accum, light| {
+                accum + light.color * light.intensity
+            });
+        canvas
+            .surface_mut()
+            .set_color([ambient.r, ambient.g, ambient.b, 1.0]);
+        canvas.with(context.draw, context.graphics, true, |draw, graphics| {
+            for (light, polygons) in lights.iter().zip(polygons.iter()) {
+                let (direction, cone) = match light.kind {
+                    Light2dKind::Point => (Vec2::unit_x(), PI),
+                    Light2dKind::Spot { direction, angle } => {
+                        (direction.try_normalized().unwrap_or_default(), angle)
+                    }
+                    Light2dKind::Ambient => continue,
+                };
+                let color = light.color * light.intensity / polygons.len() as f32;
+                let batch = GraphicsBatch {
+                    shader: draw.shader(Some(&ShaderRef::name(LIGHT_SHADER))),
+                    uniforms: [
+                        (
+                            "u_projection_view".into(),
+                            GlowUniformValue::M4(
+                                graphics.state.main_camera.world_matrix().into_col_array(),
+                            ),
+                        ),
+                        (
+                            "u_direction".into(),
+                            GlowUniformValue::F2(direction.into_array()),
+                        ),
+                        ("u_cone".into(), GlowUniformValue::F1(cone)),
+                        ("u_falloff".into(), GlowUniformValue::F1(light.falloff)),
+                    ]
+                    .into_iter()
+                    .collect(),
+                    textures: vec![],
+                    blending: GlowBlending::Additive,
+                    scissor: None,
+                    wireframe: false,
+                };
+                graphics.state.stream.batch_optimized(batch);
+                for (origin, polygon) in polygons {
+                    if polygon.len() < 2 {
+                        continue;
+                    }
+                    let vertex = |position: Vec2<f32>| {
+                        let uv = (position - light.position) / light.radius.max(f32::EPSILON);
+                        Vertex {
+                            position: position.into_array(),
+                            uv: [uv.x, uv.y, 0.0],
+                            color: color.into_array(),
+                        }
+                    };
+                    let count = polygon.len() as u32;
+                    graphics.state.stream.extend(
+                        std::iter::once(vertex(*origin)).chain(polygon.iter().copied().map(vertex)),
+                        (0..count).map(|index| Triangle {
+                            a: 0,
+                            b: index + 1,
+                            c: (index + 1) % count + 1,
+                        }),
+                    );
+                }
+            }
+        });
+        if let Some(texture) =
+            canvas.sprite_texture(0, "u_image".into(), GlowTextureFiltering::Linear)
+        {
+            Sprite::single(texture)
+                .pivot([0.0, 1.0].into())
+                .scale([1.0, -1.0].into())
+                .shader(ShaderRef::name(COMPOSITE_SHADER))
+                .blending(GlowBlending::Multiply)
+                .screen_space(true)
+                .draw(context.draw, context.graphics);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn clip_segment(
+    mut from: Vec2<f32>,
+    mut to: Vec2<f32>,
+    rect: Rect<f32, f32>,
+) -> Option<(Vec2<f32>, Vec2<f32>)> {
+    let delta = to - from;
+    let mut range = (0.0f32, 1.0f32);
+    for (p, q) in [
+        (-delta.x, from.x - rect.x),
+        (delta.x, rect.x + rect.w - from.x),
+        (-delta.y, from.y - rect.y),
+        (delta.y, rect.y + rect.h - from.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            range.0 = range.0.max(q / p);
+        } else {
+            range.1 = range.1.min(q / p);
+        }
+    }
+    if range.0 > range.1 {
+        return None;
+    }
+    (from, to) = (from + delta * range.0, from + delta * range.1);
+    Some((from, to))
+}
+
+fn ray_segment(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    from: Vec2<f32>,
+    to: Vec2<f32>,
+) -> Option<f32> {
+    let edge = to - from;
+    let denominator = direction.x * edge.y - direction.y * edge.x;
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let offset = from - origin;
+    let t = (offset.x * edge.y - offset.y * edge.x) / denominator;
+    let u = (offset.x * direction.y - offset.y * direction.x) / denominator;
+    (t >= 0.0 && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+/// Polygon of area visible from origin, limited by bounds and blocked by segments.
+fn visibility_polygon(
+    origin: Vec2<f32>,
+    bounds: Rect<f32, f32>,
+    segments: &[(Vec2<f32>, Vec2<f32>)],
+) -> Vec<Vec2<f32>> {
+    let corners = [
+        Vec2::new(bounds.x, bounds.y),
+        Vec2::new(bounds.x + bounds.w, bounds.y),
+        Vec2::new(bounds.x + bounds.w, bounds.y + bounds.h),
+        Vec2::new(bounds.x, bounds.y + bounds.h),
+    ];
+    let walls = (0..4)
+        .map(|index| (corners[index], corners[(index + 1) % 4]))
+        .chain(segments.iter().copied())
+        .collect::<Vec<_>>();
+    let mut angles = corners
+        .iter()
+        .chain(segments.iter().flat_map(|(from, to)| [from, to]))
+        .flat_map(|point| {
+            let angle = (point.y - origin.y).atan2(point.x - origin.x);
+            [angle - 1.0e-4, angle, angle + 1.0e-4]
+        })
+        .collect::<Vec<_>>();
+    angles.sort_by(|a, b| a.total_cmp(b));
+    angles
+        .into_iter()
+        .filter_map(|angle| {
+            let direction = Vec2::new(angle.cos(), angle.sin());
+            let distance = walls
+                .iter()
+                .filter_map(|(from, to)| ray_segment(origin, direction, *from, *to))
+                .min_by(|a, b| a.total_cmp(b))?;
+            Some(origin + direction * distance)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lighting_occluders() {
+        let bounds = Rect::new(-10.0, -10.0, 20.0, 20.0);
+        let polygon = visibility_polygon(Vec2::zero(), bounds, &[]);
+        assert!(polygon.iter().all(|point| bounds.contains_point(*point)
+            || (point.x.abs() - 10.0).abs() < 1.0e-3
+            || (point.y.abs() - 10.0).abs() < 1.0e-3));
+
+        let wall = (Vec2::new(5.0, -20.0), Vec2::new(5.0, 20.0));
+        let wall = clip_segment(wall.0, wall.1, bounds).unwrap();
+        assert_eq!(wall, (Vec2::new(5.0, -10.0), Vec2::new(5.0, 10.0)));
+        let polygon = visibility_polygon(Vec2::zero(), bounds, &[wall]);
+        assert!(polygon.iter().all(|point| point.x <= 5.0 + 1.0e-3));
+
+        let interactible = Interactible::from_rect(Rect::new(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(
+            Occluder2d::from_interactible(&interactible).segments.len(),
+            4
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::map::{MapLayer, MapTile, ldtk::LayerInstance};
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+use vek::{Rect, Vec2};
+
+/// Pattern value matching any non-empty cell, negated matches empty cell.
+pub const MAP_AUTO_TILE_ANYTHING: i64 = 1000001;
+
+/// LDtk-like auto tile rule: square pattern of values around cell, where
+/// `0` matches anything, positive value requires that value, negative value
+/// forbids it and `MAP_AUTO_TILE_ANYTHING` matches any non-empty cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapAutoTileRule {
+    /// Odd pattern side length.
+    pub size: usize,
+    pub pattern: Vec<i64>,
+    /// Texture regions, one is picked per cell.
+    pub tiles: Vec<Rect<f32, f32>>,
+    pub page: f32,
+    /// Probability of rule being applied to matching cell.
+    pub chance: f32,
+    /// Also tries pattern mirrored horizontally, producing flipped tile.
+    pub flip_x: bool,
+    /// Also tries pattern mirrored vertically, producing flipped tile.
+    pub flip_y: bool,
+    /// Stops evaluating further rules for cell once this one matched.
+    pub break_on_match: bool,
+}
+
+impl MapAutoTileRule {
+    pub fn new(size: usize, pattern: impl IntoIterator<Item = i64>) -> Self {
+        Self {
+            size,
+            pattern: pattern.into_iter().collect(),
+            tiles: Default::default(),
+            page: 0.0,
+            chance: 1.0,
+            flip_x: false,
+            flip_y: false,
+            break_on_match: true,
+        }
+    }
+
+    /// Rule applied to every cell with given value.
+    pub fn single(value: i64) -> Self {
+        Self::new(1, [value])
+    }
+
+    pub fn tile(mut self, region: Rect<f32, f32>) -> Self {
+        self.tiles.push(region);
+        self
+    }
+
+    pub fn page(mut self, value: f32) -> Self {
+        self.page = value;
+        self
+    }
+
+    pub fn chance(mut self, value: f32) -> Self {
+        self.chance = value;
+        self
+    }
+
+    pub fn flip(mut self, x: bool, y: bool) -> Self {
+        self.flip_x = x;
+        self.flip_y = y;
+        self
+    }
+
+    pub fn break_on_match(mut self, value: bool) -> Self {
+        self.break_on_match = value;
+        self
+    }
+
+    /// Cells distance from center covered by pattern.
+    pub fn radius(&self) -> i32 {
+        self.size as i32 / 2
+    }
+
+    pub fn matches(
+        &self,
+        cell: Vec2<i32>,
+        flip_x: bool,
+        flip_y: bool,
+        value: impl Fn(Vec2<i32>) -> i64,
+    ) -> bool {
+        let radius = self.radius();
+        self.pattern.iter().enumerate().all(|(index, expected)| {
+            let mut x = (index % self.size.max(1)) as i32 - radius;
+            let mut y = (index / self.size.max(1)) as i32 - radius;
+            if flip_x {
+                x = -x;
+            }
+            if flip_y {
+                y = -y;
+            }
+            let value = value(cell + Vec2::new(x, y));
+            match *expected {
+                0 => true,
+                MAP_AUTO_TILE_ANYTHING => value != 0,
+                expected if expected == -MAP_AUTO_TILE_ANYTHING => value == 0,
+                expected if expected > 0 => value == expected,
+                expected => value != -expected,
+            }
+        })
+    }
+}
+
+/// Keeps int grid values and produces layer tiles from auto tile rules,
+/// re-evaluating cells around edits.
+#[derive(Debug, Default, Clone)]
+pub struct MapAutoTiler {
+    /// Earlier rules are drawn on top of later ones.
+    pub rules: Vec<MapAutoTileRule>,
+    pub values: HashMap<Vec2<i32>, i64>,
+    pub seed: u64,
+}
+
+impl MapAutoTiler {
+    /// Takes int grid values of LDtk IntGrid or AutoLayer source layer. Project
+    /// auto layer rules are not loaded, they have to be added with `rule`.
+    pub fn from_ldtk_int_grid(layer: &LayerInstance) -> Self {
+        Self {
+            values: layer
+                .int_grid_csv
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|(_, value)| *value != 0)
+                .map(|(index, value)| {
+                    let width = layer.c_wid.max(1) as usize;
+                    (
+                        Vec2::new((index % width) as i32, (index / width) as i32),
+                        value,
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn rule(mut self, rule: MapAutoTileRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn seed(mut self, value: u64) -> Self {
+        self.seed = value;
+        self
+    }
+
+    pub fn value(&self, cell: Vec2<i32>) -> i64 {
+        self.values.get(&cell).copied().unwrap_or_default()
+    }
+
+    pub fn set_value(&mut self, cell: Vec2<i32>, value: i64) {
+        if value == 0 {
+            self.values.remove(&cell);
+        } else {
+            self.values.insert(cell, value);
+        }
+    }
+
+    /// Largest rule radius, cells this far from edit are affected by it.
+    pub fn radius(&self) -> i32 {
+        self.rules
+            .iter()
+            .map(|rule| rule.radius())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Tiles produced by rules for cell, in draw order.
+    pub fn evaluate(&self, cell: Vec2<i32>, cell_size: Vec2<f32>) -> Vec<MapTile> {
+        let mut result = vec![];
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.tiles.is_empty() {
+                continue;
+            }
+            let variants = [(false, false), (true, false), (false, true), (true, true)];
+            let Some((flip_x, flip_y)) = variants
+                .into_iter()
+                .filter(|(flip_x, flip_y)| (!flip_x || rule.flip_x) && (!flip_y || rule.flip_y))
+                .find(|(flip_x, flip_y)| {
+                    rule.matches(cell, *flip_x, *flip_y, |cell| self.value(cell))
+                })
+            else {
+                continue;
+            };
+            let hash = self.hash(cell, index);
+            if rule.chance < 1.0 && (hash % 10000) as f32 / 10000.0 >= rule.chance {
+                continue;
+            }
+            let region = rule.tiles[(hash / 10000) as usize % rule.tiles.len()];
+            result.push(
+                MapTile::new(
+                    Rect::new(
+                        cell.x as f32 * cell_size.x,
+                        cell.y as f32 * cell_size.y,
+                        cell_size.x,
+                        cell_size.y,
+                    ),
+                    region,
+                    rule.page,
+                )
+                .flip(flip_x, flip_y),
+            );
+            if rule.break_on_match {
+                break;
+            }
+        }
+        result.reverse();
+        result
+    }
+
+    /// Rebuilds layer tiles of given cells.
+    pub fn apply(&self, layer: &mut MapLayer, cells: impl IntoIterator<Item = Vec2<i32>>) {
+        let Some(cell_size) = layer.grid_cell_size else {
+            return;
+        };
+        for cell in cells {
+            layer.set_tiles(cell, self.evaluate(cell, cell_size));
+        }
+    }
+
+    /// Changes cell value and re-evaluates tiles of all cells affected by it.
+    pub fn edit(&mut self, layer: &mut MapLayer, cell: Vec2<i32>, value: i64) {
+        self.set_value(cell, value);
+        let radius = self.radius();
+        self.apply(
+            layer,
+            (-radius..=radius)
+                .flat_map(|y| (-radius..=radius).map(move |x| cell + Vec2::new(x, y))),
+        );
+    }
+
+    fn hash(&self, cell: Vec2<i32>, rule: usize) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.seed, cell.x, cell.y, rule).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_tiler() {
+        let wall = Rect::new(0.0, 0.0, 0.5, 0.5);
+        let top = Rect::new(0.5, 0.0, 0.5, 0.5);
+        let mut tiler = MapAutoTiler::default()
+            .rule(MapAutoTileRule::new(3, [0, -1, 0, 0, 1, 0, 0, 0, 0]).tile(top))
+            .rule(MapAutoTileRule::single(1).tile(wall));
+        let mut layer = MapLayer::default().grid_cell_size(Vec2::new(16.0, 16.0));
+
+        tiler.edit(&mut layer, Vec2::new(0, 1), 1);
+        assert_eq!(layer.tile_at(Vec2::new(0, 1)).unwrap().region, top);
+
+        tiler.edit(&mut layer, Vec2::new(0, 0), 1);
+        assert_eq!(layer.tile_at(Vec2::new(0, 1)).unwrap().region, wall);
+        assert_eq!(layer.tile_at(Vec2::new(0, 0)).unwrap().region, top);
+        assert_eq!(
+            layer.tile_at(Vec2::new(0, 0)).unwrap().rectangle,
+            Rect::new(0.0, 0.0, 16.0, 16.0)
+        );
+        assert!(layer.dirty_cells().any(|cell| cell == Vec2::new(0, 1)));
+
+        tiler.edit(&mut layer, Vec2::new(0, 0), 0);
+        assert!(layer.tile_at(Vec2::new(0, 0)).is_none());
+        assert_eq!(layer.tiles.len(), 1);
+    }
+}
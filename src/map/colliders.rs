@@ -0,0 +1,244 @@
+use crate::map::{
+    LdtkMapColliderResult, MapCollider,
+    grid_world::{GridWorld, GridWorldLayout},
+    ldtk::{LayerInstance, Ldtk},
+};
+use std::collections::HashMap;
+use vek::{Rect, Vec2};
+
+/// Grid of collider masks, `0` marks empty cell.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MapColliderGrid {
+    pub size: Vec2<usize>,
+    pub cell_size: Vec2<f32>,
+    /// Position of grid top-left corner.
+    pub offset: Vec2<f32>,
+    cells: Vec<u32>,
+}
+
+impl MapColliderGrid {
+    pub fn new(size: Vec2<usize>, cell_size: Vec2<f32>) -> Self {
+        Self {
+            size,
+            cell_size,
+            offset: Default::default(),
+            cells: vec![0; size.x * size.y],
+        }
+    }
+
+    pub fn offset(mut self, value: Vec2<f32>) -> Self {
+        self.offset = value;
+        self
+    }
+
+    /// Collects IntGrid layer values in level space, applying the same
+    /// extractor rules as `LdtkMapBuilder`.
+    pub fn from_ldtk_int_grid(
+        ldtk: &Ldtk,
+        layer: &LayerInstance,
+        pixel_world_scale: f32,
+        extractor: impl Fn(&str) -> LdtkMapColliderResult,
+    ) -> Self {
+        let mut result = Self::new(
+            Vec2::new(layer.c_wid.max(0) as usize, layer.c_hei.max(0) as usize),
+            Vec2::broadcast(layer.grid_size as f32 * pixel_world_scale),
+        )
+        .offset(
+            Vec2::new(
+                layer.px_total_offset_x as f32,
+                layer.px_total_offset_y as f32,
+            ) * pixel_world_scale,
+        );
+        let Some(layer_definition) = ldtk
+            .defs
+            .layers
+            .iter()
+            .find(|definition| definition.uid == layer.layer_def_uid)
+        else {
+            return result;
+        };
+        for (index, value) in layer.int_grid_csv.iter().enumerate() {
+            let Some(value_id) = layer_definition
+                .int_grid_values
+                .iter()
+                .find(|definition| definition.value == *value)
+                .and_then(|definition| definition.identifier.as_deref())
+            else {
+                continue;
+            };
+            if let Some(mask) = extractor(value_id).mask() {
+                result.cells[index] = mask;
+            }
+        }
+        result
+    }
+
+    /// Takes collider cells of grid world, all with full mask. Returns
+    /// `None` for non-orthogonal layouts, which cells are not rectangles.
+    pub fn from_grid_world(world: &GridWorld) -> Option<Self> {
+        if world.layout != GridWorldLayout::Orthogonal {
+            return None;
+        }
+        let size = world
+            .layers()
+            .first()
+            .map(|layer| layer.tilemap.size())
+            .unwrap_or_default();
+        let mut result =
+            Self::new(size, world.tile_size).offset(world.local_to_world(Vec2::zero()));
+        for y in 0..size.y {
+            for x in 0..size.x {
+                if world.collider(Vec2::new(x, y)) {
+                    result.set(Vec2::new(x, y), u32::MAX);
+                }
+            }
+        }
+        Some(result)
+    }
+
+    pub fn get(&self, location: Vec2<usize>) -> u32 {
+        if location.x < self.size.x && location.y < self.size.y {
+            self.cells[location.y * self.size.x + location.x]
+        } else {
+            0
+        }
+    }
+
+    pub fn set(&mut self, location: Vec2<usize>, mask: u32) {
+        if location.x < self.size.x && location.y < self.size.y {
+            self.cells[location.y * self.size.x + location.x] = mask;
+        }
+    }
+
+    /// Colliders of cells in grid space, merged with `MapCollider::merge`.
+    pub fn colliders(&self) -> Vec<MapCollider> {
+        MapCollider::merge((0..self.size.y).flat_map(|y| {
+            (0..self.size.x).filter_map(move |x| {
+                let mask = self.get(Vec2::new(x, y));
+                (mask != 0).then(|| {
+                    MapCollider::new(
+                        Rect::new(
+                            self.offset.x + x as f32 * self.cell_size.x,
+                            self.offset.y + y as f32 * self.cell_size.y,
+                            self.cell_size.x,
+                            self.cell_size.y,
+                        ),
+                        mask,
+                    )
+                })
+            })
+        }))
+    }
+
+    /// Closed outlines of cells matching mask, traced along cell edges like
+    /// marching squares without interpolation. Solid areas wind clockwise
+    /// (in Y-down space) and holes counter-clockwise, collinear points are
+    /// removed.
+    pub fn outlines(&self, mask: u32) -> Vec<Vec<Vec2<f32>>> {
+        let solid = |x: isize, y: isize| {
+            x >= 0 && y >= 0 && self.get(Vec2::new(x as usize, y as usize)) & mask != 0
+        };
+        let mut edges = HashMap::<Vec2<isize>, Vec<Vec2<isize>>>::default();
+        for y in 0..self.size.y as isize {
+            for x in 0..self.size.x as isize {
+                if !solid(x, y) {
+                    continue;
+                }
+                let corners = [
+                    Vec2::new(x, y),
+                    Vec2::new(x + 1, y),
+                    Vec2::new(x + 1, y + 1),
+                    Vec2::new(x, y + 1),
+                ];
+                let neighbours = [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)];
+                for (side, (nx, ny)) in neighbours.into_iter().enumerate() {
+                    if !solid(nx, ny) {
+                        edges
+                            .entry(corners[side])
+                            .or_default()
+                            .push(corners[(side + 1) % 4]);
+                    }
+                }
+            }
+        }
+        let mut starts = edges.keys().copied().collect::<Vec<_>>();
+        starts.sort_by_key(|point| (point.y, point.x));
+        let mut result = vec![];
+        for start in starts {
+            while let Some(mut next) = edges.get_mut(&start).and_then(|ends| ends.pop()) {
+                let mut outline = vec![start];
+                let mut current = start;
+                while next != start {
+                    outline.push(next);
+                    let direction = next - current;
+                    current = next;
+                    let Some(ends) = edges.get_mut(&current).filter(|ends| !ends.is_empty()) else {
+                        break;
+                    };
+                    // On touching corners prefer turning right, keeping areas separate.
+                    let right = Vec2::new(-direction.y, direction.x);
+                    let index = ends
+                        .iter()
+                        .position(|end| *end - current == right)
+                        .unwrap_or(ends.len() - 1);
+                    next = ends.swap_remove(index);
+                }
+                result.push(self.simplify(&outline));
+            }
+        }
+        result
+    }
+
+    fn simplify(&self, outline: &[Vec2<isize>]) -> Vec<Vec2<f32>> {
+        let count = outline.len();
+        (0..count)
+            .filter(|index| {
+                let previous = outline[(index + count - 1) % count];
+                let current = outline[*index];
+                let next = outline[(index + 1) % count];
+                let a = current - previous;
+                let b = next - current;
+                a.x * b.y - a.y * b.x != 0
+            })
+            .map(|index| {
+                let point = outline[index];
+                self.offset + Vec2::new(point.x as f32, point.y as f32) * self.cell_size
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collider_grid() {
+        let mut grid = MapColliderGrid::new(Vec2::new(4, 3), Vec2::new(2.0, 2.0));
+        for (x, y) in [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (3, 2)] {
+            grid.set(Vec2::new(x, y), 1);
+        }
+        assert_eq!(
+            grid.colliders()
+                .into_iter()
+                .map(|collider| (collider.rectangle, collider.mask))
+                .collect::<Vec<_>>(),
+            vec![
+                (Rect::new(0.0, 0.0, 6.0, 4.0), 1),
+                (Rect::new(6.0, 4.0, 2.0, 2.0), 1)
+            ]
+        );
+        let outlines = grid.outlines(1);
+        assert_eq!(outlines.len(), 2);
+        assert_eq!(
+            outlines[0],
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(6.0, 0.0),
+                Vec2::new(6.0, 4.0),
+                Vec2::new(0.0, 4.0),
+            ]
+        );
+        assert_eq!(outlines[1].len(), 4);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::map::animation_frame_index;
 use randscape::grid::Grid;
 use spitfire_draw::{
     context::DrawContext,
@@ -5,7 +6,12 @@ use spitfire_draw::{
     utils::{Drawable, Vertex},
 };
 use spitfire_glow::graphics::GraphicsTarget;
-use std::{any::Any, ops::Range};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    ops::Range,
+    sync::RwLock,
+};
 use vek::{Rect, Vec2};
 
 pub trait GridWorldEmitterFilter: Any {
@@ -23,13 +29,35 @@ pub struct InRangeFilter {
     pub location: Vec2<usize>,
     pub range: usize,
     pub clear_outside: bool,
+    pub layout: GridWorldLayout,
+}
+
+impl InRangeFilter {
+    pub fn new(location: Vec2<usize>, range: usize) -> Self {
+        Self {
+            location,
+            range,
+            clear_outside: false,
+            layout: GridWorldLayout::Orthogonal,
+        }
+    }
+
+    pub fn clear_outside(mut self, value: bool) -> Self {
+        self.clear_outside = value;
+        self
+    }
+
+    pub fn layout(mut self, value: GridWorldLayout) -> Self {
+        self.layout = value;
+        self
+    }
 }
 
 impl GridWorldEmitterFilter for InRangeFilter {
     fn filter(&self, tile: &TileInstance) -> bool {
         let tile_location = tile.location.numcast::<isize>().unwrap();
         let location = self.location.numcast::<isize>().unwrap();
-        let status = tile_location.distance_squared(location) as usize > self.range * self.range;
+        let status = self.layout.distance(tile_location, location) > self.range as f32;
         status != self.clear_outside
     }
 }
@@ -72,15 +100,322 @@ impl GridWorldLayer {
     }
 }
 
+/// Looping sequence of tileset item IDs, as (id, duration in seconds).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GridWorldTileAnimation {
+    pub frames: Vec<(usize, f32)>,
+}
+
+impl GridWorldTileAnimation {
+    pub fn new(frames: impl IntoIterator<Item = (usize, f32)>) -> Self {
+        Self {
+            frames: frames.into_iter().collect(),
+        }
+    }
+
+    pub fn frame(mut self, id: usize, duration: f32) -> Self {
+        self.frames.push((id, duration));
+        self
+    }
+
+    pub fn frame_at(&self, time: f32) -> Option<usize> {
+        animation_frame_index(self.frames.iter().map(|(_, duration)| *duration), time)
+            .map(|index| self.frames[index].0)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GridWorldStaggerIndex {
+    /// Odd rows (or columns) are shifted by half a tile.
+    #[default]
+    Odd,
+    /// Even rows (or columns) are shifted by half a tile.
+    Even,
+}
+
+impl GridWorldStaggerIndex {
+    fn is_shifted(self, value: isize) -> bool {
+        (value.rem_euclid(2) == 1) == (self == Self::Odd)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GridWorldHexOrientation {
+    /// Rows of hexes with pointy top, rows are staggered.
+    #[default]
+    Pointy,
+    /// Columns of hexes with flat top, columns are staggered.
+    Flat,
+}
+
+/// Placement of grid cells, `tile_size` is always size of cell bounding box.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GridWorldLayout {
+    #[default]
+    Orthogonal,
+    /// Diamond shaped map, cell (0, 0) at the top.
+    IsometricDiamond,
+    /// Rectangular map of diamond cells, with staggered rows.
+    IsometricStaggered(GridWorldStaggerIndex),
+    /// Hexes with offset coordinates as grid locations.
+    Hexagonal(GridWorldHexOrientation, GridWorldStaggerIndex),
+}
+
+impl GridWorldLayout {
+    /// Top-left corner of cell bounding box in layout space.
+    pub fn cell_position(&self, location: Vec2<isize>, tile_size: Vec2<f32>) -> Vec2<f32> {
+        self.lattice_position(location).as_::<f32>() * tile_size
+            / self.lattice_divisor().as_::<f32>()
+    }
+
+    /// Cell shape corners in layout space, clockwise in Y down space.
+    pub fn cell_polygon(&self, location: Vec2<isize>, tile_size: Vec2<f32>) -> Vec<Vec2<f32>> {
+        let step = tile_size / self.lattice_divisor().as_::<f32>();
+        self.lattice_polygon(location)
+            .map(|point| point.as_::<f32>() * step)
+            .collect()
+    }
+
+    pub fn cell_center(&self, location: Vec2<isize>, tile_size: Vec2<f32>) -> Vec2<f32> {
+        self.cell_position(location, tile_size) + tile_size * 0.5
+    }
+
+    /// Grid location of cell containing point in layout space.
+    pub fn locate(&self, point: Vec2<f32>, tile_size: Vec2<f32>) -> Vec2<isize> {
+        let (w, h) = tile_size.into_tuple();
+        match *self {
+            Self::Orthogonal => Vec2::new((point.x / w).floor() as _, (point.y / h).floor() as _),
+            Self::IsometricDiamond => {
+                let a = point.x / (w * 0.5) - 1.0;
+                let b = point.y / (h * 0.5);
+                Vec2::new(((a + b) * 0.5).floor() as _, ((b - a) * 0.5).floor() as _)
+            }
+            Self::IsometricStaggered(_) | Self::Hexagonal(..) => {
+                let (column, row) = match *self {
+                    Self::IsometricStaggered(_) => (w, h * 0.5),
+                    Self::Hexagonal(GridWorldHexOrientation::Pointy, _) => (w, h * 0.75),
+                    _ => (w * 0.75, h),
+                };
+                let scale = match *self {
+                    Self::IsometricStaggered(_) => Vec2::new(1.0 / w, 1.0 / h),
+                    Self::Hexagonal(GridWorldHexOrientation::Pointy, _) => {
+                        Vec2::new(1.0, w / h * 2.0 / 3.0f32.sqrt())
+                    }
+                    _ => Vec2::new(h / w * 2.0 / 3.0f32.sqrt(), 1.0),
+                };
+                let guess = Vec2::new(
+                    (point.x / column).floor() as isize,
+                    (point.y / row).floor() as isize,
+                );
+                let metric = |location: Vec2<isize>| {
+                    let delta = (point - self.cell_center(location, tile_size)) * scale;
+                    match self {
+                        Self::IsometricStaggered(_) => delta.x.abs() + delta.y.abs(),
+                        _ => delta.magnitude_squared(),
+                    }
+                };
+                (-1..=1)
+                    .flat_map(|y| (-1..=1).map(move |x| guess + Vec2::new(x, y)))
+                    .min_by(|a, b| metric(*a).total_cmp(&metric(*b)))
+                    .unwrap_or(guess)
+            }
+        }
+    }
+
+    /// Bounding box of map cells in layout space.
+    pub fn bounds(&self, size: Vec2<usize>, tile_size: Vec2<f32>) -> Rect<f32, f32> {
+        if size.x == 0 || size.y == 0 {
+            return Rect::new(0.0, 0.0, 0.0, 0.0);
+        }
+        let size = size.as_::<isize>();
+        let border = (0..size.x)
+            .flat_map(|x| [Vec2::new(x, 0), Vec2::new(x, size.y - 1)])
+            .chain((0..size.y).flat_map(|y| [Vec2::new(0, y), Vec2::new(size.x - 1, y)]));
+        let mut result = None::<Rect<f32, f32>>;
+        for location in border {
+            let position = self.cell_position(location, tile_size);
+            let rectangle = Rect::new(position.x, position.y, tile_size.x, tile_size.y);
+            result = Some(match result {
+                Some(result) => result.union(rectangle),
+                None => rectangle,
+            });
+        }
+        result.unwrap_or_default()
+    }
+
+    /// Hexagonal axial coordinates of offset location, other layouts keep it.
+    pub fn offset_to_axial(&self, location: Vec2<isize>) -> Vec2<isize> {
+        let Self::Hexagonal(orientation, index) = *self else {
+            return location;
+        };
+        let (x, y) = location.into_tuple();
+        match (orientation, index) {
+            (GridWorldHexOrientation::Pointy, GridWorldStaggerIndex::Odd) => {
+                Vec2::new(x - (y - y.rem_euclid(2)) / 2, y)
+            }
+            (GridWorldHexOrientation::Pointy, GridWorldStaggerIndex::Even) => {
+                Vec2::new(x - (y + y.rem_euclid(2)) / 2, y)
+            }
+            (GridWorldHexOrientation::Flat, GridWorldStaggerIndex::Odd) => {
+                Vec2::new(x, y - (x - x.rem_euclid(2)) / 2)
+            }
+            (GridWorldHexOrientation::Flat, GridWorldStaggerIndex::Even) => {
+                Vec2::new(x, y - (x + x.rem_euclid(2)) / 2)
+            }
+        }
+    }
+
+    pub fn axial_to_offset(&self, axial: Vec2<isize>) -> Vec2<isize> {
+        let Self::Hexagonal(orientation, index) = *self else {
+            return axial;
+        };
+        let (q, r) = axial.into_tuple();
+        match (orientation, index) {
+            (GridWorldHexOrientation::Pointy, GridWorldStaggerIndex::Odd) => {
+                Vec2::new(q + (r - r.rem_euclid(2)) / 2, r)
+            }
+            (GridWorldHexOrientation::Pointy, GridWorldStaggerIndex::Even) => {
+                Vec2::new(q + (r + r.rem_euclid(2)) / 2, r)
+            }
+            (GridWorldHexOrientation::Flat, GridWorldStaggerIndex::Odd) => {
+                Vec2::new(q, r + (q - q.rem_euclid(2)) / 2)
+            }
+            (GridWorldHexOrientation::Flat, GridWorldStaggerIndex::Even) => {
+                Vec2::new(q, r + (q + q.rem_euclid(2)) / 2)
+            }
+        }
+    }
+
+    /// Adjacent cells: 4 sides for square and diamond cells, 6 for hexes.
+    pub fn neighbours(&self, location: Vec2<isize>) -> Vec<Vec2<isize>> {
+        const SIDES: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+        const HEX_SIDES: [(isize, isize); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+        match self {
+            Self::Orthogonal | Self::IsometricDiamond => SIDES
+                .iter()
+                .map(|(x, y)| location + Vec2::new(*x, *y))
+                .collect(),
+            Self::IsometricStaggered(_) => {
+                let diamond = self.staggered_to_diamond(location);
+                SIDES
+                    .iter()
+                    .map(|(x, y)| self.diamond_to_staggered(diamond + Vec2::new(*x, *y)))
+                    .collect()
+            }
+            Self::Hexagonal(..) => {
+                let axial = self.offset_to_axial(location);
+                HEX_SIDES
+                    .iter()
+                    .map(|(q, r)| self.axial_to_offset(axial + Vec2::new(*q, *r)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Euclidean distance in cells for square and diamond cells, number of
+    /// steps for hexes.
+    pub fn distance(&self, from: Vec2<isize>, to: Vec2<isize>) -> f32 {
+        match self {
+            Self::Orthogonal | Self::IsometricDiamond => (to - from).as_::<f32>().magnitude(),
+            Self::IsometricStaggered(_) => (self.staggered_to_diamond(to)
+                - self.staggered_to_diamond(from))
+            .as_::<f32>()
+            .magnitude(),
+            Self::Hexagonal(..) => {
+                let delta = self.offset_to_axial(to) - self.offset_to_axial(from);
+                ((delta.x.abs() + delta.y.abs() + (delta.x + delta.y).abs()) / 2) as f32
+            }
+        }
+    }
+
+    /// Number of lattice steps per cell bounding box, cell positions always
+    /// land on whole steps.
+    fn lattice_divisor(&self) -> Vec2<isize> {
+        match self {
+            Self::Orthogonal => Vec2::new(1, 1),
+            Self::IsometricDiamond | Self::IsometricStaggered(_) => Vec2::new(2, 2),
+            Self::Hexagonal(GridWorldHexOrientation::Pointy, _) => Vec2::new(2, 4),
+            Self::Hexagonal(GridWorldHexOrientation::Flat, _) => Vec2::new(4, 2),
+        }
+    }
+
+    /// Cell shape corners in lattice steps.
+    fn lattice_polygon(&self, location: Vec2<isize>) -> impl Iterator<Item = Vec2<isize>> {
+        let corners: &[(isize, isize)] = match self {
+            Self::Orthogonal => &[(0, 0), (1, 0), (1, 1), (0, 1)],
+            Self::IsometricDiamond | Self::IsometricStaggered(_) => {
+                &[(1, 0), (2, 1), (1, 2), (0, 1)]
+            }
+            Self::Hexagonal(GridWorldHexOrientation::Pointy, _) => {
+                &[(1, 0), (2, 1), (2, 3), (1, 4), (0, 3), (0, 1)]
+            }
+            Self::Hexagonal(GridWorldHexOrientation::Flat, _) => {
+                &[(1, 0), (3, 0), (4, 1), (3, 2), (1, 2), (0, 1)]
+            }
+        };
+        let position = self.lattice_position(location);
+        corners
+            .iter()
+            .map(move |(x, y)| position + Vec2::new(*x, *y))
+    }
+
+    /// Cell position in lattice steps.
+    fn lattice_position(&self, location: Vec2<isize>) -> Vec2<isize> {
+        let (x, y) = location.into_tuple();
+        match *self {
+            Self::Orthogonal => Vec2::new(x, y),
+            Self::IsometricDiamond => Vec2::new(x - y, x + y),
+            Self::IsometricStaggered(index) => Vec2::new(2 * x + index.is_shifted(y) as isize, y),
+            Self::Hexagonal(GridWorldHexOrientation::Pointy, index) => {
+                Vec2::new(2 * x + index.is_shifted(y) as isize, 3 * y)
+            }
+            Self::Hexagonal(GridWorldHexOrientation::Flat, index) => {
+                Vec2::new(3 * x, 2 * y + index.is_shifted(x) as isize)
+            }
+        }
+    }
+
+    fn stagger_delta(&self, y: isize) -> isize {
+        match self {
+            Self::IsometricStaggered(GridWorldStaggerIndex::Even) => -y.rem_euclid(2),
+            _ => y.rem_euclid(2),
+        }
+    }
+
+    fn staggered_to_diamond(&self, location: Vec2<isize>) -> Vec2<isize> {
+        let sum = location.y;
+        let difference = 2 * location.x + self.stagger_delta(location.y);
+        Vec2::new((sum + difference) / 2, (sum - difference) / 2)
+    }
+
+    fn diamond_to_staggered(&self, location: Vec2<isize>) -> Vec2<isize> {
+        let y = location.x + location.y;
+        Vec2::new((location.x - location.y - self.stagger_delta(y)) / 2, y)
+    }
+}
+
 pub struct GridWorld {
     pub position: Vec2<f32>,
     pub pivot: Vec2<f32>,
     pub tile_size: Vec2<f32>,
+    pub layout: GridWorldLayout,
     pub tileset: TileSet,
     pub visible_layers: Range<usize>,
+    /// Animations keyed by tileset item ID they replace.
+    pub animations: HashMap<usize, GridWorldTileAnimation>,
+    /// Time in seconds used to pick animated tiles frames.
+    pub time: f32,
     map_layers: Vec<GridWorldLayer>,
     tile_instances: Vec<TileInstance>,
     colliders: Grid<u8>,
+    draw_order: RwLock<Option<GridWorldDrawOrder>>,
+}
+
+/// Map locations sorted back to front for non-orthogonal layouts.
+struct GridWorldDrawOrder {
+    layout: GridWorldLayout,
+    size: Vec2<usize>,
+    locations: Vec<Vec2<usize>>,
 }
 
 impl GridWorld {
@@ -90,11 +425,15 @@ impl GridWorld {
             position: Default::default(),
             pivot: Default::default(),
             tile_size,
+            layout: Default::default(),
             tileset,
             visible_layers: 0..1,
+            animations: Default::default(),
+            time: 0.0,
             map_layers: vec![terrain_layer],
             tile_instances: Default::default(),
             colliders: Grid::new(size, 0),
+            draw_order: Default::default(),
         }
     }
 
@@ -127,6 +466,25 @@ impl GridWorld {
         self
     }
 
+    pub fn with_layout(mut self, value: GridWorldLayout) -> Self {
+        self.layout = value;
+        self
+    }
+
+    pub fn with_animation(mut self, id: usize, animation: GridWorldTileAnimation) -> Self {
+        self.animations.insert(id, animation);
+        self
+    }
+
+    pub fn with_time(mut self, value: f32) -> Self {
+        self.time = value;
+        self
+    }
+
+    pub fn advance_time(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
     pub fn with_layer(mut self, layer: GridWorldLayer) -> Self {
         if layer.tilemap.size() == self.map_layers[0].tilemap.size() {
             self.map_layers.push(layer);
@@ -230,10 +588,10 @@ impl GridWorld {
 
     pub fn world_to_local(&self, location: Vec2<f32>) -> Option<Vec2<usize>> {
         let size = self.map_layers[0].tilemap.size();
-        let result = location - self.position
-            + Vec2::new(size.x as f32, size.y as f32) * self.tile_size * self.pivot;
-        let result = result / self.tile_size;
-        if result.x >= 0.0 && result.y >= 0.0 {
+        let result = self
+            .layout
+            .locate(location - self.layout_origin(), self.tile_size);
+        if result.x >= 0 && result.y >= 0 {
             let result = Vec2::new(result.x as usize, result.y as usize);
             if result.x < size.x && result.y < size.y {
                 return Some(result);
@@ -242,15 +600,200 @@ impl GridWorld {
         None
     }
 
+    /// Top-left corner of cell bounding box in world space.
     pub fn local_to_world(&self, location: Vec2<usize>) -> Vec2<f32> {
+        self.layout.cell_position(location.as_(), self.tile_size) + self.layout_origin()
+    }
+
+    /// Adjacent cell locations within map bounds.
+    pub fn neighbours(&self, location: Vec2<usize>) -> impl Iterator<Item = Vec2<usize>> {
+        let size = self.map_layers[0].tilemap.size();
+        self.layout
+            .neighbours(location.as_())
+            .into_iter()
+            .filter(move |location| {
+                location.x >= 0
+                    && location.y >= 0
+                    && (location.x as usize) < size.x
+                    && (location.y as usize) < size.y
+            })
+            .map(|location| location.as_())
+    }
+
+    pub fn distance(&self, from: Vec2<usize>, to: Vec2<usize>) -> f32 {
+        self.layout.distance(from.as_(), to.as_())
+    }
+
+    /// Outer edges of collider cell shapes in world space, edges shared by
+    /// two collider cells are skipped.
+    pub fn collider_edges(&self) -> Vec<[Vec2<f32>; 2]> {
+        let edges = self
+            .locations_iter()
+            .filter(|location| self.collider(*location))
+            .flat_map(|location| {
+                let corners = self
+                    .layout
+                    .lattice_polygon(location.as_())
+                    .collect::<Vec<_>>();
+                (0..corners.len())
+                    .map(move |index| (corners[index], corners[(index + 1) % corners.len()]))
+            })
+            .collect::<Vec<_>>();
+        let shared = edges
+            .iter()
+            .map(|(from, to)| (*to, *from))
+            .collect::<HashSet<_>>();
+        let origin = self.layout_origin();
+        let step = self.tile_size / self.layout.lattice_divisor().as_::<f32>();
+        edges
+            .into_iter()
+            .filter(|edge| !shared.contains(edge))
+            .map(|(from, to)| {
+                [
+                    origin + from.as_::<f32>() * step,
+                    origin + to.as_::<f32>() * step,
+                ]
+            })
+            .collect()
+    }
+
+    /// World position of layout space origin, respecting position and pivot.
+    fn layout_origin(&self) -> Vec2<f32> {
+        let size = self.map_layers[0].tilemap.size();
+        let bounds = self.layout.bounds(size, self.tile_size);
+        self.position - bounds.position() - Vec2::from(bounds.extent()) * self.pivot
+    }
+
+    fn ensure_draw_order(&self) {
+        let size = self.map_layers[0].tilemap.size();
+        if self
+            .draw_order
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|order| order.layout == self.layout && order.size == size)
+        {
+            return;
+        }
+        let mut locations = self.locations_iter().collect::<Vec<_>>();
+        locations.sort_by_key(|location| {
+            let position = self.layout.lattice_position(location.as_());
+            (position.y, position.x)
+        });
+        *self.draw_order.write().unwrap() = Some(GridWorldDrawOrder {
+            layout: self.layout,
+            size,
+            locations,
+        });
+    }
+
+    fn draw_layout(&self, context: &mut DrawContext, graphics: &mut dyn GraphicsTarget<Vertex>) {
+        self.ensure_draw_order();
+        let view = graphics.state().main_camera.world_rectangle();
+        let origin = self.layout_origin();
         let size = self.map_layers[0].tilemap.size();
-        Vec2::new(location.x as f32, location.y as f32) * self.tile_size + self.position
-            - Vec2::new(size.x as f32, size.y as f32) * self.tile_size * self.pivot
+        let divisor = self.layout.lattice_divisor();
+        let mut tileset = self.tileset.clone();
+        for item in tileset.mappings.values_mut() {
+            item.size *= divisor.as_();
+            item.offset *= divisor;
+        }
+        let margin = self
+            .tileset
+            .mappings
+            .values()
+            .fold(Vec2::<f32>::zero(), |result, item| {
+                let extent = (item.offset.map(|value| value.abs()) + item.size.as_()).as_::<f32>();
+                Vec2::partial_max(result, extent)
+            })
+            * self.tile_size;
+        let is_visible = |location: Vec2<usize>, offset: Vec2<isize>| {
+            let position = origin
+                + self.layout.cell_position(location.as_(), self.tile_size)
+                + offset.as_::<f32>() * self.tile_size;
+            view.collides_with_rect(Rect::new(
+                position.x - margin.x,
+                position.y - margin.y,
+                self.tile_size.x + margin.x * 2.0,
+                self.tile_size.y + margin.y * 2.0,
+            ))
+        };
+        let mut instances = HashMap::<Vec2<usize>, Vec<TileInstance>>::new();
+        for instance in &self.tile_instances {
+            if instance.location.x < size.x
+                && instance.location.y < size.y
+                && is_visible(instance.location, instance.offset)
+            {
+                instances
+                    .entry(instance.location)
+                    .or_default()
+                    .push(*instance);
+            }
+        }
+        let layers = self
+            .visible_layers
+            .clone()
+            .filter_map(|index| self.map_layers.get(index))
+            .filter(|layer| layer.visible)
+            .collect::<Vec<_>>();
+        let draw_order = self.draw_order.read().unwrap();
+        let locations = draw_order
+            .as_ref()
+            .map(|order| order.locations.as_slice())
+            .unwrap_or_default();
+        let tiles = locations
+            .iter()
+            .copied()
+            .flat_map(|location| {
+                let tiles = if is_visible(location, Vec2::zero()) {
+                    layers.as_slice()
+                } else {
+                    &[]
+                };
+                tiles
+                    .iter()
+                    .filter_map(move |layer| {
+                        let id = layer.tilemap.get(location)?;
+                        let tile = TileInstance::new(id, location);
+                        (layer.tilemap.is_id_valid(id) && layer.filter.filter(&tile))
+                            .then_some(tile)
+                    })
+                    .chain(instances.get(&location).into_iter().flatten().copied())
+            })
+            .chain(self.tile_instances.iter().copied().filter(|instance| {
+                (instance.location.x >= size.x || instance.location.y >= size.y)
+                    && is_visible(instance.location, instance.offset)
+            }))
+            .map(|mut tile| {
+                if let Some(id) = self
+                    .animations
+                    .get(&tile.id)
+                    .and_then(|animation| animation.frame_at(self.time))
+                {
+                    tile.id = id;
+                }
+                TileInstance {
+                    id: tile.id,
+                    location: Vec2::zero(),
+                    offset: self.layout.lattice_position(tile.location.as_())
+                        + tile.offset * divisor,
+                }
+            });
+
+        TilesEmitter::default()
+            .position(origin)
+            .tile_size(self.tile_size / divisor.as_::<f32>())
+            .emit(&tileset, tiles)
+            .draw(context, graphics);
     }
 }
 
 impl Drawable for GridWorld {
     fn draw(&self, context: &mut DrawContext, graphics: &mut dyn GraphicsTarget<Vertex>) {
+        if self.layout != GridWorldLayout::Orthogonal {
+            self.draw_layout(context, graphics);
+            return;
+        }
         let size = self.map_layers[0].tilemap.size();
         let rectangle = graphics.state().main_camera.world_rectangle();
         let offset = (rectangle.position() - self.position) / self.tile_size;
@@ -296,8 +839,141 @@ impl Drawable for GridWorld {
                                     .unwrap_or_default()
                             })
                             .cloned(),
-                    ),
+                    )
+                    .map(|mut tile| {
+                        if let Some(id) = self
+                            .animations
+                            .get(&tile.id)
+                            .and_then(|animation| animation.frame_at(self.time))
+                        {
+                            tile.id = id;
+                        }
+                        tile
+                    }),
             )
             .draw(context, graphics);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::colliders::MapColliderGrid;
+
+    #[test]
+    fn test_grid_world_layouts() {
+        let tile_size = Vec2::new(32.0, 16.0);
+        let layout = GridWorldLayout::IsometricDiamond;
+        assert_eq!(
+            layout.cell_position(Vec2::new(1, 0), tile_size),
+            Vec2::new(16.0, 8.0)
+        );
+        assert_eq!(
+            layout.locate(Vec2::new(32.0, 16.0), tile_size),
+            Vec2::new(1, 0)
+        );
+
+        let layout = GridWorldLayout::IsometricStaggered(GridWorldStaggerIndex::Odd);
+        for location in [Vec2::new(0, 0), Vec2::new(2, 1), Vec2::new(1, 4)] {
+            let center = layout.cell_center(location, tile_size);
+            assert_eq!(layout.locate(center, tile_size), location);
+        }
+        assert_eq!(layout.neighbours(Vec2::new(1, 2)).len(), 4);
+        assert!(
+            layout
+                .neighbours(Vec2::new(1, 2))
+                .contains(&Vec2::new(1, 1))
+        );
+        assert!(
+            layout
+                .neighbours(Vec2::new(1, 2))
+                .contains(&Vec2::new(0, 1))
+        );
+
+        for orientation in [
+            GridWorldHexOrientation::Pointy,
+            GridWorldHexOrientation::Flat,
+        ] {
+            for index in [GridWorldStaggerIndex::Odd, GridWorldStaggerIndex::Even] {
+                let layout = GridWorldLayout::Hexagonal(orientation, index);
+                let tile_size = Vec2::new(32.0, 32.0);
+                for y in -3..3 {
+                    for x in -3..3 {
+                        let location = Vec2::new(x, y);
+                        let axial = layout.offset_to_axial(location);
+                        assert_eq!(layout.axial_to_offset(axial), location);
+                        let center = layout.cell_center(location, tile_size);
+                        assert_eq!(layout.locate(center, tile_size), location);
+                        for neighbour in layout.neighbours(location) {
+                            assert_eq!(layout.distance(location, neighbour), 1.0);
+                        }
+                    }
+                }
+                assert_eq!(layout.distance(Vec2::new(0, 0), Vec2::new(3, 0)), 3.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_world_draw_order() {
+        let layer = GridWorldLayer::new_filtered(
+            TileMap::new(Vec2::new(3, 3), 0),
+            true,
+            InRangeFilter::new(Vec2::new(1, 1), 1),
+        );
+        let filter = layer.access_filter::<InRangeFilter>().unwrap();
+        assert_eq!(filter.layout, GridWorldLayout::Orthogonal);
+        assert!(!filter.clear_outside);
+
+        let mut world = GridWorld::new(Vec2::new(32.0, 16.0), TileSet::default(), layer)
+            .with_layout(GridWorldLayout::IsometricDiamond);
+        world.ensure_draw_order();
+        {
+            let order = world.draw_order.read().unwrap();
+            let locations = &order.as_ref().unwrap().locations;
+            assert_eq!(locations[0], Vec2::new(0, 0));
+            assert_eq!(&locations[1..3], &[Vec2::new(0, 1), Vec2::new(1, 0)]);
+            assert_eq!(locations[8], Vec2::new(2, 2));
+        }
+
+        world.layout = GridWorldLayout::IsometricStaggered(GridWorldStaggerIndex::Odd);
+        world.ensure_draw_order();
+        let order = world.draw_order.read().unwrap();
+        let locations = &order.as_ref().unwrap().locations;
+        assert_eq!(
+            &locations[0..4],
+            &[
+                Vec2::new(0, 0),
+                Vec2::new(1, 0),
+                Vec2::new(2, 0),
+                Vec2::new(0, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grid_world_collider_edges() {
+        let layer = GridWorldLayer::new(TileMap::new(Vec2::new(2, 1), 0), true);
+        let world = GridWorld::new(Vec2::new(32.0, 16.0), TileSet::default(), layer)
+            .with_layout(GridWorldLayout::IsometricDiamond)
+            .with_collider(Vec2::new(0, 0))
+            .with_collider(Vec2::new(1, 0));
+        assert_eq!(
+            world.layout.cell_polygon(Vec2::new(1, 0), world.tile_size),
+            vec![
+                Vec2::new(32.0, 8.0),
+                Vec2::new(48.0, 16.0),
+                Vec2::new(32.0, 24.0),
+                Vec2::new(16.0, 16.0)
+            ]
+        );
+        assert_eq!(world.collider_edges().len(), 6);
+        assert!(MapColliderGrid::from_grid_world(&world).is_none());
+
+        let world = world.with_layout(GridWorldLayout::Hexagonal(
+            GridWorldHexOrientation::Pointy,
+            GridWorldStaggerIndex::Odd,
+        ));
+        assert_eq!(world.collider_edges().len(), 10);
+    }
+}
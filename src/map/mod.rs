@@ -1,11 +1,16 @@
+pub mod autotile;
+pub mod colliders;
 pub mod grid_world;
 /// 1.5.3
 /// Converted LDTK JSON schema to Rust using QuickType generator.
 /// https://ldtk.io/files/MINIMAL_JSON_SCHEMA.json
 #[allow(clippy::all)]
 pub mod ldtk;
+pub mod streaming;
+pub mod tiled;
 
-use crate::map::ldtk::Ldtk;
+use crate::map::ldtk::{LayerInstance, Ldtk, Level, TilesetDefinition};
+use spitfire_core::Triangle;
 use spitfire_draw::{
     context::DrawContext,
     sprite::SpriteTexture,
@@ -15,8 +20,12 @@ use spitfire_glow::{
     graphics::{GraphicsBatch, GraphicsTarget},
     renderer::{GlowBlending, GlowTextureFiltering, GlowUniformValue},
 };
-use std::{borrow::Cow, collections::HashMap};
-use vek::{Quaternion, Rect, Rgba, Transform, Vec2, Vec3};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::RwLock,
+};
+use vek::{Mat4, Quaternion, Rect, Rgba, Transform, Vec2, Vec3};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LdtkMapColliderResult {
@@ -40,6 +49,7 @@ impl LdtkMapColliderResult {
 }
 
 pub struct LdtkMapBuilder<'a> {
+    pub only_worlds: Vec<String>,
     pub only_levels: Vec<String>,
     pub only_layers: Vec<String>,
     pub pixel_world_scale: f32,
@@ -47,15 +57,33 @@ pub struct LdtkMapBuilder<'a> {
     pub image_shader: Option<ShaderRef>,
     pub sampler: Cow<'static, str>,
     pub texture_filtering: GlowTextureFiltering,
+    /// Adds level background color and image layers below level layers.
+    pub level_backgrounds: bool,
+    /// Splits layers into chunks of given number of tiles per side.
+    pub chunk_tiles: Option<usize>,
+    /// Merges adjacent IntGrid colliders with the same mask into larger
+    /// rectangles. Off by default, so levels keep one collider per cell.
+    pub merge_colliders: bool,
     #[allow(clippy::type_complexity)]
     pub tileset_reference_extractor: Option<Box<dyn Fn(&str) -> String + 'a>>,
     #[allow(clippy::type_complexity)]
+    pub image_size_extractor: Option<Box<dyn Fn(&str) -> Option<Vec2<f32>> + 'a>>,
+    #[allow(clippy::type_complexity)]
     pub int_grid_collision_extractor: Box<dyn Fn(&str) -> LdtkMapColliderResult + 'a>,
+    /// Tiles tagged with enum value animate through all tiles with that tag,
+    /// using given frame duration in seconds.
+    pub animated_enum_tags: HashMap<String, f32>,
+    /// Overrides tile animation source, by default animations are read
+    /// from tile custom data and animated enum tags.
+    #[allow(clippy::type_complexity)]
+    pub tile_animation_extractor:
+        Option<Box<dyn Fn(&TilesetDefinition, i64) -> Option<MapTileAnimation> + 'a>>,
 }
 
 impl Default for LdtkMapBuilder<'_> {
     fn default() -> Self {
         Self {
+            only_worlds: Default::default(),
             only_levels: Default::default(),
             only_layers: Default::default(),
             pixel_world_scale: 1.0,
@@ -63,13 +91,24 @@ impl Default for LdtkMapBuilder<'_> {
             image_shader: None,
             sampler: "u_image".into(),
             texture_filtering: Default::default(),
+            level_backgrounds: false,
+            chunk_tiles: Some(16),
+            merge_colliders: false,
             tileset_reference_extractor: None,
+            image_size_extractor: None,
             int_grid_collision_extractor: Box::new(|_| LdtkMapColliderResult::Ignore),
+            animated_enum_tags: Default::default(),
+            tile_animation_extractor: None,
         }
     }
 }
 
 impl<'a> LdtkMapBuilder<'a> {
+    pub fn only_worlds(mut self, worlds: impl IntoIterator<Item = String>) -> Self {
+        self.only_worlds = worlds.into_iter().collect();
+        self
+    }
+
     pub fn only_levels(mut self, levels: impl IntoIterator<Item = String>) -> Self {
         self.only_levels = levels.into_iter().collect();
         self
@@ -100,11 +139,34 @@ impl<'a> LdtkMapBuilder<'a> {
         self
     }
 
+    pub fn level_backgrounds(mut self, value: bool) -> Self {
+        self.level_backgrounds = value;
+        self
+    }
+
+    pub fn chunk_tiles(mut self, value: Option<usize>) -> Self {
+        self.chunk_tiles = value;
+        self
+    }
+
+    pub fn merge_colliders(mut self, value: bool) -> Self {
+        self.merge_colliders = value;
+        self
+    }
+
     pub fn tileset_reference_extractor(mut self, extractor: impl Fn(&str) -> String + 'a) -> Self {
         self.tileset_reference_extractor = Some(Box::new(extractor));
         self
     }
 
+    pub fn image_size_extractor(
+        mut self,
+        extractor: impl Fn(&str) -> Option<Vec2<f32>> + 'a,
+    ) -> Self {
+        self.image_size_extractor = Some(Box::new(extractor));
+        self
+    }
+
     pub fn int_grid_collision_extractor(
         mut self,
         extractor: impl Fn(&str) -> LdtkMapColliderResult + 'a,
@@ -113,15 +175,117 @@ impl<'a> LdtkMapBuilder<'a> {
         self
     }
 
-    pub fn build(&self, ldtk: &Ldtk) -> Map {
-        let levels = ldtk
-            .levels
+    pub fn animated_enum_tag(mut self, enum_value: impl ToString, frame_duration: f32) -> Self {
+        self.animated_enum_tags
+            .insert(enum_value.to_string(), frame_duration);
+        self
+    }
+
+    pub fn tile_animation_extractor(
+        mut self,
+        extractor: impl Fn(&TilesetDefinition, i64) -> Option<MapTileAnimation> + 'a,
+    ) -> Self {
+        self.tile_animation_extractor = Some(Box::new(extractor));
+        self
+    }
+
+    /// Animation of tileset tile, read from tile custom data in form of
+    /// `{"animation": {"frames": [1, 2, 3], "duration": 0.1}}` (duration can
+    /// also be a list per frame) or from animated enum tags.
+    pub fn tile_animation(
+        &self,
+        tileset: &TilesetDefinition,
+        tile_id: i64,
+    ) -> Option<MapTileAnimation> {
+        if let Some(extractor) = self.tile_animation_extractor.as_ref() {
+            return extractor(tileset, tile_id);
+        }
+        let region = |tile_id: i64| {
+            let step = tileset.tile_grid_size + tileset.spacing;
+            let x = tileset.padding + (tile_id % tileset.c_wid.max(1)) * step;
+            let y = tileset.padding + (tile_id / tileset.c_wid.max(1)) * step;
+            Rect {
+                x: x as f32 / tileset.px_wid as f32,
+                y: y as f32 / tileset.px_hei as f32,
+                w: tileset.tile_grid_size as f32 / tileset.px_wid as f32,
+                h: tileset.tile_grid_size as f32 / tileset.px_hei as f32,
+            }
+        };
+        let custom = tileset
+            .custom_data
+            .iter()
+            .find(|item| item.tile_id == tile_id)
+            .and_then(|item| serde_json::from_str::<serde_json::Value>(&item.data).ok())
+            .and_then(|data| data.get("animation").cloned());
+        if let Some(custom) = custom {
+            let frames = custom.get("frames")?.as_array()?;
+            let duration = custom.get("duration")?;
+            return Some(MapTileAnimation::new(frames.iter().enumerate().filter_map(
+                |(index, frame)| {
+                    let duration = match duration.as_array() {
+                        Some(durations) => durations.get(index)?.as_f64()?,
+                        None => duration.as_f64()?,
+                    };
+                    Some(MapTileFrame::new(
+                        region(frame.as_i64()?),
+                        0.0,
+                        duration as f32,
+                    ))
+                },
+            )));
+        }
+        tileset
+            .enum_tags
+            .iter()
+            .filter(|tag| tag.tile_ids.contains(&tile_id))
+            .find_map(|tag| {
+                let duration = *self.animated_enum_tags.get(&tag.enum_value_id)?;
+                Some(MapTileAnimation::new(tag.tile_ids.iter().map(|tile_id| {
+                    MapTileFrame::new(region(*tile_id), 0.0, duration)
+                })))
+            })
+    }
+
+    /// Levels matching world and level filters.
+    pub fn levels<'b>(&self, ldtk: &'b Ldtk) -> impl Iterator<Item = &'b Level> {
+        let world_levels = ldtk
+            .worlds
             .iter()
-            .filter(|level| {
-                self.only_levels.is_empty() || self.only_levels.contains(&level.identifier)
+            .filter(|world| {
+                self.only_worlds.is_empty() || self.only_worlds.contains(&world.identifier)
             })
-            .map(|level| {
-                let layers = level
+            .flat_map(|world| world.levels.iter());
+        ldtk.levels.iter().chain(world_levels).filter(|level| {
+            self.only_levels.is_empty() || self.only_levels.contains(&level.identifier)
+        })
+    }
+
+    /// Level bounds in map space.
+    pub fn level_rectangle(&self, level: &Level) -> Rect<f32, f32> {
+        Rect {
+            x: level.world_x as f32 * self.pixel_world_scale,
+            y: level.world_y as f32 * self.pixel_world_scale,
+            w: level.px_wid as f32 * self.pixel_world_scale,
+            h: level.px_hei as f32 * self.pixel_world_scale,
+        }
+    }
+
+    pub fn build(&self, ldtk: &Ldtk) -> Map {
+        Map {
+            levels: self
+                .levels(ldtk)
+                .map(|level| self.build_level(ldtk, level))
+                .collect(),
+            transform: Default::default(),
+        }
+    }
+
+    pub fn build_level(&self, ldtk: &Ldtk, level: &Level) -> MapLevel {
+        let layers = self
+            .level_background_layers(level)
+            .into_iter()
+            .chain(
+                level
                     .layer_instances
                     .as_ref()
                     .into_iter()
@@ -130,208 +294,302 @@ impl<'a> LdtkMapBuilder<'a> {
                     .filter(|layer| {
                         self.only_layers.is_empty() || self.only_layers.contains(&layer.identifier)
                     })
-                    .filter_map(|layer| {
-                        // TODO: add support for non-auto-tile tiles.
-                        if let Some(tileset_uid) = layer.tileset_def_uid {
-                            if layer.auto_layer_tiles.is_empty() {
-                                return None;
-                            }
-                            let tileset_definition = ldtk
-                                .defs
-                                .tilesets
-                                .iter()
-                                .find(|definition| definition.uid == tileset_uid)
-                                .unwrap();
-                            let texture_reference = tileset_definition.rel_path.as_deref()?;
-                            let texture_reference = self
-                                .tileset_reference_extractor
-                                .as_ref()
-                                .map(|extractor| extractor(texture_reference))
-                                .unwrap_or_else(|| texture_reference.to_owned());
-                            let texture_reference = TextureRef::name(texture_reference);
-                            let tiles = layer
-                                .auto_layer_tiles
-                                .iter()
-                                .map(|tile| MapTile {
-                                    visible: true,
-                                    rectangle: Rect {
-                                        x: tile.px[0] as f32 * self.pixel_world_scale,
-                                        y: tile.px[1] as f32 * self.pixel_world_scale,
-                                        w: layer.grid_size as f32 * self.pixel_world_scale,
-                                        h: layer.grid_size as f32 * self.pixel_world_scale,
-                                    },
-                                    region: Rect {
-                                        x: tile.src[0] as f32 / tileset_definition.px_wid as f32,
-                                        y: tile.src[1] as f32 / tileset_definition.px_hei as f32,
-                                        w: tileset_definition.tile_grid_size as f32
-                                            / tileset_definition.px_wid as f32,
-                                        h: tileset_definition.tile_grid_size as f32
-                                            / tileset_definition.px_hei as f32,
-                                    },
-                                    page: 0.0,
-                                    color: Rgba::white(),
-                                })
-                                .collect::<Vec<_>>();
-                            let visibility_region = tiles
-                                .iter()
-                                .map(|tile| tile.rectangle)
-                                .reduce(|current, item| current.union(item));
-                            Some(MapLayer {
-                                visible: layer.visible,
-                                visibility_region,
-                                shader: self.image_shader.clone(),
-                                textures: vec![SpriteTexture {
-                                    sampler: self.sampler.clone(),
-                                    texture: texture_reference,
-                                    filtering: self.texture_filtering,
-                                }],
-                                uniforms: Default::default(),
-                                blending: None,
-                                transform: Transform {
-                                    position: Vec3::new(
-                                        layer.px_total_offset_x as f32 * self.pixel_world_scale,
-                                        layer.px_total_offset_y as f32 * self.pixel_world_scale,
-                                        0.0,
-                                    ),
-                                    orientation: Quaternion::identity(),
-                                    scale: Vec3::one(),
-                                },
-                                tiles,
-                            })
-                        } else {
-                            if layer.int_grid_csv.is_empty() {
-                                return None;
-                            }
-                            let layer_definition = ldtk
-                                .defs
-                                .layers
-                                .iter()
-                                .find(|definition| definition.uid == layer.layer_def_uid)
-                                .unwrap();
-                            let tiles = layer
-                                .int_grid_csv
-                                .iter()
-                                .copied()
-                                .enumerate()
-                                .filter_map(|(index, value)| {
-                                    let value_def = layer_definition
-                                        .int_grid_values
-                                        .iter()
-                                        .find(|v| v.value == value)?;
-                                    let col = index as i64 % layer.c_wid;
-                                    let row = index as i64 / layer.c_wid;
-                                    let color = Self::parse_color(&value_def.color);
-                                    Some(MapTile {
-                                        visible: true,
-                                        rectangle: Rect {
-                                            x: col as f32
-                                                * layer.grid_size as f32
-                                                * self.pixel_world_scale,
-                                            y: row as f32
-                                                * layer.grid_size as f32
-                                                * self.pixel_world_scale,
-                                            w: layer.grid_size as f32 * self.pixel_world_scale,
-                                            h: layer.grid_size as f32 * self.pixel_world_scale,
-                                        },
-                                        region: Default::default(),
-                                        page: 0.0,
-                                        color,
-                                    })
-                                })
-                                .collect::<Vec<_>>();
-                            let visibility_region = tiles
-                                .iter()
-                                .map(|tile| tile.rectangle)
-                                .reduce(|current, item| current.union(item));
-                            Some(MapLayer {
-                                visible: layer.visible,
-                                visibility_region,
-                                shader: self.color_shader.clone(),
-                                textures: Default::default(),
-                                uniforms: Default::default(),
-                                blending: None,
-                                transform: Transform {
-                                    position: Vec3::new(
-                                        layer.px_total_offset_x as f32 * self.pixel_world_scale,
-                                        layer.px_total_offset_y as f32 * self.pixel_world_scale,
-                                        0.0,
-                                    ),
-                                    orientation: Quaternion::identity(),
-                                    scale: Vec3::one(),
-                                },
-                                tiles,
-                            })
-                        }
-                    })
-                    .collect();
-                let mut colliders = vec![];
-                for layer in level.layer_instances.as_ref().into_iter().flatten().rev() {
-                    let layer_definition = ldtk
-                        .defs
-                        .layers
-                        .iter()
-                        .find(|definition| definition.uid == layer.layer_def_uid)
-                        .unwrap();
-                    for (index, value) in layer.int_grid_csv.iter().enumerate() {
-                        let index = index as i64;
-                        let Some(value_definition) = layer_definition
-                            .int_grid_values
-                            .iter()
-                            .find(|v| v.value == *value)
-                        else {
-                            continue;
-                        };
-                        let Some(value_id) = value_definition.identifier.as_deref() else {
-                            continue;
-                        };
-                        let col = index % layer.c_wid;
-                        let row = index / layer.c_wid;
-                        let rectangle = Rect {
-                            x: (col * layer.grid_size + layer.px_total_offset_x) as f32
-                                * self.pixel_world_scale,
-                            y: (row * layer.grid_size + layer.px_total_offset_y) as f32
-                                * self.pixel_world_scale,
-                            w: layer.grid_size as f32 * self.pixel_world_scale,
-                            h: layer.grid_size as f32 * self.pixel_world_scale,
-                        };
-                        let result = self.int_grid_collision_extractor.as_ref()(value_id);
-                        if result.does_clear_area() {
-                            colliders.retain(|collider: &MapCollider| {
-                                !collider.rectangle.collides_with_rect(rectangle)
-                            });
-                        }
-                        if let Some(mask) = result.mask() {
-                            colliders.push(MapCollider {
-                                enabled: true,
-                                rectangle,
-                                mask,
-                            });
-                        }
-                    }
+                    .filter_map(|layer| self.build_layer(ldtk, layer)),
+            )
+            .collect();
+        let mut colliders = vec![];
+        for layer in level.layer_instances.as_ref().into_iter().flatten().rev() {
+            let layer_definition = ldtk
+                .defs
+                .layers
+                .iter()
+                .find(|definition| definition.uid == layer.layer_def_uid)
+                .unwrap();
+            for (index, value) in layer.int_grid_csv.iter().enumerate() {
+                let index = index as i64;
+                let Some(value_definition) = layer_definition
+                    .int_grid_values
+                    .iter()
+                    .find(|v| v.value == *value)
+                else {
+                    continue;
+                };
+                let Some(value_id) = value_definition.identifier.as_deref() else {
+                    continue;
+                };
+                let col = index % layer.c_wid;
+                let row = index / layer.c_wid;
+                let rectangle = Rect {
+                    x: (col * layer.grid_size + layer.px_total_offset_x) as f32
+                        * self.pixel_world_scale,
+                    y: (row * layer.grid_size + layer.px_total_offset_y) as f32
+                        * self.pixel_world_scale,
+                    w: layer.grid_size as f32 * self.pixel_world_scale,
+                    h: layer.grid_size as f32 * self.pixel_world_scale,
+                };
+                let result = self.int_grid_collision_extractor.as_ref()(value_id);
+                if result.does_clear_area() {
+                    colliders.retain(|collider: &MapCollider| {
+                        !collider.rectangle.collides_with_rect(rectangle)
+                    });
                 }
-                MapLevel {
+                if let Some(mask) = result.mask() {
+                    colliders.push(MapCollider {
+                        enabled: true,
+                        rectangle,
+                        mask,
+                    });
+                }
+            }
+        }
+        if self.merge_colliders {
+            colliders = MapCollider::merge(colliders);
+        }
+        MapLevel {
+            id: Some(level.iid.clone()),
+            visible: true,
+            layers,
+            colliders,
+            transform: Transform {
+                position: Vec3::new(
+                    level.world_x as f32 * self.pixel_world_scale,
+                    level.world_y as f32 * self.pixel_world_scale,
+                    0.0,
+                ),
+                orientation: Quaternion::identity(),
+                scale: Vec3::one(),
+            },
+        }
+    }
+
+    fn build_layer(&self, ldtk: &Ldtk, layer: &LayerInstance) -> Option<MapLayer> {
+        if let Some(tileset_uid) = layer.tileset_def_uid {
+            if layer.auto_layer_tiles.is_empty() && layer.grid_tiles.is_empty() {
+                return None;
+            }
+            let tileset_definition = ldtk
+                .defs
+                .tilesets
+                .iter()
+                .find(|definition| definition.uid == tileset_uid)
+                .unwrap();
+            let texture_reference = tileset_definition.rel_path.as_deref()?;
+            let texture_reference = self
+                .tileset_reference_extractor
+                .as_ref()
+                .map(|extractor| extractor(texture_reference))
+                .unwrap_or_else(|| texture_reference.to_owned());
+            let texture_reference = TextureRef::name(texture_reference);
+            let mut animations = Vec::new();
+            let mut animation_indices = HashMap::new();
+            let tiles = layer
+                .auto_layer_tiles
+                .iter()
+                .chain(layer.grid_tiles.iter())
+                .map(|tile| MapTile {
                     visible: true,
-                    layers,
-                    colliders,
-                    transform: Transform {
-                        position: Vec3::new(
-                            level.world_x as f32 * self.pixel_world_scale,
-                            level.world_y as f32 * self.pixel_world_scale,
-                            0.0,
-                        ),
-                        orientation: Quaternion::identity(),
-                        scale: Vec3::one(),
+                    rectangle: Rect {
+                        x: tile.px[0] as f32 * self.pixel_world_scale,
+                        y: tile.px[1] as f32 * self.pixel_world_scale,
+                        w: layer.grid_size as f32 * self.pixel_world_scale,
+                        h: layer.grid_size as f32 * self.pixel_world_scale,
                     },
-                }
+                    region: Rect {
+                        x: tile.src[0] as f32 / tileset_definition.px_wid as f32,
+                        y: tile.src[1] as f32 / tileset_definition.px_hei as f32,
+                        w: tileset_definition.tile_grid_size as f32
+                            / tileset_definition.px_wid as f32,
+                        h: tileset_definition.tile_grid_size as f32
+                            / tileset_definition.px_hei as f32,
+                    },
+                    page: 0.0,
+                    color: Rgba::new(1.0, 1.0, 1.0, tile.a as f32),
+                    flip_x: tile.f & 1 != 0,
+                    flip_y: tile.f & 2 != 0,
+                    flip_diagonal: false,
+                    animation: *animation_indices.entry(tile.t).or_insert_with(|| {
+                        let animation = self.tile_animation(tileset_definition, tile.t)?;
+                        animations.push(animation);
+                        Some(animations.len() - 1)
+                    }),
+                })
+                .collect::<Vec<_>>();
+            let visibility_region = tiles
+                .iter()
+                .map(|tile| tile.rectangle)
+                .reduce(|current, item| current.union(item));
+            Some(MapLayer {
+                visible: layer.visible,
+                visibility_region,
+                opacity: layer.opacity as f32,
+                shader: self.image_shader.clone(),
+                textures: vec![SpriteTexture {
+                    sampler: self.sampler.clone(),
+                    texture: texture_reference,
+                    filtering: self.texture_filtering,
+                }],
+                uniforms: Default::default(),
+                blending: None,
+                transform: Transform {
+                    position: Vec3::new(
+                        layer.px_total_offset_x as f32 * self.pixel_world_scale,
+                        layer.px_total_offset_y as f32 * self.pixel_world_scale,
+                        0.0,
+                    ),
+                    orientation: Quaternion::identity(),
+                    scale: Vec3::one(),
+                },
+                tiles,
+                animations,
+                chunk_size: self.chunk_size(layer.grid_size),
+                grid_cell_size: Some(Vec2::broadcast(
+                    layer.grid_size as f32 * self.pixel_world_scale,
+                )),
+                ..Default::default()
             })
-            .collect();
-        Map {
-            levels,
-            transform: Default::default(),
+        } else {
+            if layer.int_grid_csv.is_empty() {
+                return None;
+            }
+            let layer_definition = ldtk
+                .defs
+                .layers
+                .iter()
+                .find(|definition| definition.uid == layer.layer_def_uid)
+                .unwrap();
+            let tiles = layer
+                .int_grid_csv
+                .iter()
+                .copied()
+                .enumerate()
+                .filter_map(|(index, value)| {
+                    let value_def = layer_definition
+                        .int_grid_values
+                        .iter()
+                        .find(|v| v.value == value)?;
+                    let col = index as i64 % layer.c_wid;
+                    let row = index as i64 / layer.c_wid;
+                    let color = Self::parse_color(&value_def.color);
+                    Some(MapTile {
+                        visible: true,
+                        rectangle: Rect {
+                            x: col as f32 * layer.grid_size as f32 * self.pixel_world_scale,
+                            y: row as f32 * layer.grid_size as f32 * self.pixel_world_scale,
+                            w: layer.grid_size as f32 * self.pixel_world_scale,
+                            h: layer.grid_size as f32 * self.pixel_world_scale,
+                        },
+                        region: Default::default(),
+                        page: 0.0,
+                        color,
+                        flip_x: false,
+                        flip_y: false,
+                        flip_diagonal: false,
+                        animation: None,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let visibility_region = tiles
+                .iter()
+                .map(|tile| tile.rectangle)
+                .reduce(|current, item| current.union(item));
+            Some(MapLayer {
+                visible: layer.visible,
+                visibility_region,
+                opacity: layer.opacity as f32,
+                shader: self.color_shader.clone(),
+                textures: Default::default(),
+                uniforms: Default::default(),
+                blending: None,
+                transform: Transform {
+                    position: Vec3::new(
+                        layer.px_total_offset_x as f32 * self.pixel_world_scale,
+                        layer.px_total_offset_y as f32 * self.pixel_world_scale,
+                        0.0,
+                    ),
+                    orientation: Quaternion::identity(),
+                    scale: Vec3::one(),
+                },
+                tiles,
+                animations: Default::default(),
+                chunk_size: self.chunk_size(layer.grid_size),
+                grid_cell_size: Some(Vec2::broadcast(
+                    layer.grid_size as f32 * self.pixel_world_scale,
+                )),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn chunk_size(&self, grid_size: i64) -> Option<f32> {
+        self.chunk_tiles
+            .map(|tiles| (tiles as i64 * grid_size) as f32 * self.pixel_world_scale)
+    }
+
+    fn level_background_layers(&self, level: &Level) -> Vec<MapLayer> {
+        let mut result = vec![];
+        if !self.level_backgrounds {
+            return result;
+        }
+        let level_rectangle = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: level.px_wid as f32 * self.pixel_world_scale,
+            h: level.px_hei as f32 * self.pixel_world_scale,
+        };
+        if !level.bg_color.is_empty() {
+            result.push(MapLayer {
+                visibility_region: Some(level_rectangle),
+                shader: self.color_shader.clone(),
+                tiles: vec![
+                    MapTile::new(level_rectangle, Default::default(), 0.0)
+                        .color(Self::parse_color(&level.bg_color)),
+                ],
+                ..Default::default()
+            });
+        }
+        if let Some(texture_reference) = level.bg_rel_path.as_deref()
+            && let Some(position) = level.bg_pos.as_ref()
+            && let [crop_x, crop_y, crop_w, crop_h] = position.crop_rect[..]
+            && let [scale_x, scale_y] = position.scale[..]
+            && let [left, top] = position.top_left_px[..]
+            && let Some(size) = self
+                .image_size_extractor
+                .as_ref()
+                .and_then(|extractor| extractor(texture_reference))
+        {
+            let texture_reference = self
+                .tileset_reference_extractor
+                .as_ref()
+                .map(|extractor| extractor(texture_reference))
+                .unwrap_or_else(|| texture_reference.to_owned());
+            let rectangle = Rect {
+                x: left as f32 * self.pixel_world_scale,
+                y: top as f32 * self.pixel_world_scale,
+                w: (crop_w * scale_x) as f32 * self.pixel_world_scale,
+                h: (crop_h * scale_y) as f32 * self.pixel_world_scale,
+            };
+            let region = Rect {
+                x: crop_x as f32 / size.x,
+                y: crop_y as f32 / size.y,
+                w: crop_w as f32 / size.x,
+                h: crop_h as f32 / size.y,
+            };
+            result.push(MapLayer {
+                visibility_region: Some(rectangle),
+                shader: self.image_shader.clone(),
+                textures: vec![SpriteTexture {
+                    sampler: self.sampler.clone(),
+                    texture: TextureRef::name(texture_reference),
+                    filtering: self.texture_filtering,
+                }],
+                tiles: vec![MapTile::new(rectangle, region, 0.0)],
+                ..Default::default()
+            });
         }
+        result
     }
 
-    fn parse_color(value: &str) -> Rgba<f32> {
+    pub(crate) fn parse_color(value: &str) -> Rgba<f32> {
         let value = value.trim_start_matches('#');
         let r = u8::from_str_radix(&value[0..2], 16).unwrap_or_default();
         let g = u8::from_str_radix(&value[2..4], 16).unwrap_or_default();
@@ -394,6 +652,18 @@ impl Map {
         self
     }
 
+    pub fn level_by_id(&self, id: &str) -> Option<&MapLevel> {
+        self.levels
+            .iter()
+            .find(|level| level.id.as_deref() == Some(id))
+    }
+
+    pub fn level_by_id_mut(&mut self, id: &str) -> Option<&mut MapLevel> {
+        self.levels
+            .iter_mut()
+            .find(|level| level.id.as_deref() == Some(id))
+    }
+
     pub fn collides_with_point(&self, point: Vec2<f32>, mask: u32) -> bool {
         let point = transform_to_matrix(self.transform)
             .inverted()
@@ -435,6 +705,8 @@ impl Map {
             clip_region: None,
             clip_each_tile: false,
             show_colliders: None,
+            time: 0.0,
+            frustum_culling: true,
             map: self,
         }
     }
@@ -442,6 +714,8 @@ impl Map {
 
 #[derive(Debug, Clone)]
 pub struct MapLevel {
+    /// Unique level identifier, LDtk levels use their IID.
+    pub id: Option<String>,
     pub visible: bool,
     pub layers: Vec<MapLayer>,
     pub colliders: Vec<MapCollider>,
@@ -451,6 +725,7 @@ pub struct MapLevel {
 impl Default for MapLevel {
     fn default() -> Self {
         Self {
+            id: None,
             visible: true,
             layers: Default::default(),
             colliders: Default::default(),
@@ -460,6 +735,11 @@ impl Default for MapLevel {
 }
 
 impl MapLevel {
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
     pub fn visibility(mut self, visible: bool) -> Self {
         self.visible = visible;
         self
@@ -505,6 +785,73 @@ impl MapLevel {
         self
     }
 
+    /// Grid cell rectangle of layer, in level space.
+    pub fn layer_cell_rectangle(&self, layer: usize, cell: Vec2<i32>) -> Option<Rect<f32, f32>> {
+        let layer = self.layers.get(layer)?;
+        let rectangle = layer.cell_rectangle(cell)?;
+        let matrix = transform_to_matrix(layer.transform);
+        let vertices = [
+            matrix.mul_point(Vec2::new(rectangle.x, rectangle.y)),
+            matrix.mul_point(Vec2::new(rectangle.x + rectangle.w, rectangle.y)),
+            matrix.mul_point(Vec2::new(rectangle.x, rectangle.y + rectangle.h)),
+            matrix.mul_point(Vec2::new(
+                rectangle.x + rectangle.w,
+                rectangle.y + rectangle.h,
+            )),
+        ];
+        Some(
+            Rect::new(vertices[0].x, vertices[0].y, 0.0, 0.0)
+                .expanded_to_contain_point(vertices[1])
+                .expanded_to_contain_point(vertices[2])
+                .expanded_to_contain_point(vertices[3]),
+        )
+    }
+
+    /// Indices of colliders fully contained in region.
+    pub fn collider_indices_in(&self, region: Rect<f32, f32>) -> Vec<usize> {
+        self.colliders
+            .iter()
+            .enumerate()
+            .filter(|(_, collider)| region.contains_rect(collider.rectangle))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub fn colliders_at(&self, point: Vec2<f32>) -> impl Iterator<Item = &MapCollider> {
+        self.colliders
+            .iter()
+            .filter(move |collider| collider.rectangle.contains_point(point))
+    }
+
+    /// Replaces colliders covering layer grid cell with collider of given
+    /// mask, or just removes them for `None`. Merged colliders are split
+    /// around the cell. Returns removed colliders.
+    pub fn set_cell_collider(
+        &mut self,
+        layer: usize,
+        cell: Vec2<i32>,
+        mask: Option<u32>,
+    ) -> Vec<MapCollider> {
+        let Some(rectangle) = self.layer_cell_rectangle(layer, cell) else {
+            return vec![];
+        };
+        let mut result = vec![];
+        let mut remaining = vec![];
+        for collider in self.colliders.drain(..) {
+            if rectangle_overlap(collider.rectangle, rectangle).is_some() {
+                remaining.extend(collider.subtract(rectangle));
+                result.push(collider);
+            } else {
+                remaining.push(collider);
+            }
+        }
+        self.colliders = remaining;
+        if let Some(mask) = mask {
+            self.colliders.push(MapCollider::new(rectangle, mask));
+        }
+        result
+    }
+
     pub fn collides_with_point(&self, point: Vec2<f32>, mask: u32) -> bool {
         let point = transform_to_matrix(self.transform)
             .inverted()
@@ -548,12 +895,23 @@ impl MapLevel {
 pub struct MapLayer {
     pub visible: bool,
     pub visibility_region: Option<Rect<f32, f32>>,
+    pub opacity: f32,
     pub shader: Option<ShaderRef>,
     pub textures: Vec<SpriteTexture>,
     pub uniforms: HashMap<Cow<'static, str>, GlowUniformValue>,
     pub blending: Option<GlowBlending>,
     pub transform: Transform<f32, f32, f32>,
-    pub tiles: Vec<MapTile>,
+    /// Private so every edit goes through methods keeping cached chunks valid.
+    tiles: Vec<MapTile>,
+    /// Animations referenced by tiles of this layer.
+    pub animations: Vec<MapTileAnimation>,
+    /// Size of spatial chunks with cached vertices, `None` draws tiles directly.
+    pub chunk_size: Option<f32>,
+    /// Size of grid cell used to address tiles by grid coordinates.
+    pub grid_cell_size: Option<Vec2<f32>>,
+    revision: u64,
+    dirty_cells: HashSet<Vec2<i32>>,
+    cache: MapLayerCache,
 }
 
 impl Default for MapLayer {
@@ -561,12 +919,19 @@ impl Default for MapLayer {
         Self {
             visible: true,
             visibility_region: None,
+            opacity: 1.0,
             shader: None,
             textures: Default::default(),
             uniforms: Default::default(),
             blending: None,
             transform: Default::default(),
             tiles: Default::default(),
+            animations: Default::default(),
+            chunk_size: None,
+            grid_cell_size: None,
+            revision: 0,
+            dirty_cells: Default::default(),
+            cache: Default::default(),
         }
     }
 }
@@ -589,6 +954,11 @@ impl MapLayer {
         self
     }
 
+    pub fn opacity(mut self, value: f32) -> Self {
+        self.opacity = value;
+        self
+    }
+
     pub fn shader(mut self, shader: ShaderRef) -> Self {
         self.shader = Some(shader);
         self
@@ -639,6 +1009,356 @@ impl MapLayer {
         self
     }
 
+    pub fn chunk_size(mut self, value: f32) -> Self {
+        self.chunk_size = Some(value);
+        self
+    }
+
+    pub fn all_tiles(&self) -> &[MapTile] {
+        &self.tiles
+    }
+
+    /// Gives mutable access to tiles and invalidates cached chunks.
+    pub fn tiles_mut(&mut self) -> &mut Vec<MapTile> {
+        self.invalidate_chunks();
+        &mut self.tiles
+    }
+
+    /// Gives mutable access to single tile, its chunk and cell get updated on next use.
+    pub fn tile_mut(&mut self, index: usize) -> Option<&mut MapTile> {
+        if index >= self.tiles.len() {
+            return None;
+        }
+        self.touch_tile(index);
+        self.tiles.get_mut(index)
+    }
+
+    pub fn grid_cell_size(mut self, value: Vec2<f32>) -> Self {
+        self.grid_cell_size = Some(value);
+        self
+    }
+
+    /// Forces chunks and cells index rebuild.
+    pub fn invalidate_chunks(&mut self) {
+        *self.cache.chunks.get_mut().unwrap() = None;
+        *self.cache.cells.get_mut().unwrap() = None;
+        self.cache.dirty_chunks.get_mut().unwrap().clear();
+        self.cache.touched.get_mut().unwrap().clear();
+        self.revision += 1;
+    }
+
+    /// Counter increased with every tiles change, useful for renderers caching layer data.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Grid cells edited since last `take_dirty_cells` call.
+    pub fn dirty_cells(&self) -> impl Iterator<Item = Vec2<i32>> + '_ {
+        self.dirty_cells.iter().copied()
+    }
+
+    pub fn take_dirty_cells(&mut self) -> HashSet<Vec2<i32>> {
+        std::mem::take(&mut self.dirty_cells)
+    }
+
+    /// Grid cell containing point in layer space.
+    pub fn cell_at(&self, point: Vec2<f32>) -> Option<Vec2<i32>> {
+        let size = self.grid_cell_size?;
+        Some(Vec2::new(
+            (point.x / size.x).floor() as i32,
+            (point.y / size.y).floor() as i32,
+        ))
+    }
+
+    /// Grid cell of tile, tiles larger than cell are anchored at their bottom-left cell.
+    pub fn tile_cell(&self, tile: &MapTile) -> Option<Vec2<i32>> {
+        let size = self.grid_cell_size?;
+        self.cell_at(Vec2::new(
+            tile.rectangle.x + size.x * 0.5,
+            tile.rectangle.y + tile.rectangle.h - size.y * 0.5,
+        ))
+    }
+
+    pub fn cell_rectangle(&self, cell: Vec2<i32>) -> Option<Rect<f32, f32>> {
+        let size = self.grid_cell_size?;
+        Some(Rect::new(
+            cell.x as f32 * size.x,
+            cell.y as f32 * size.y,
+            size.x,
+            size.y,
+        ))
+    }
+
+    /// Indices of tiles at grid cell, in draw order.
+    pub fn tile_indices_at(&self, cell: Vec2<i32>) -> Vec<usize> {
+        self.ensure_cells();
+        self.cache
+            .cells
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|cells| cells.get(&cell))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Top-most tile at grid cell.
+    pub fn tile_at(&self, cell: Vec2<i32>) -> Option<&MapTile> {
+        let index = *self.tile_indices_at(cell).last()?;
+        self.tiles.get(index)
+    }
+
+    pub fn tile_at_mut(&mut self, cell: Vec2<i32>) -> Option<&mut MapTile> {
+        let index = *self.tile_indices_at(cell).last()?;
+        self.touch_tile(index);
+        self.mark_dirty(cell);
+        self.tiles.get_mut(index)
+    }
+
+    /// Replaces tiles at grid cell with single tile, returns removed tiles.
+    pub fn set_tile(&mut self, cell: Vec2<i32>, tile: MapTile) -> Vec<MapTile> {
+        self.set_tiles(cell, [tile])
+    }
+
+    /// Replaces tiles at grid cell, returns removed tiles. New tiles are moved
+    /// into cell, anchored at its bottom-left corner. Only chunks of changed
+    /// tiles get rebuilt.
+    pub fn set_tiles(
+        &mut self,
+        cell: Vec2<i32>,
+        tiles: impl IntoIterator<Item = MapTile>,
+    ) -> Vec<MapTile> {
+        let Some(rectangle) = self.cell_rectangle(cell) else {
+            return vec![];
+        };
+        let indices = self.tile_indices_at(cell);
+        let mut tiles = tiles.into_iter().map(|mut tile| {
+            tile.rectangle.x = rectangle.x;
+            tile.rectangle.y = rectangle.y + rectangle.h - tile.rectangle.h;
+            tile
+        });
+        let mut result = Vec::with_capacity(indices.len());
+        let mut replaced = 0;
+        for index in &indices {
+            let Some(tile) = tiles.next() else {
+                break;
+            };
+            self.mark_chunk_dirty(self.tiles[*index]);
+            self.mark_chunk_dirty(tile);
+            result.push(std::mem::replace(&mut self.tiles[*index], tile));
+            replaced += 1;
+        }
+        result.extend(self.remove_tiles(&indices[replaced..]));
+        for tile in tiles {
+            self.mark_chunk_dirty(tile);
+            if let Some(cells) = self.cache.cells.get_mut().unwrap().as_mut() {
+                cells.entry(cell).or_default().push(self.tiles.len());
+            }
+            self.tiles.push(tile);
+        }
+        self.mark_dirty(cell);
+        result
+    }
+
+    /// Removes tiles at grid cell and returns them.
+    pub fn remove_tiles_at(&mut self, cell: Vec2<i32>) -> Vec<MapTile> {
+        let indices = self.tile_indices_at(cell);
+        if indices.is_empty() {
+            return vec![];
+        }
+        let result = self.remove_tiles(&indices);
+        self.mark_dirty(cell);
+        result
+    }
+
+    /// Removes tiles at sorted indices, remapping cached indices of remaining tiles.
+    fn remove_tiles(&mut self, indices: &[usize]) -> Vec<MapTile> {
+        if indices.is_empty() {
+            return vec![];
+        }
+        self.sync_touched();
+        for index in indices {
+            self.mark_chunk_dirty(self.tiles[*index]);
+        }
+        let mut result = Vec::with_capacity(indices.len());
+        let mut index = 0;
+        self.tiles.retain(|tile| {
+            let keep = indices.binary_search(&index).is_err();
+            if !keep {
+                result.push(*tile);
+            }
+            index += 1;
+            keep
+        });
+        let remap = |list: &mut Vec<usize>| {
+            list.retain_mut(|index| match indices.binary_search(index) {
+                Ok(_) => false,
+                Err(shift) => {
+                    *index -= shift;
+                    true
+                }
+            });
+        };
+        if let Some(cells) = self.cache.cells.get_mut().unwrap().as_mut() {
+            cells.retain(|_, list| {
+                remap(list);
+                !list.is_empty()
+            });
+        }
+        if let Some(chunks) = self.cache.chunks.get_mut().unwrap().as_mut() {
+            for chunk in chunks.values_mut() {
+                remap(&mut chunk.tiles);
+                remap(&mut chunk.animated);
+            }
+        }
+        result
+    }
+
+    fn mark_dirty(&mut self, cell: Vec2<i32>) {
+        self.dirty_cells.insert(cell);
+        self.revision += 1;
+    }
+
+    fn chunk_key(&self, tile: &MapTile) -> Option<Vec2<i32>> {
+        let chunk_size = self.chunk_size.filter(|size| *size > 0.0)?;
+        Some(Vec2::new(
+            (tile.rectangle.x / chunk_size).floor() as i32,
+            (tile.rectangle.y / chunk_size).floor() as i32,
+        ))
+    }
+
+    fn mark_chunk_dirty(&mut self, tile: MapTile) {
+        if let Some(key) = self.chunk_key(&tile) {
+            self.cache.dirty_chunks.get_mut().unwrap().insert(key);
+        }
+    }
+
+    /// Remembers where tile was cached before caller mutates it in place.
+    fn touch_tile(&mut self, index: usize) {
+        let tile = self.tiles[index];
+        let touched = MapLayerTouchedTile {
+            index,
+            cell: self.tile_cell(&tile),
+            chunk: self.chunk_key(&tile),
+        };
+        self.cache.touched.get_mut().unwrap().push(touched);
+        self.revision += 1;
+    }
+
+    /// Moves in place mutated tiles to their current cells and chunks.
+    fn sync_touched(&self) {
+        let touched = std::mem::take(&mut *self.cache.touched.write().unwrap());
+        if touched.is_empty() {
+            return;
+        }
+        let mut cells = self.cache.cells.write().unwrap();
+        let mut dirty_chunks = self.cache.dirty_chunks.write().unwrap();
+        for MapLayerTouchedTile { index, cell, chunk } in touched {
+            let tile = &self.tiles[index];
+            dirty_chunks.extend(chunk);
+            dirty_chunks.extend(self.chunk_key(tile));
+            let new_cell = self.tile_cell(tile);
+            let Some(cells) = cells.as_mut().filter(|_| new_cell != cell) else {
+                continue;
+            };
+            if let Some(list) = cell.and_then(|cell| cells.get_mut(&cell)) {
+                list.retain(|item| *item != index);
+            }
+            if let Some(cell) = cell
+                && cells.get(&cell).is_some_and(|list| list.is_empty())
+            {
+                cells.remove(&cell);
+            }
+            if let Some(new_cell) = new_cell {
+                let list = cells.entry(new_cell).or_default();
+                let position = list.partition_point(|item| *item < index);
+                list.insert(position, index);
+            }
+        }
+    }
+
+    fn ensure_cells(&self) {
+        self.sync_touched();
+        if self.grid_cell_size.is_none() || self.cache.cells.read().unwrap().is_some() {
+            return;
+        }
+        let mut cells = HashMap::<Vec2<i32>, Vec<usize>>::default();
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if let Some(cell) = self.tile_cell(tile) {
+                cells.entry(cell).or_default().push(index);
+            }
+        }
+        *self.cache.cells.write().unwrap() = Some(cells);
+    }
+
+    pub fn chunks_count(&self) -> usize {
+        self.ensure_chunks();
+        self.cache
+            .chunks
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|chunks| chunks.len())
+            .unwrap_or_default()
+    }
+
+    /// Builds missing chunks, either all of them or only ones marked dirty.
+    fn ensure_chunks(&self) {
+        if self.chunk_size.is_none_or(|size| size <= 0.0) {
+            return;
+        }
+        self.sync_touched();
+        let mut chunks = self.cache.chunks.write().unwrap();
+        let mut dirty_chunks = self.cache.dirty_chunks.write().unwrap();
+        let only = match chunks.as_mut() {
+            Some(_) if dirty_chunks.is_empty() => return,
+            Some(chunks) => {
+                chunks.retain(|key, _| !dirty_chunks.contains(&Vec2::new(key.1, key.0)));
+                Some(std::mem::take(&mut *dirty_chunks))
+            }
+            None => {
+                dirty_chunks.clear();
+                None
+            }
+        };
+        let chunks = chunks.get_or_insert_with(Default::default);
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if !tile.visible {
+                continue;
+            }
+            let Some(key) = self.chunk_key(tile) else {
+                continue;
+            };
+            if only.as_ref().is_some_and(|only| !only.contains(&key)) {
+                continue;
+            }
+            let chunk = chunks
+                .entry((key.y, key.x))
+                .or_insert_with(|| MapLayerChunk {
+                    bounds: tile.rectangle,
+                    ..Default::default()
+                });
+            chunk.bounds = chunk.bounds.union(tile.rectangle);
+            if tile.animation.is_some() {
+                chunk.animated.push(index);
+            } else {
+                chunk.tiles.push(index);
+                let offset = chunk.vertices.len();
+                chunk.vertices.extend(tile.vertices());
+                chunk.triangles.extend([
+                    Triangle { a: 0, b: 1, c: 2 }.offset(offset),
+                    Triangle { a: 2, b: 3, c: 0 }.offset(offset),
+                ]);
+            }
+        }
+    }
+
+    /// Adds animation and returns its index for tiles to reference.
+    pub fn add_animation(&mut self, animation: MapTileAnimation) -> usize {
+        self.animations.push(animation);
+        self.animations.len() - 1
+    }
+
     pub fn bounding_box(&self) -> Option<Rect<f32, f32>> {
         self.tiles
             .iter()
@@ -664,6 +1384,42 @@ impl MapLayer {
     }
 }
 
+#[derive(Debug, Default, Clone)]
+struct MapLayerChunk {
+    bounds: Rect<f32, f32>,
+    vertices: Vec<Vertex>,
+    triangles: Vec<Triangle>,
+    /// Indices of tiles baked into vertices, used when clipping each tile.
+    tiles: Vec<usize>,
+    /// Indices of animated tiles, emitted every frame.
+    animated: Vec<usize>,
+}
+
+/// Lazily built layer chunks and grid cells index, clones start with empty cache.
+#[derive(Debug, Default)]
+struct MapLayerCache {
+    /// Keyed by chunk row and column, so iteration goes in draw order.
+    chunks: RwLock<Option<BTreeMap<(i32, i32), MapLayerChunk>>>,
+    cells: RwLock<Option<HashMap<Vec2<i32>, Vec<usize>>>>,
+    /// Chunks to rebuild from tiles on next use.
+    dirty_chunks: RwLock<HashSet<Vec2<i32>>>,
+    touched: RwLock<Vec<MapLayerTouchedTile>>,
+}
+
+/// Tile mutated in place, with cell and chunk it was cached at.
+#[derive(Debug)]
+struct MapLayerTouchedTile {
+    index: usize,
+    cell: Option<Vec2<i32>>,
+    chunk: Option<Vec2<i32>>,
+}
+
+impl Clone for MapLayerCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct MapTile {
     pub visible: bool,
@@ -671,6 +1427,12 @@ pub struct MapTile {
     pub region: Rect<f32, f32>,
     pub page: f32,
     pub color: Rgba<f32>,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Swaps texture axes, applied before horizontal and vertical flips.
+    pub flip_diagonal: bool,
+    /// Index of layer animation replacing region and page at draw time.
+    pub animation: Option<usize>,
 }
 
 impl MapTile {
@@ -681,6 +1443,10 @@ impl MapTile {
             region,
             page,
             color: Rgba::white(),
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false,
+            animation: None,
         }
     }
 
@@ -693,6 +1459,163 @@ impl MapTile {
         self.color = color;
         self
     }
+
+    pub fn flip(mut self, x: bool, y: bool) -> Self {
+        self.flip_x = x;
+        self.flip_y = y;
+        self
+    }
+
+    pub fn flip_diagonal(mut self, value: bool) -> Self {
+        self.flip_diagonal = value;
+        self
+    }
+
+    pub fn animation(mut self, index: usize) -> Self {
+        self.animation = Some(index);
+        self
+    }
+
+    /// Quad vertices in layer space, with tile color.
+    pub fn vertices(&self) -> [Vertex; 4] {
+        let offset = self.rectangle.position();
+        let size = self.rectangle.extent();
+        let color = self.color.into_array();
+        let [top_left, top_right, bottom_right, bottom_left] = self.uvs();
+        [
+            Vertex {
+                position: [offset.x, offset.y],
+                uv: [top_left[0], top_left[1], self.page],
+                color,
+            },
+            Vertex {
+                position: [offset.x + size.w, offset.y],
+                uv: [top_right[0], top_right[1], self.page],
+                color,
+            },
+            Vertex {
+                position: [offset.x + size.w, offset.y + size.h],
+                uv: [bottom_right[0], bottom_right[1], self.page],
+                color,
+            },
+            Vertex {
+                position: [offset.x, offset.y + size.h],
+                uv: [bottom_left[0], bottom_left[1], self.page],
+                color,
+            },
+        ]
+    }
+
+    /// Tile with region and page of its animation frame at given time.
+    pub fn animated(&self, animations: &[MapTileAnimation], time: f32) -> Self {
+        let mut result = *self;
+        if let Some(frame) = self
+            .animation
+            .and_then(|index| animations.get(index))
+            .and_then(|animation| animation.frame_at(time))
+        {
+            result.region = frame.region;
+            result.page = frame.page;
+        }
+        result
+    }
+
+    /// Texture coordinates with flips applied, for top-left, top-right,
+    /// bottom-right and bottom-left corners.
+    pub fn uvs(&self) -> [[f32; 2]; 4] {
+        [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].map(|[mut u, mut v]| {
+            if self.flip_x {
+                u = 1.0 - u;
+            }
+            if self.flip_y {
+                v = 1.0 - v;
+            }
+            if self.flip_diagonal {
+                std::mem::swap(&mut u, &mut v);
+            }
+            [
+                self.region.x + self.region.w * u,
+                self.region.y + self.region.h * v,
+            ]
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapTileFrame {
+    pub region: Rect<f32, f32>,
+    pub page: f32,
+    /// Seconds.
+    pub duration: f32,
+}
+
+impl MapTileFrame {
+    pub fn new(region: Rect<f32, f32>, page: f32, duration: f32) -> Self {
+        Self {
+            region,
+            page,
+            duration,
+        }
+    }
+}
+
+/// Looping sequence of tile frames.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MapTileAnimation {
+    pub frames: Vec<MapTileFrame>,
+}
+
+impl MapTileAnimation {
+    pub fn new(frames: impl IntoIterator<Item = MapTileFrame>) -> Self {
+        Self {
+            frames: frames.into_iter().collect(),
+        }
+    }
+
+    pub fn frame(mut self, frame: MapTileFrame) -> Self {
+        self.frames.push(frame);
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    pub fn frame_at(&self, time: f32) -> Option<&MapTileFrame> {
+        animation_frame_index(self.frames.iter().map(|frame| frame.duration), time)
+            .and_then(|index| self.frames.get(index))
+    }
+}
+
+/// Intersection of rectangles with non-zero area.
+fn rectangle_overlap(a: Rect<f32, f32>, b: Rect<f32, f32>) -> Option<Rect<f32, f32>> {
+    let left = a.x.max(b.x);
+    let top = a.y.max(b.y);
+    let right = (a.x + a.w).min(b.x + b.w);
+    let bottom = (a.y + a.h).min(b.y + b.h);
+    (right - left > f32::EPSILON && bottom - top > f32::EPSILON)
+        .then(|| Rect::new(left, top, right - left, bottom - top))
+}
+
+/// Index of frame active at given time of looping animation.
+pub(crate) fn animation_frame_index(
+    durations: impl Iterator<Item = f32> + Clone,
+    time: f32,
+) -> Option<usize> {
+    let total = durations.clone().sum::<f32>();
+    if total <= 0.0 {
+        return durations.clone().next().map(|_| 0);
+    }
+    let mut time = time.rem_euclid(total);
+    let mut last = None;
+    for (index, duration) in durations.enumerate() {
+        if time < duration {
+            return Some(index);
+        }
+        time -= duration;
+        last = Some(index);
+    }
+    last
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -724,12 +1647,92 @@ impl MapCollider {
     pub fn collides_with_point(&self, point: Vec2<f32>, mask: u32) -> bool {
         self.enabled && self.mask & mask != 0 && self.rectangle.contains_point(point)
     }
+
+    /// Parts of collider left after cutting out region.
+    pub fn subtract(&self, region: Rect<f32, f32>) -> Vec<Self> {
+        let Some(cut) = rectangle_overlap(self.rectangle, region) else {
+            return vec![*self];
+        };
+        let rect = self.rectangle;
+        [
+            Rect::new(rect.x, rect.y, rect.w, cut.y - rect.y),
+            Rect::new(
+                rect.x,
+                cut.y + cut.h,
+                rect.w,
+                rect.y + rect.h - cut.y - cut.h,
+            ),
+            Rect::new(rect.x, cut.y, cut.x - rect.x, cut.h),
+            Rect::new(cut.x + cut.w, cut.y, rect.x + rect.w - cut.x - cut.w, cut.h),
+        ]
+        .into_iter()
+        .filter(|part| part.w > f32::EPSILON && part.h > f32::EPSILON)
+        .map(|rectangle| Self { rectangle, ..*self })
+        .collect()
+    }
+
+    /// Greedily merges adjacent colliders with the same mask and state,
+    /// first along rows, then stacks equal rows vertically.
+    pub fn merge(colliders: impl IntoIterator<Item = Self>) -> Vec<Self> {
+        fn near(a: f32, b: f32) -> bool {
+            (a - b).abs() <= 1.0e-4 * a.abs().max(b.abs()).max(1.0)
+        }
+
+        let mut colliders = colliders.into_iter().collect::<Vec<_>>();
+        colliders.sort_by(|a, b| {
+            (a.mask, a.enabled)
+                .cmp(&(b.mask, b.enabled))
+                .then(a.rectangle.y.total_cmp(&b.rectangle.y))
+                .then(a.rectangle.h.total_cmp(&b.rectangle.h))
+                .then(a.rectangle.x.total_cmp(&b.rectangle.x))
+        });
+        let mut rows = Vec::<Self>::with_capacity(colliders.len());
+        for collider in colliders {
+            if let Some(last) = rows.last_mut()
+                && last.mask == collider.mask
+                && last.enabled == collider.enabled
+                && near(last.rectangle.y, collider.rectangle.y)
+                && near(last.rectangle.h, collider.rectangle.h)
+                && near(last.rectangle.x + last.rectangle.w, collider.rectangle.x)
+            {
+                last.rectangle.w = collider.rectangle.x + collider.rectangle.w - last.rectangle.x;
+                continue;
+            }
+            rows.push(collider);
+        }
+        rows.sort_by(|a, b| {
+            (a.mask, a.enabled)
+                .cmp(&(b.mask, b.enabled))
+                .then(a.rectangle.x.total_cmp(&b.rectangle.x))
+                .then(a.rectangle.w.total_cmp(&b.rectangle.w))
+                .then(a.rectangle.y.total_cmp(&b.rectangle.y))
+        });
+        let mut result = Vec::<Self>::with_capacity(rows.len());
+        for collider in rows {
+            if let Some(last) = result.last_mut()
+                && last.mask == collider.mask
+                && last.enabled == collider.enabled
+                && near(last.rectangle.x, collider.rectangle.x)
+                && near(last.rectangle.w, collider.rectangle.w)
+                && near(last.rectangle.y + last.rectangle.h, collider.rectangle.y)
+            {
+                last.rectangle.h = collider.rectangle.y + collider.rectangle.h - last.rectangle.y;
+                continue;
+            }
+            result.push(collider);
+        }
+        result
+    }
 }
 
 pub struct MapRenderer<'a> {
     pub clip_region: Option<Rect<f32, f32>>,
     pub clip_each_tile: bool,
     pub show_colliders: Option<(ShaderRef, Rgba<f32>, GlowBlending)>,
+    /// Time in seconds used to pick animated tiles frames.
+    pub time: f32,
+    /// Skips layers, chunks and clipped tiles outside of camera view.
+    pub frustum_culling: bool,
     map: &'a Map,
 }
 
@@ -744,6 +1747,55 @@ impl MapRenderer<'_> {
         self
     }
 
+    pub fn time(mut self, value: f32) -> Self {
+        self.time = value;
+        self
+    }
+
+    pub fn frustum_culling(mut self, value: bool) -> Self {
+        self.frustum_culling = value;
+        self
+    }
+
+    fn is_region_visible(
+        &self,
+        region: Rect<f32, f32>,
+        view_region: Option<Rect<f32, f32>>,
+    ) -> bool {
+        self.clip_region
+            .is_none_or(|clip_region| region.collides_with_rect(clip_region))
+            && view_region.is_none_or(|view_region| region.collides_with_rect(view_region))
+    }
+
+    fn is_region_fully_visible(
+        &self,
+        region: Rect<f32, f32>,
+        view_region: Option<Rect<f32, f32>>,
+    ) -> bool {
+        self.clip_region
+            .is_none_or(|clip_region| clip_region.contains_rect(region))
+            && view_region.is_none_or(|view_region| view_region.contains_rect(region))
+    }
+
+    /// Camera view bounds in space of given transform.
+    fn view_region(camera_rectangle: Rect<f32, f32>, transform: Mat4<f32>) -> Rect<f32, f32> {
+        let matrix = transform.inverted();
+        let rectangle = camera_rectangle;
+        let vertices = [
+            matrix.mul_point(Vec2::new(rectangle.x, rectangle.y)),
+            matrix.mul_point(Vec2::new(rectangle.x + rectangle.w, rectangle.y)),
+            matrix.mul_point(Vec2::new(rectangle.x, rectangle.y + rectangle.h)),
+            matrix.mul_point(Vec2::new(
+                rectangle.x + rectangle.w,
+                rectangle.y + rectangle.h,
+            )),
+        ];
+        Rect::new(vertices[0].x, vertices[0].y, 0.0, 0.0)
+            .expanded_to_contain_point(vertices[1])
+            .expanded_to_contain_point(vertices[2])
+            .expanded_to_contain_point(vertices[3])
+    }
+
     pub fn show_colliders(
         mut self,
         shader: ShaderRef,
@@ -765,9 +1817,15 @@ impl Drawable for MapRenderer<'_> {
                 if !layer.visible || layer.tiles.is_empty() {
                     continue;
                 }
+                let transform = context.top_transform()
+                    * transform_to_matrix(self.map.transform)
+                    * transform_to_matrix(level.transform)
+                    * transform_to_matrix(layer.transform);
+                let view_region = self.frustum_culling.then(|| {
+                    Self::view_region(graphics.state().main_camera.world_rectangle(), transform)
+                });
                 if let Some(visibility_region) = layer.visibility_region
-                    && let Some(clip_region) = self.clip_region
-                    && !visibility_region.collides_with_rect(clip_region)
+                    && !self.is_region_visible(visibility_region, view_region)
                 {
                     continue;
                 }
@@ -799,59 +1857,68 @@ impl Drawable for MapRenderer<'_> {
                     wireframe: context.wireframe,
                 };
                 graphics.state_mut().stream.batch_optimized(batch);
-                let transform = context.top_transform()
-                    * transform_to_matrix(self.map.transform)
-                    * transform_to_matrix(level.transform)
-                    * transform_to_matrix(layer.transform);
-                graphics.state_mut().stream.transformed(
-                    move |stream| {
-                        for tile in &layer.tiles {
-                            if !tile.visible {
-                                continue;
+                let opacity = layer.opacity;
+                let vertex_transform = move |vertex: &mut Vertex| {
+                    let point = transform.mul_point(Vec2::from(vertex.position));
+                    vertex.position[0] = point.x;
+                    vertex.position[1] = point.y;
+                    vertex.color[3] *= opacity;
+                };
+                if layer.chunk_size.is_some_and(|size| size > 0.0) {
+                    layer.ensure_chunks();
+                    let chunks = layer.cache.chunks.read().unwrap();
+                    graphics.state_mut().stream.transformed(
+                        |stream| {
+                            for chunk in chunks.iter().flat_map(|chunks| chunks.values()) {
+                                if !self.is_region_visible(chunk.bounds, view_region) {
+                                    continue;
+                                }
+                                let clip_tiles = self.clip_each_tile
+                                    && !self.is_region_fully_visible(chunk.bounds, view_region);
+                                if clip_tiles {
+                                    for index in &chunk.tiles {
+                                        let tile = &layer.tiles[*index];
+                                        if self.is_region_visible(tile.rectangle, view_region) {
+                                            stream.quad(tile.vertices());
+                                        }
+                                    }
+                                } else {
+                                    stream.extend(
+                                        chunk.vertices.iter().copied(),
+                                        chunk.triangles.iter().copied(),
+                                    );
+                                }
+                                for index in &chunk.animated {
+                                    let tile = &layer.tiles[*index];
+                                    if clip_tiles
+                                        && !self.is_region_visible(tile.rectangle, view_region)
+                                    {
+                                        continue;
+                                    }
+                                    let tile = tile.animated(&layer.animations, self.time);
+                                    stream.quad(tile.vertices());
+                                }
                             }
-                            if self.clip_each_tile
-                                && let Some(clip_region) = self.clip_region
-                                && !tile.rectangle.collides_with_rect(clip_region)
-                            {
-                                continue;
+                        },
+                        vertex_transform,
+                    );
+                } else {
+                    graphics.state_mut().stream.transformed(
+                        |stream| {
+                            for tile in &layer.tiles {
+                                if !tile.visible
+                                    || (self.clip_each_tile
+                                        && !self.is_region_visible(tile.rectangle, view_region))
+                                {
+                                    continue;
+                                }
+                                let tile = tile.animated(&layer.animations, self.time);
+                                stream.quad(tile.vertices());
                             }
-                            let offset = tile.rectangle.position();
-                            let size = tile.rectangle.extent();
-                            let color = tile.color.into_array();
-                            stream.quad([
-                                Vertex {
-                                    position: [offset.x, offset.y],
-                                    uv: [tile.region.x, tile.region.y, tile.page],
-                                    color,
-                                },
-                                Vertex {
-                                    position: [offset.x + size.w, offset.y],
-                                    uv: [tile.region.x + tile.region.w, tile.region.y, tile.page],
-                                    color,
-                                },
-                                Vertex {
-                                    position: [offset.x + size.w, offset.y + size.h],
-                                    uv: [
-                                        tile.region.x + tile.region.w,
-                                        tile.region.y + tile.region.h,
-                                        tile.page,
-                                    ],
-                                    color,
-                                },
-                                Vertex {
-                                    position: [offset.x, offset.y + size.h],
-                                    uv: [tile.region.x, tile.region.y + tile.region.h, tile.page],
-                                    color,
-                                },
-                            ]);
-                        }
-                    },
-                    |vertex| {
-                        let point = transform.mul_point(Vec2::from(vertex.position));
-                        vertex.position[0] = point.x;
-                        vertex.position[1] = point.y;
-                    },
-                );
+                        },
+                        vertex_transform,
+                    );
+                }
             }
             let Some((shader, color, blending)) = &self.show_colliders else {
                 continue;
@@ -922,3 +1989,137 @@ impl Drawable for MapRenderer<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_layer_chunks() {
+        let tile = |x: f32, y: f32| {
+            MapTile::new(
+                Rect::new(x, y, 16.0, 16.0),
+                Rect::new(0.0, 0.0, 1.0, 1.0),
+                0.0,
+            )
+        };
+        let mut layer = MapLayer::default()
+            .chunk_size(32.0)
+            .scale(Vec3::new(2.0, 2.0, 1.0))
+            .tiles([tile(0.0, 0.0), tile(16.0, 0.0), tile(64.0, 0.0)]);
+        assert_eq!(layer.chunks_count(), 2);
+
+        layer.tile_mut(2).unwrap().visible = false;
+        assert_eq!(layer.chunks_count(), 1);
+        layer.tiles_mut().push(tile(0.0, 64.0));
+        assert_eq!(layer.chunks_count(), 2);
+
+        let map = Map::default().level(MapLevel::default().layer(layer));
+        let renderer = map.draw();
+        let view_region = MapRenderer::view_region(
+            Rect::new(0.0, 0.0, 40.0, 40.0),
+            transform_to_matrix(map.levels[0].layers[0].transform),
+        );
+        assert_eq!(view_region, Rect::new(0.0, 0.0, 20.0, 20.0));
+        let chunks = map.levels[0].layers[0].cache.chunks.read().unwrap();
+        let bounds = chunks
+            .iter()
+            .flat_map(|chunks| chunks.values())
+            .map(|chunk| chunk.bounds)
+            .collect::<Vec<_>>();
+        assert_eq!(bounds[0], Rect::new(0.0, 0.0, 32.0, 16.0));
+        assert!(renderer.is_region_visible(bounds[0], Some(view_region)));
+        assert!(!renderer.is_region_fully_visible(bounds[0], Some(view_region)));
+        assert!(!renderer.is_region_visible(bounds[1], Some(view_region)));
+    }
+
+    #[test]
+    fn test_ldtk_map_builder_levels() {
+        let mut ldtk =
+            serde_json::from_str::<Ldtk>(include_str!("../../resources/ldtk/world.ldtk")).unwrap();
+        let mut level = ldtk.levels.remove(0);
+        level.layer_instances = None;
+        level.bg_color = "#ff0000".to_owned();
+        level.bg_rel_path = Some("background.png".to_owned());
+        level.bg_pos = Some(crate::map::ldtk::LevelBackgroundPosition {
+            crop_rect: vec![16.0, 0.0, 32.0, 32.0],
+            scale: vec![2.0, 2.0],
+            top_left_px: vec![8, 4],
+        });
+        ldtk.worlds = ["north", "south"]
+            .into_iter()
+            .map(|identifier| crate::map::ldtk::World {
+                identifier: identifier.to_owned(),
+                iid: identifier.to_owned(),
+                levels: vec![level.clone()],
+                world_grid_height: 256,
+                world_grid_width: 256,
+                world_layout: None,
+            })
+            .collect();
+        ldtk.worlds[1].levels[0].iid = "south_level".to_owned();
+
+        let builder = LdtkMapBuilder::default()
+            .only_worlds(["south".to_owned()])
+            .level_backgrounds(true)
+            .image_size_extractor(|_| Some(Vec2::new(64.0, 32.0)));
+        let levels = builder.levels(&ldtk).collect::<Vec<_>>();
+        assert_eq!(levels.len(), ldtk.levels.len() + 1);
+        assert_eq!(levels.last().unwrap().iid, "south_level");
+
+        let result = builder.build_level(&ldtk, levels.last().unwrap());
+        assert_eq!(result.layers.len(), 2);
+        assert_eq!(
+            result.layers[0].all_tiles()[0].color,
+            Rgba::new(1.0, 0.0, 0.0, 1.0)
+        );
+        let background = result.layers[1].all_tiles()[0];
+        assert_eq!(background.rectangle, Rect::new(8.0, 4.0, 64.0, 64.0));
+        assert_eq!(background.region, Rect::new(0.25, 0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn test_map_layer_cell_edits() {
+        let tile = |u: f32| {
+            MapTile::new(
+                Rect::new(0.0, 0.0, 16.0, 16.0),
+                Rect::new(u, 0.0, 0.1, 0.1),
+                0.0,
+            )
+        };
+        let mut layer = MapLayer::default()
+            .chunk_size(32.0)
+            .grid_cell_size(Vec2::new(16.0, 16.0));
+        for x in 0..4 {
+            layer.set_tile(Vec2::new(x, 0), tile(x as f32 * 0.1));
+        }
+        assert_eq!(layer.chunks_count(), 2);
+
+        layer.set_tile(Vec2::new(3, 0), tile(0.5));
+        assert_eq!(
+            layer
+                .cache
+                .dirty_chunks
+                .read()
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![&Vec2::new(1, 0)]
+        );
+        assert_eq!(layer.chunks_count(), 2);
+        assert_eq!(layer.all_tiles().len(), 4);
+
+        layer.remove_tiles_at(Vec2::new(0, 0));
+        assert_eq!(layer.tile_at(Vec2::new(1, 0)).unwrap().region.x, 0.1);
+        assert_eq!(layer.tile_at(Vec2::new(3, 0)).unwrap().region.x, 0.5);
+
+        layer.tile_at_mut(Vec2::new(1, 0)).unwrap().rectangle.y = 16.0;
+        assert!(layer.tile_at(Vec2::new(1, 0)).is_none());
+        assert_eq!(layer.tile_at(Vec2::new(1, 1)).unwrap().region.x, 0.1);
+        assert_eq!(layer.chunks_count(), 2);
+        let chunks = layer.cache.chunks.read().unwrap();
+        let chunk = &chunks.as_ref().unwrap()[&(0, 0)];
+        assert_eq!(chunk.tiles, vec![0]);
+        assert_eq!(chunk.bounds, Rect::new(16.0, 16.0, 16.0, 16.0));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::map::{
+    LdtkMapBuilder, Map,
+    ldtk::{Ldtk, Level},
+};
+use std::collections::HashSet;
+use vek::{Rect, Vec2};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapStreamEvent {
+    /// Level got built and added to map.
+    Loaded(String),
+    /// Level got removed from map.
+    Unloaded(String),
+    /// Focus point entered level.
+    Entered(String),
+    /// Focus point left level.
+    Exited(String),
+}
+
+impl MapStreamEvent {
+    /// Level IID of event.
+    pub fn level(&self) -> &str {
+        match self {
+            Self::Loaded(iid) | Self::Unloaded(iid) | Self::Entered(iid) | Self::Exited(iid) => iid,
+        }
+    }
+}
+
+/// Keeps only LDtk levels near focus area built in its map.
+pub struct LdtkMapStreamer {
+    /// Distance around focus area within which levels get loaded.
+    pub load_distance: f32,
+    /// Distance around focus area beyond which levels get unloaded.
+    pub unload_distance: f32,
+    /// Also loads neighbours of level containing focus point.
+    pub load_neighbours: bool,
+    /// Keeps at most this many levels built, nearest ones win.
+    pub max_loaded_levels: Option<usize>,
+    /// Limits how many levels get built per update.
+    pub max_loads_per_update: Option<usize>,
+    pub map: Map,
+    current: Option<String>,
+    events: Vec<MapStreamEvent>,
+}
+
+impl Default for LdtkMapStreamer {
+    fn default() -> Self {
+        Self {
+            load_distance: 0.0,
+            unload_distance: 0.0,
+            load_neighbours: true,
+            max_loaded_levels: None,
+            max_loads_per_update: None,
+            map: Default::default(),
+            current: None,
+            events: Default::default(),
+        }
+    }
+}
+
+impl LdtkMapStreamer {
+    pub fn load_distance(mut self, value: f32) -> Self {
+        self.load_distance = value;
+        self.unload_distance = self.unload_distance.max(value);
+        self
+    }
+
+    pub fn unload_distance(mut self, value: f32) -> Self {
+        self.unload_distance = value;
+        self
+    }
+
+    pub fn load_neighbours(mut self, value: bool) -> Self {
+        self.load_neighbours = value;
+        self
+    }
+
+    pub fn max_loaded_levels(mut self, value: usize) -> Self {
+        self.max_loaded_levels = Some(value);
+        self
+    }
+
+    pub fn max_loads_per_update(mut self, value: usize) -> Self {
+        self.max_loads_per_update = Some(value);
+        self
+    }
+
+    /// IID of level containing focus point.
+    pub fn current_level(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn loaded_levels(&self) -> impl Iterator<Item = &str> {
+        self.map
+            .levels
+            .iter()
+            .filter_map(|level| level.id.as_deref())
+    }
+
+    pub fn is_loaded(&self, iid: &str) -> bool {
+        self.map.level_by_id(iid).is_some()
+    }
+
+    pub fn drain_events(&mut self) -> impl Iterator<Item = MapStreamEvent> {
+        std::mem::take(&mut self.events).into_iter()
+    }
+
+    /// Loads and unloads levels around focus area, given in map space.
+    pub fn update(&mut self, ldtk: &Ldtk, builder: &LdtkMapBuilder, focus: Rect<f32, f32>) {
+        let levels = builder.levels(ldtk).collect::<Vec<_>>();
+        let center = Vec2::new(focus.x + focus.w * 0.5, focus.y + focus.h * 0.5);
+        let distance = |level: &Level| {
+            let rectangle = builder.level_rectangle(level);
+            let closest = Vec2::new(
+                center.x.clamp(rectangle.x, rectangle.x + rectangle.w),
+                center.y.clamp(rectangle.y, rectangle.y + rectangle.h),
+            );
+            closest.distance(center)
+        };
+
+        let current = levels
+            .iter()
+            .filter(|level| builder.level_rectangle(level).contains_point(center))
+            .min_by_key(|level| level.world_depth)
+            .map(|level| level.iid.clone());
+        if current != self.current {
+            if let Some(iid) = self.current.take() {
+                self.events.push(MapStreamEvent::Exited(iid));
+            }
+            if let Some(iid) = current.clone() {
+                self.events.push(MapStreamEvent::Entered(iid));
+            }
+            self.current = current;
+        }
+
+        let load_area = expanded(focus, self.load_distance);
+        let unload_area = expanded(focus, self.unload_distance.max(self.load_distance));
+        let mut desired = levels
+            .iter()
+            .filter(|level| builder.level_rectangle(level).collides_with_rect(load_area))
+            .map(|level| level.iid.as_str())
+            .collect::<HashSet<_>>();
+        if self.load_neighbours
+            && let Some(level) = levels
+                .iter()
+                .find(|level| Some(&level.iid) == self.current.as_ref())
+        {
+            desired.extend(
+                level
+                    .neighbours
+                    .iter()
+                    .map(|neighbour| neighbour.level_iid.as_str())
+                    .filter(|iid| levels.iter().any(|level| level.iid == *iid)),
+            );
+        }
+        if let Some(limit) = self.max_loaded_levels {
+            let mut nearest = levels
+                .iter()
+                .filter(|level| desired.contains(level.iid.as_str()))
+                .collect::<Vec<_>>();
+            nearest.sort_by(|a, b| {
+                let a_current = Some(&a.iid) == self.current.as_ref();
+                let b_current = Some(&b.iid) == self.current.as_ref();
+                b_current
+                    .cmp(&a_current)
+                    .then(distance(a).total_cmp(&distance(b)))
+            });
+            nearest.truncate(limit);
+            desired = nearest
+                .into_iter()
+                .map(|level| level.iid.as_str())
+                .collect();
+        }
+
+        let events = &mut self.events;
+        self.map.levels.retain(|map_level| {
+            let Some(iid) = map_level.id.as_deref() else {
+                return true;
+            };
+            let keep = desired.contains(iid)
+                || levels.iter().any(|level| {
+                    level.iid == iid
+                        && builder
+                            .level_rectangle(level)
+                            .collides_with_rect(unload_area)
+                });
+            if !keep {
+                events.push(MapStreamEvent::Unloaded(iid.to_owned()));
+            }
+            keep
+        });
+
+        let mut to_load = levels
+            .iter()
+            .filter(|level| desired.contains(level.iid.as_str()) && !self.is_loaded(&level.iid))
+            .collect::<Vec<_>>();
+        to_load.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        if let Some(limit) = self.max_loads_per_update {
+            to_load.truncate(limit);
+        }
+        for level in to_load {
+            self.map.levels.push(builder.build_level(ldtk, level));
+            self.events
+                .push(MapStreamEvent::Loaded(level.iid.to_owned()));
+        }
+
+        if let Some(limit) = self.max_loaded_levels {
+            while self.map.levels.len() > limit {
+                let farthest = self
+                    .map
+                    .levels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, map_level)| {
+                        map_level
+                            .id
+                            .as_deref()
+                            .is_some_and(|iid| !desired.contains(iid))
+                    })
+                    .filter_map(|(index, map_level)| {
+                        let iid = map_level.id.as_deref()?;
+                        let level = levels.iter().find(|level| level.iid == iid)?;
+                        Some((index, distance(level)))
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(index, _)| index);
+                let Some(index) = farthest else {
+                    break;
+                };
+                if let Some(iid) = self.map.levels.remove(index).id {
+                    self.events.push(MapStreamEvent::Unloaded(iid));
+                }
+            }
+        }
+    }
+
+    /// Unloads all levels.
+    pub fn clear(&mut self) {
+        for level in self.map.levels.drain(..) {
+            if let Some(iid) = level.id {
+                self.events.push(MapStreamEvent::Unloaded(iid));
+            }
+        }
+        if let Some(iid) = self.current.take() {
+            self.events.push(MapStreamEvent::Exited(iid));
+        }
+    }
+}
+
+fn expanded(rectangle: Rect<f32, f32>, distance: f32) -> Rect<f32, f32> {
+    Rect::new(
+        rectangle.x - distance,
+        rectangle.y - distance,
+        rectangle.w + distance * 2.0,
+        rectangle.h + distance * 2.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ldtk() -> Ldtk {
+        let mut ldtk =
+            serde_json::from_str::<Ldtk>(include_str!("../../resources/ldtk/world.ldtk")).unwrap();
+        let mut template = ldtk.levels[0].clone();
+        template.neighbours.clear();
+        template.layer_instances = None;
+        ldtk.levels = (0..5)
+            .map(|index| {
+                let mut level = template.clone();
+                level.iid = format!("level{index}");
+                level.identifier = level.iid.clone();
+                level.world_x = index * 256;
+                level.world_y = 0;
+                level
+            })
+            .collect();
+        ldtk
+    }
+
+    fn loaded(streamer: &LdtkMapStreamer) -> Vec<&str> {
+        let mut result = streamer.loaded_levels().collect::<Vec<_>>();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn test_map_streamer() {
+        let ldtk = ldtk();
+        let builder = LdtkMapBuilder::default().pixel_world_scale(1.0);
+        let focus = |x: f32| Rect::new(x, 128.0, 1.0, 1.0);
+
+        let mut streamer = LdtkMapStreamer::default()
+            .unload_distance(128.0)
+            .load_neighbours(false);
+        streamer.update(&ldtk, &builder, focus(128.0));
+        assert_eq!(loaded(&streamer), vec!["level0"]);
+        assert_eq!(streamer.current_level(), Some("level0"));
+        streamer.update(&ldtk, &builder, focus(300.0));
+        assert_eq!(loaded(&streamer), vec!["level0", "level1"]);
+        streamer.update(&ldtk, &builder, focus(400.0));
+        assert_eq!(loaded(&streamer), vec!["level1"]);
+        assert!(
+            streamer
+                .drain_events()
+                .any(|event| event == MapStreamEvent::Unloaded("level0".to_owned()))
+        );
+
+        let mut streamer = LdtkMapStreamer::default()
+            .load_distance(600.0)
+            .max_loaded_levels(2);
+        streamer.update(&ldtk, &builder, focus(128.0));
+        assert_eq!(loaded(&streamer), vec!["level0", "level1"]);
+        streamer.drain_events().for_each(drop);
+        streamer.update(&ldtk, &builder, focus(128.0));
+        assert_eq!(streamer.drain_events().count(), 0);
+        streamer.update(&ldtk, &builder, focus(900.0));
+        assert_eq!(loaded(&streamer), vec!["level3", "level4"]);
+    }
+}
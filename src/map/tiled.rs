@@ -0,0 +1,1227 @@
+use crate::map::{Map, MapCollider, MapLayer, MapLevel, MapTile, MapTileAnimation, MapTileFrame};
+use base64::Engine;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value};
+use spitfire_draw::{
+    sprite::SpriteTexture,
+    utils::{ShaderRef, TextureRef},
+};
+use spitfire_glow::renderer::GlowTextureFiltering;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    io::Read,
+};
+use vek::{Quaternion, Rect, Rgba, Transform, Vec2, Vec3};
+use xml::reader::{EventReader, XmlEvent};
+
+pub const TILED_FLIP_HORIZONTAL: u32 = 0x80000000;
+pub const TILED_FLIP_VERTICAL: u32 = 0x40000000;
+pub const TILED_FLIP_DIAGONAL: u32 = 0x20000000;
+const TILED_FLAGS: u32 = 0xF0000000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledMap {
+    pub width: u32,
+    pub height: u32,
+    pub tilewidth: u32,
+    pub tileheight: u32,
+    pub orientation: String,
+    pub infinite: bool,
+    pub backgroundcolor: Option<String>,
+    pub layers: Vec<TiledLayer>,
+    pub tilesets: Vec<TiledTileset>,
+    pub properties: Vec<TiledProperty>,
+}
+
+impl Default for TiledMap {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            tilewidth: 0,
+            tileheight: 0,
+            orientation: "orthogonal".to_owned(),
+            infinite: false,
+            backgroundcolor: None,
+            layers: Default::default(),
+            tilesets: Default::default(),
+            properties: Default::default(),
+        }
+    }
+}
+
+impl TiledMap {
+    pub fn from_tmj(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    pub fn from_tmx(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let root = XmlNode::parse(bytes)?;
+        if root.name != "map" {
+            return Err(format!("Expected TMX `map` root element, found: `{}`", root.name).into());
+        }
+        Ok(serde_json::from_value(root.map_to_json())?)
+    }
+
+    /// Decodes base64 and compressed layer data into plain tile lists.
+    pub fn decode_layers(&mut self) -> Result<(), Box<dyn Error>> {
+        fn visit(layers: &mut [TiledLayer]) -> Result<(), Box<dyn Error>> {
+            for layer in layers {
+                let encoding = layer.encoding.take();
+                let compression = layer.compression.take();
+                if let Some(data) = layer.data.as_mut() {
+                    *data = TiledLayerData::Tiles(
+                        data.decode(encoding.as_deref(), compression.as_deref())?,
+                    );
+                }
+                for chunk in &mut layer.chunks {
+                    chunk.data = TiledLayerData::Tiles(
+                        chunk
+                            .data
+                            .decode(encoding.as_deref(), compression.as_deref())?,
+                    );
+                }
+                visit(&mut layer.layers)?;
+            }
+            Ok(())
+        }
+
+        visit(&mut self.layers)
+    }
+
+    /// (tileset index, tileset, local tile ID) of tileset owning global tile ID.
+    pub fn tileset_for_gid(&self, gid: u32) -> Option<(usize, &TiledTileset, u32)> {
+        let gid = gid & !TILED_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+        self.tilesets
+            .iter()
+            .enumerate()
+            .filter(|(_, tileset)| tileset.firstgid <= gid)
+            .max_by_key(|(_, tileset)| tileset.firstgid)
+            .map(|(index, tileset)| (index, tileset, gid - tileset.firstgid))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TiledLayerKind {
+    #[default]
+    TileLayer,
+    ObjectGroup,
+    ImageLayer,
+    Group,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TiledLayerData {
+    Tiles(Vec<u32>),
+    Encoded(String),
+}
+
+impl Default for TiledLayerData {
+    fn default() -> Self {
+        Self::Tiles(Default::default())
+    }
+}
+
+impl TiledLayerData {
+    pub fn decode(
+        &self,
+        encoding: Option<&str>,
+        compression: Option<&str>,
+    ) -> Result<Vec<u32>, Box<dyn Error>> {
+        let text = match self {
+            Self::Tiles(tiles) => return Ok(tiles.to_owned()),
+            Self::Encoded(text) => text.trim(),
+        };
+        match encoding {
+            Some("csv") => Ok(text
+                .split(',')
+                .map(|item| item.trim())
+                .filter(|item| !item.is_empty())
+                .map(|item| item.parse::<u32>())
+                .collect::<Result<_, _>>()?),
+            Some("base64") => {
+                let bytes = base64::engine::general_purpose::STANDARD.decode(text)?;
+                let bytes = match compression {
+                    None | Some("") => bytes,
+                    Some("zlib") => {
+                        let mut result = vec![];
+                        ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut result)?;
+                        result
+                    }
+                    Some("gzip") => {
+                        let mut result = vec![];
+                        GzDecoder::new(bytes.as_slice()).read_to_end(&mut result)?;
+                        result
+                    }
+                    Some(compression) => {
+                        return Err(format!(
+                            "Unsupported Tiled layer compression: `{compression}`"
+                        )
+                        .into());
+                    }
+                };
+                Ok(bytes
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect())
+            }
+            encoding => Err(format!("Unsupported Tiled layer encoding: `{encoding:?}`").into()),
+        }
+    }
+
+    /// Tiles of already decoded data.
+    pub fn tiles(&self) -> &[u32] {
+        match self {
+            Self::Tiles(tiles) => tiles,
+            Self::Encoded(_) => &[],
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledChunk {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub data: TiledLayerData,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledLayer {
+    pub id: u32,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: TiledLayerKind,
+    pub class: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub offsetx: f32,
+    pub offsety: f32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub data: Option<TiledLayerData>,
+    pub encoding: Option<String>,
+    pub compression: Option<String>,
+    pub chunks: Vec<TiledChunk>,
+    pub objects: Vec<TiledObject>,
+    pub layers: Vec<TiledLayer>,
+    pub image: Option<String>,
+    pub imagewidth: u32,
+    pub imageheight: u32,
+    pub properties: Vec<TiledProperty>,
+}
+
+impl Default for TiledLayer {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: Default::default(),
+            kind: Default::default(),
+            class: Default::default(),
+            visible: true,
+            opacity: 1.0,
+            offsetx: 0.0,
+            offsety: 0.0,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            data: None,
+            encoding: None,
+            compression: None,
+            chunks: Default::default(),
+            objects: Default::default(),
+            layers: Default::default(),
+            image: None,
+            imagewidth: 0,
+            imageheight: 0,
+            properties: Default::default(),
+        }
+    }
+}
+
+impl TiledLayer {
+    /// Tile chunks of layer, finite layers are reported as single chunk.
+    pub fn chunks(&self) -> impl Iterator<Item = Cow<'_, TiledChunk>> {
+        self.data
+            .as_ref()
+            .map(|data| {
+                Cow::Owned(TiledChunk {
+                    x: self.x,
+                    y: self.y,
+                    width: self.width,
+                    height: self.height,
+                    data: data.to_owned(),
+                })
+            })
+            .into_iter()
+            .chain(self.chunks.iter().map(Cow::Borrowed))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledTileset {
+    pub firstgid: u32,
+    /// External tileset file, resolved by asset protocol.
+    pub source: Option<String>,
+    pub name: String,
+    pub tilewidth: u32,
+    pub tileheight: u32,
+    pub tilecount: u32,
+    pub columns: u32,
+    pub margin: u32,
+    pub spacing: u32,
+    pub image: Option<String>,
+    pub imagewidth: u32,
+    pub imageheight: u32,
+    pub tiles: Vec<TiledTile>,
+    pub properties: Vec<TiledProperty>,
+}
+
+impl TiledTileset {
+    pub fn from_tsj(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    pub fn from_tsx(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let root = XmlNode::parse(bytes)?;
+        if root.name != "tileset" {
+            return Err(format!(
+                "Expected TSX `tileset` root element, found: `{}`",
+                root.name
+            )
+            .into());
+        }
+        Ok(serde_json::from_value(root.tileset_to_json())?)
+    }
+
+    pub fn tile(&self, id: u32) -> Option<&TiledTile> {
+        self.tiles.iter().find(|tile| tile.id == id)
+    }
+
+    /// Normalized texture region of local tile ID.
+    pub fn tile_region(&self, id: u32) -> Option<Rect<f32, f32>> {
+        if self.columns == 0 || self.imagewidth == 0 || self.imageheight == 0 {
+            return None;
+        }
+        let col = id % self.columns;
+        let row = id / self.columns;
+        let x = self.margin + col * (self.tilewidth + self.spacing);
+        let y = self.margin + row * (self.tileheight + self.spacing);
+        Some(Rect {
+            x: x as f32 / self.imagewidth as f32,
+            y: y as f32 / self.imageheight as f32,
+            w: self.tilewidth as f32 / self.imagewidth as f32,
+            h: self.tileheight as f32 / self.imageheight as f32,
+        })
+    }
+
+    /// Animation frames of local tile ID, as (region, duration in seconds).
+    pub fn tile_animation(&self, id: u32) -> Option<Vec<(Rect<f32, f32>, f32)>> {
+        let tile = self.tile(id)?;
+        if tile.animation.is_empty() {
+            return None;
+        }
+        tile.animation
+            .iter()
+            .map(|frame| {
+                Some((
+                    self.tile_region(frame.tileid)?,
+                    frame.duration as f32 / 1000.0,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledTile {
+    pub id: u32,
+    #[serde(rename = "type", alias = "class")]
+    pub class: String,
+    pub animation: Vec<TiledFrame>,
+    /// Collision shapes of tile.
+    pub objectgroup: Option<TiledLayer>,
+    pub properties: Vec<TiledProperty>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledFrame {
+    pub tileid: u32,
+    /// Milliseconds.
+    pub duration: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    #[serde(rename = "type", alias = "class")]
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub rotation: f32,
+    pub visible: bool,
+    pub gid: Option<u32>,
+    pub point: bool,
+    pub ellipse: bool,
+    pub polygon: Vec<TiledPoint>,
+    pub polyline: Vec<TiledPoint>,
+    pub properties: Vec<TiledProperty>,
+}
+
+impl Default for TiledObject {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: Default::default(),
+            class: Default::default(),
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            rotation: 0.0,
+            visible: true,
+            gid: None,
+            point: false,
+            ellipse: false,
+            polygon: Default::default(),
+            polyline: Default::default(),
+            properties: Default::default(),
+        }
+    }
+}
+
+impl TiledObject {
+    /// Axis aligned bounds of object shape, relative to its parent.
+    pub fn bounding_box(&self) -> Rect<f32, f32> {
+        let points = if self.polygon.is_empty() {
+            &self.polyline
+        } else {
+            &self.polygon
+        };
+        if points.is_empty() {
+            return Rect::new(self.x, self.y, self.width, self.height);
+        }
+        let (min, max) = points.iter().fold(
+            (
+                Vec2::broadcast(f32::INFINITY),
+                Vec2::broadcast(f32::NEG_INFINITY),
+            ),
+            |(min, max), point| {
+                let point = Vec2::new(point.x, point.y);
+                (Vec2::partial_min(min, point), Vec2::partial_max(max, point))
+            },
+        );
+        Rect::new(self.x + min.x, self.y + min.y, max.x - min.x, max.y - min.y)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TiledProperty {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub property_type: String,
+    pub value: Value,
+}
+
+/// Typed access to custom properties of Tiled items.
+pub trait TiledProperties {
+    fn properties(&self) -> &[TiledProperty];
+
+    fn property(&self, name: &str) -> Option<&Value> {
+        self.properties()
+            .iter()
+            .find(|property| property.name == name)
+            .map(|property| &property.value)
+    }
+
+    fn property_int(&self, name: &str) -> Option<i64> {
+        self.property(name)?.as_i64()
+    }
+
+    fn property_float(&self, name: &str) -> Option<f64> {
+        self.property(name)?.as_f64()
+    }
+
+    fn property_bool(&self, name: &str) -> Option<bool> {
+        self.property(name)?.as_bool()
+    }
+
+    fn property_string(&self, name: &str) -> Option<&str> {
+        self.property(name)?.as_str()
+    }
+
+    /// Tiled colors are stored as `#AARRGGBB` or `#RRGGBB`.
+    fn property_color(&self, name: &str) -> Option<Rgba<f32>> {
+        parse_tiled_color(self.property(name)?.as_str()?)
+    }
+
+    /// ID of object referenced by object property.
+    fn property_object(&self, name: &str) -> Option<u32> {
+        self.property(name)?.as_u64().map(|value| value as u32)
+    }
+}
+
+macro_rules! impl_tiled_properties {
+    ($($type:ty),*) => {
+        $(
+            impl TiledProperties for $type {
+                fn properties(&self) -> &[TiledProperty] {
+                    &self.properties
+                }
+            }
+        )*
+    };
+}
+
+impl_tiled_properties!(TiledMap, TiledLayer, TiledTileset, TiledTile, TiledObject);
+
+pub fn parse_tiled_color(value: &str) -> Option<Rgba<f32>> {
+    let value = value.trim_start_matches('#');
+    let channel = |index: usize| {
+        u8::from_str_radix(value.get(index..index + 2)?, 16)
+            .ok()
+            .map(|value| value as f32 / 255.0)
+    };
+    match value.len() {
+        6 => Some(Rgba::new(channel(0)?, channel(2)?, channel(4)?, 1.0)),
+        8 => Some(Rgba::new(
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+            channel(0)?,
+        )),
+        _ => None,
+    }
+}
+
+pub struct TiledMapBuilder<'a> {
+    pub only_layers: Vec<String>,
+    pub pixel_world_scale: f32,
+    pub image_shader: Option<ShaderRef>,
+    pub sampler: Cow<'static, str>,
+    pub texture_filtering: GlowTextureFiltering,
+    /// Splits tile layers into chunks of given number of tiles per side.
+    pub chunk_tiles: Option<usize>,
+    #[allow(clippy::type_complexity)]
+    pub tileset_reference_extractor: Option<Box<dyn Fn(&str) -> String + 'a>>,
+    /// Maps tile collision shape to collider mask, `None` skips shape.
+    #[allow(clippy::type_complexity)]
+    pub collision_extractor: Box<dyn Fn(&TiledTile, &TiledObject) -> Option<u32> + 'a>,
+}
+
+impl Default for TiledMapBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            only_layers: Default::default(),
+            pixel_world_scale: 1.0,
+            image_shader: None,
+            sampler: "u_image".into(),
+            texture_filtering: Default::default(),
+            chunk_tiles: Some(16),
+            tileset_reference_extractor: None,
+            collision_extractor: Box::new(|_, _| Some(u32::MAX)),
+        }
+    }
+}
+
+impl<'a> TiledMapBuilder<'a> {
+    pub fn only_layers(mut self, layers: impl IntoIterator<Item = String>) -> Self {
+        self.only_layers = layers.into_iter().collect();
+        self
+    }
+
+    pub fn pixel_world_scale(mut self, scale: f32) -> Self {
+        self.pixel_world_scale = scale;
+        self
+    }
+
+    pub fn image_shader(mut self, shader: ShaderRef) -> Self {
+        self.image_shader = Some(shader);
+        self
+    }
+
+    pub fn sampler(mut self, sampler: impl Into<Cow<'static, str>>) -> Self {
+        self.sampler = sampler.into();
+        self
+    }
+
+    pub fn chunk_tiles(mut self, value: Option<usize>) -> Self {
+        self.chunk_tiles = value;
+        self
+    }
+
+    pub fn tileset_reference_extractor(mut self, extractor: impl Fn(&str) -> String + 'a) -> Self {
+        self.tileset_reference_extractor = Some(Box::new(extractor));
+        self
+    }
+
+    pub fn collision_extractor(
+        mut self,
+        extractor: impl Fn(&TiledTile, &TiledObject) -> Option<u32> + 'a,
+    ) -> Self {
+        self.collision_extractor = Box::new(extractor);
+        self
+    }
+
+    /// Builds single level map, tiles are laid out orthogonally.
+    pub fn build(&self, map: &TiledMap) -> Map {
+        let mut level = MapLevel::default();
+        self.build_layers(map, &map.layers, Vec2::zero(), 1.0, true, &mut level);
+        Map::new([level])
+    }
+
+    fn texture(&self, image: &str) -> SpriteTexture {
+        let reference = self
+            .tileset_reference_extractor
+            .as_ref()
+            .map(|extractor| extractor(image))
+            .unwrap_or_else(|| image.to_owned());
+        SpriteTexture {
+            sampler: self.sampler.clone(),
+            texture: TextureRef::name(reference),
+            filtering: self.texture_filtering,
+        }
+    }
+
+    fn layer_transform(&self, offset: Vec2<f32>) -> Transform<f32, f32, f32> {
+        Transform {
+            position: Vec3::new(
+                offset.x * self.pixel_world_scale,
+                offset.y * self.pixel_world_scale,
+                0.0,
+            ),
+            orientation: Quaternion::identity(),
+            scale: Vec3::one(),
+        }
+    }
+
+    fn build_layers(
+        &self,
+        map: &TiledMap,
+        layers: &[TiledLayer],
+        offset: Vec2<f32>,
+        opacity: f32,
+        visible: bool,
+        level: &mut MapLevel,
+    ) {
+        for layer in layers {
+            let offset = offset + Vec2::new(layer.offsetx, layer.offsety);
+            let opacity = opacity * layer.opacity;
+            let visible = visible && layer.visible;
+            if layer.kind == TiledLayerKind::Group {
+                self.build_layers(map, &layer.layers, offset, opacity, visible, level);
+                continue;
+            }
+            if !self.only_layers.is_empty() && !self.only_layers.contains(&layer.name) {
+                continue;
+            }
+            match layer.kind {
+                TiledLayerKind::TileLayer => {
+                    self.build_tile_layer(map, layer, offset, opacity, visible, level)
+                }
+                TiledLayerKind::ImageLayer => {
+                    let Some(image) = layer.image.as_deref() else {
+                        continue;
+                    };
+                    let rectangle = Rect::new(
+                        0.0,
+                        0.0,
+                        layer.imagewidth as f32 * self.pixel_world_scale,
+                        layer.imageheight as f32 * self.pixel_world_scale,
+                    );
+                    level.layers.push(MapLayer {
+                        visible,
+                        visibility_region: Some(rectangle),
+                        opacity,
+                        shader: self.image_shader.clone(),
+                        textures: vec![self.texture(image)],
+                        transform: self.layer_transform(offset),
+                        tiles: vec![MapTile::new(rectangle, Rect::new(0.0, 0.0, 1.0, 1.0), 0.0)],
+                        ..Default::default()
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn build_tile_layer(
+        &self,
+        map: &TiledMap,
+        layer: &TiledLayer,
+        offset: Vec2<f32>,
+        opacity: f32,
+        visible: bool,
+        level: &mut MapLevel,
+    ) {
+        let mut layers_per_tileset = BTreeMap::<usize, MapLayer>::default();
+        let mut animations = HashMap::<(usize, u32), Option<usize>>::default();
+        for chunk in layer.chunks() {
+            for (index, gid) in chunk.data.tiles().iter().copied().enumerate() {
+                let Some((tileset_index, tileset, id)) = map.tileset_for_gid(gid) else {
+                    continue;
+                };
+                let Some(region) = tileset.tile_region(id) else {
+                    continue;
+                };
+                let col = chunk.x + (index as u32 % chunk.width.max(1)) as i32;
+                let row = chunk.y + (index as u32 / chunk.width.max(1)) as i32;
+                let position = Vec2::new(
+                    (col * map.tilewidth as i32) as f32,
+                    ((row + 1) * map.tileheight as i32 - tileset.tileheight as i32) as f32,
+                );
+                let flip_x = gid & TILED_FLIP_HORIZONTAL != 0;
+                let flip_y = gid & TILED_FLIP_VERTICAL != 0;
+                let flip_diagonal = gid & TILED_FLIP_DIAGONAL != 0;
+                let map_layer = layers_per_tileset.entry(tileset_index).or_default();
+                let animation =
+                    *animations.entry((tileset_index, id)).or_insert_with(|| {
+                        let frames = tileset.tile_animation(id)?;
+                        Some(map_layer.add_animation(
+                            MapTileAnimation::new(frames.into_iter().map(|(region, duration)| {
+                                MapTileFrame::new(region, 0.0, duration)
+                            })),
+                        ))
+                    });
+                let mut tile = MapTile::new(
+                    Rect::new(
+                        position.x * self.pixel_world_scale,
+                        position.y * self.pixel_world_scale,
+                        tileset.tilewidth as f32 * self.pixel_world_scale,
+                        tileset.tileheight as f32 * self.pixel_world_scale,
+                    ),
+                    region,
+                    0.0,
+                )
+                .flip(flip_x, flip_y)
+                .flip_diagonal(flip_diagonal);
+                tile.animation = animation;
+                map_layer.tiles.push(tile);
+                let Some(definition) = tileset.tile(id) else {
+                    continue;
+                };
+                for object in definition
+                    .objectgroup
+                    .iter()
+                    .flat_map(|group| &group.objects)
+                {
+                    if object.point {
+                        continue;
+                    }
+                    let Some(mask) = (self.collision_extractor)(definition, object) else {
+                        continue;
+                    };
+                    // Inverse of `MapTile::uvs` mapping: diagonal swap goes before flips.
+                    let size = Vec2::new(tileset.tilewidth as f32, tileset.tileheight as f32);
+                    let mut shape = object.bounding_box();
+                    if flip_diagonal {
+                        shape = Rect::new(
+                            shape.y / size.y * size.x,
+                            shape.x / size.x * size.y,
+                            shape.h / size.y * size.x,
+                            shape.w / size.x * size.y,
+                        );
+                    }
+                    if flip_x {
+                        shape.x = size.x - shape.x - shape.w;
+                    }
+                    if flip_y {
+                        shape.y = size.y - shape.y - shape.h;
+                    }
+                    let origin = position + offset;
+                    level.colliders.push(MapCollider::new(
+                        Rect::new(
+                            (origin.x + shape.x) * self.pixel_world_scale,
+                            (origin.y + shape.y) * self.pixel_world_scale,
+                            shape.w * self.pixel_world_scale,
+                            shape.h * self.pixel_world_scale,
+                        ),
+                        mask,
+                    ));
+                }
+            }
+        }
+        for (index, map_layer) in layers_per_tileset {
+            let Some(image) = map.tilesets[index].image.as_deref() else {
+                continue;
+            };
+            let visibility_region = map_layer
+                .tiles
+                .iter()
+                .map(|tile| tile.rectangle)
+                .reduce(|current, item| current.union(item));
+            level.layers.push(MapLayer {
+                visible,
+                visibility_region,
+                opacity,
+                shader: self.image_shader.clone(),
+                textures: vec![self.texture(image)],
+                transform: self.layer_transform(offset),
+                chunk_size: self.chunk_tiles.map(|tiles| {
+                    (tiles as u32 * map.tilewidth.max(map.tileheight)) as f32
+                        * self.pixel_world_scale
+                }),
+                grid_cell_size: Some(
+                    Vec2::new(map.tilewidth as f32, map.tileheight as f32) * self.pixel_world_scale,
+                ),
+                ..map_layer
+            });
+        }
+    }
+}
+
+/// Minimal XML tree used to translate TMX/TSX into TMJ/TSJ layout.
+#[derive(Debug, Default)]
+struct XmlNode {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlNode>,
+    text: String,
+}
+
+impl XmlNode {
+    fn parse(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut stack = Vec::<XmlNode>::default();
+        for event in EventReader::new(bytes) {
+            match event? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(XmlNode {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect(),
+                    ..Default::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let node = stack.pop().ok_or("Unbalanced XML elements")?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(node),
+                        None => return Ok(node),
+                    }
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(node) = stack.last_mut() {
+                        node.text.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err("XML document has no root element".into())
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlNode> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn attributes_to_json(&self) -> JsonMap<String, Value> {
+        const TEXT_ATTRIBUTES: &[&str] = &[
+            "name",
+            "type",
+            "class",
+            "source",
+            "orientation",
+            "renderorder",
+            "staggeraxis",
+            "staggerindex",
+            "backgroundcolor",
+            "encoding",
+            "compression",
+            "version",
+            "tiledversion",
+            "color",
+            "tintcolor",
+            "trans",
+            "draworder",
+            "template",
+            "value",
+        ];
+        self.attributes
+            .iter()
+            .map(|(key, value)| {
+                let value = if TEXT_ATTRIBUTES.contains(&key.as_str()) {
+                    Value::String(value.to_owned())
+                } else if matches!(key.as_str(), "visible" | "infinite") {
+                    Value::Bool(value != "0" && value != "false")
+                } else if let Ok(number) = value.parse::<u64>() {
+                    Value::from(number)
+                } else if let Ok(number) = value.parse::<i64>() {
+                    Value::from(number)
+                } else if let Ok(number) = value.parse::<f64>() {
+                    Value::from(number)
+                } else {
+                    Value::String(value.to_owned())
+                };
+                (key.to_owned(), value)
+            })
+            .collect()
+    }
+
+    fn properties_to_json(&self) -> Value {
+        self.children("properties")
+            .flat_map(|node| node.children("property"))
+            .map(|node| {
+                let attribute = |key: &str| {
+                    node.attributes
+                        .iter()
+                        .find(|(name, _)| name == key)
+                        .map(|(_, value)| value.as_str())
+                };
+                let property_type = attribute("type").unwrap_or("string");
+                let raw = attribute("value").unwrap_or(node.text.as_str());
+                let value = match property_type {
+                    "int" | "object" => raw.parse::<i64>().map(Value::from).unwrap_or_default(),
+                    "float" => raw.parse::<f64>().map(Value::from).unwrap_or_default(),
+                    "bool" => Value::Bool(raw == "true"),
+                    _ => Value::String(raw.to_owned()),
+                };
+                serde_json::json!({
+                    "name": attribute("name").unwrap_or_default(),
+                    "type": property_type,
+                    "value": value,
+                })
+            })
+            .collect()
+    }
+
+    fn map_to_json(&self) -> Value {
+        let mut result = self.attributes_to_json();
+        result.insert(
+            "tilesets".to_owned(),
+            self.children("tileset")
+                .map(|node| node.tileset_to_json())
+                .collect(),
+        );
+        result.insert("layers".to_owned(), self.layers_to_json());
+        result.insert("properties".to_owned(), self.properties_to_json());
+        Value::Object(result)
+    }
+
+    fn tileset_to_json(&self) -> Value {
+        let mut result = self.attributes_to_json();
+        if let Some(image) = self.children("image").next() {
+            result.extend(image.image_to_json());
+        }
+        result.insert(
+            "tiles".to_owned(),
+            self.children("tile")
+                .map(|node| {
+                    let mut result = node.attributes_to_json();
+                    if let Some(class) = result.remove("class") {
+                        result.insert("type".to_owned(), class);
+                    }
+                    result.insert(
+                        "animation".to_owned(),
+                        node.children("animation")
+                            .flat_map(|node| node.children("frame"))
+                            .map(|node| Value::Object(node.attributes_to_json()))
+                            .collect(),
+                    );
+                    if let Some(group) = node.children("objectgroup").next() {
+                        result.insert("objectgroup".to_owned(), group.layer_to_json());
+                    }
+                    result.insert("properties".to_owned(), node.properties_to_json());
+                    Value::Object(result)
+                })
+                .collect(),
+        );
+        result.insert("properties".to_owned(), self.properties_to_json());
+        Value::Object(result)
+    }
+
+    fn image_to_json(&self) -> JsonMap<String, Value> {
+        self.attributes_to_json()
+            .into_iter()
+            .filter_map(|(key, value)| match key.as_str() {
+                "source" => Some(("image".to_owned(), value)),
+                "width" => Some(("imagewidth".to_owned(), value)),
+                "height" => Some(("imageheight".to_owned(), value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn layers_to_json(&self) -> Value {
+        self.children
+            .iter()
+            .filter(|node| {
+                matches!(
+                    node.name.as_str(),
+                    "layer" | "objectgroup" | "imagelayer" | "group"
+                )
+            })
+            .map(|node| node.layer_to_json())
+            .collect()
+    }
+
+    fn layer_to_json(&self) -> Value {
+        let mut result = self.attributes_to_json();
+        let kind = match self.name.as_str() {
+            "layer" => "tilelayer",
+            name => name,
+        };
+        result.insert("type".to_owned(), Value::from(kind));
+        match kind {
+            "tilelayer" => {
+                if let Some(data) = self.children("data").next() {
+                    let attributes = data.attributes_to_json();
+                    let encoding = attributes.get("encoding").and_then(|value| value.as_str());
+                    if let Some(compression) = attributes.get("compression") {
+                        result.insert("compression".to_owned(), compression.to_owned());
+                    }
+                    let chunks = data.children("chunk").collect::<Vec<_>>();
+                    if chunks.is_empty() {
+                        result.insert("data".to_owned(), data.data_to_json(encoding));
+                    } else {
+                        result.insert(
+                            "chunks".to_owned(),
+                            chunks
+                                .into_iter()
+                                .map(|node| {
+                                    let mut result = node.attributes_to_json();
+                                    result.insert("data".to_owned(), node.data_to_json(encoding));
+                                    Value::Object(result)
+                                })
+                                .collect(),
+                        );
+                    }
+                    if encoding == Some("base64") {
+                        result.insert("encoding".to_owned(), Value::from("base64"));
+                    }
+                }
+            }
+            "objectgroup" => {
+                result.insert(
+                    "objects".to_owned(),
+                    self.children("object")
+                        .map(|node| node.object_to_json())
+                        .collect(),
+                );
+            }
+            "imagelayer" => {
+                if let Some(image) = self.children("image").next() {
+                    result.extend(image.image_to_json());
+                }
+            }
+            "group" => {
+                result.insert("layers".to_owned(), self.layers_to_json());
+            }
+            _ => {}
+        }
+        result.insert("properties".to_owned(), self.properties_to_json());
+        Value::Object(result)
+    }
+
+    fn data_to_json(&self, encoding: Option<&str>) -> Value {
+        match encoding {
+            Some("csv") => self
+                .text
+                .split(',')
+                .filter_map(|item| item.trim().parse::<u32>().ok())
+                .collect(),
+            Some(_) => Value::String(self.text.trim().to_owned()),
+            None => self
+                .children("tile")
+                .map(|node| {
+                    node.attributes
+                        .iter()
+                        .find(|(name, _)| name == "gid")
+                        .and_then(|(_, value)| value.parse::<u32>().ok())
+                        .unwrap_or_default()
+                })
+                .collect(),
+        }
+    }
+
+    fn object_to_json(&self) -> Value {
+        let mut result = self.attributes_to_json();
+        if let Some(class) = result.remove("class") {
+            result.insert("type".to_owned(), class);
+        }
+        for child in &self.children {
+            match child.name.as_str() {
+                "point" | "ellipse" => {
+                    result.insert(child.name.to_owned(), Value::Bool(true));
+                }
+                "polygon" | "polyline" => {
+                    let points = child
+                        .attributes
+                        .iter()
+                        .find(|(name, _)| name == "points")
+                        .map(|(_, value)| value.as_str())
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .filter_map(|point| {
+                            let (x, y) = point.split_once(',')?;
+                            Some(serde_json::json!({
+                                "x": x.parse::<f64>().ok()?,
+                                "y": y.parse::<f64>().ok()?,
+                            }))
+                        })
+                        .collect();
+                    result.insert(child.name.to_owned(), points);
+                }
+                _ => {}
+            }
+        }
+        result.insert("properties".to_owned(), self.properties_to_json());
+        Value::Object(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TMX: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+  <image source="tiles.png" width="32" height="32"/>
+  <tile id="1" type="water">
+   <animation>
+    <frame tileid="1" duration="100"/>
+    <frame tileid="2" duration="200"/>
+   </animation>
+  </tile>
+  <tile id="3">
+   <objectgroup draworder="index">
+    <object id="1" x="0" y="8" width="16" height="8"/>
+   </objectgroup>
+  </tile>
+ </tileset>
+ <layer id="1" name="ground" width="2" height="2">
+  <data encoding="csv">1,2,2147483652,0</data>
+ </layer>
+ <objectgroup id="2" name="entities">
+  <object id="2" name="player" type="spawn" x="8" y="8">
+   <properties>
+    <property name="health" type="int" value="3"/>
+    <property name="tint" type="color" value="#ff00ff00"/>
+   </properties>
+   <point/>
+  </object>
+ </objectgroup>
+</map>
+"##;
+
+    #[test]
+    fn test_tiled_map() {
+        let mut map = TiledMap::from_tmx(TMX.as_bytes()).unwrap();
+        map.decode_layers().unwrap();
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(
+            map.layers[0].data.as_ref().unwrap().tiles(),
+            &[1, 2, 2147483652, 0]
+        );
+
+        let object = &map.layers[1].objects[0];
+        assert_eq!(object.class, "spawn");
+        assert!(object.point);
+        assert_eq!(object.property_int("health"), Some(3));
+        assert_eq!(
+            object.property_color("tint"),
+            Some(Rgba::new(0.0, 1.0, 0.0, 1.0))
+        );
+
+        let tileset = &map.tilesets[0];
+        assert_eq!(tileset.tile(1).unwrap().class, "water");
+        let animation = tileset.tile_animation(1).unwrap();
+        assert_eq!(animation.len(), 2);
+        assert_eq!(animation[1], (Rect::new(0.0, 0.5, 0.5, 0.5), 0.2));
+
+        let result = TiledMapBuilder::default().build(&map);
+        let level = &result.levels[0];
+        assert_eq!(level.layers.len(), 1);
+        let tiles = &level.layers[0].tiles;
+        assert_eq!(tiles.len(), 3);
+        assert!(tiles[2].flip_x);
+        assert_eq!(tiles[1].animation, Some(0));
+        assert_eq!(
+            tiles[1].animated(&level.layers[0].animations, 0.15).region,
+            Rect::new(0.0, 0.5, 0.5, 0.5)
+        );
+        assert_eq!(level.layers[0].chunks_count(), 1);
+        assert_eq!(level.colliders.len(), 1);
+        assert_eq!(
+            level.colliders[0].rectangle,
+            Rect::new(0.0, 24.0, 16.0, 8.0)
+        );
+
+        let json = serde_json::to_vec(&map).unwrap();
+        assert_eq!(TiledMap::from_tmj(&json).unwrap(), map);
+    }
+
+    #[test]
+    fn test_tiled_infinite_map() {
+        const TMX: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16" infinite="1">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+  <image source="tiles.png" width="32" height="32"/>
+  <tile id="0">
+   <objectgroup draworder="index">
+    <object id="1" x="0" y="0" width="16" height="4"/>
+   </objectgroup>
+  </tile>
+ </tileset>
+ <layer id="1" name="ground" width="2" height="2">
+  <data encoding="csv">
+   <chunk x="-16" y="-16" width="2" height="1">1,536870913</chunk>
+  </data>
+ </layer>
+</map>
+"##;
+        let mut map = TiledMap::from_tmx(TMX.as_bytes()).unwrap();
+        map.decode_layers().unwrap();
+        let chunk = &map.layers[0].chunks[0];
+        assert_eq!((chunk.x, chunk.y), (-16, -16));
+
+        let result = TiledMapBuilder::default().build(&map);
+        let level = &result.levels[0];
+        assert_eq!(
+            level.layers[0].tiles[0].rectangle,
+            Rect::new(-256.0, -256.0, 16.0, 16.0)
+        );
+        assert_eq!(level.colliders.len(), 2);
+        assert_eq!(
+            level.colliders[0].rectangle,
+            Rect::new(-256.0, -256.0, 16.0, 4.0)
+        );
+        // Diagonally flipped tile has collider along its left edge.
+        assert_eq!(
+            level.colliders[1].rectangle,
+            Rect::new(-240.0, -256.0, 4.0, 16.0)
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use crate::context::GameContext;
+use spitfire_core::Triangle;
+use spitfire_draw::{
+    context::DrawContext,
+    utils::{Drawable, Vertex},
+};
+use spitfire_glow::graphics::{GraphicsBatch, GraphicsState, GraphicsTarget};
+use std::{cmp::Ordering, collections::HashMap, ops::Range};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RenderLayerSorting {
+    /// Keep submission order.
+    Submission,
+    /// Sort by sort key, submissions with equal keys are grouped by batch.
+    #[default]
+    SortKey,
+    /// Ignore order and group everything by batch.
+    Batch,
+}
+
+#[derive(Debug, Clone)]
+struct RenderQueueItem {
+    layer: i32,
+    sort_key: f32,
+    submission: usize,
+    submission_batch: usize,
+    batch: usize,
+    triangles: Range<usize>,
+}
+
+/// Collects drawables with layer and sort key, then emits them sorted and batched.
+#[derive(Default)]
+pub struct RenderQueue {
+    pub layers: HashMap<i32, RenderLayerSorting>,
+    capture: GraphicsState<Vertex>,
+    batches: Vec<GraphicsBatch>,
+    items: Vec<RenderQueueItem>,
+    submissions: usize,
+}
+
+impl RenderQueue {
+    pub fn layer(mut self, layer: i32, sorting: RenderLayerSorting) -> Self {
+        self.layers.insert(layer, sorting);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.submissions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.submissions == 0
+    }
+
+    /// Number of distinct batches among submitted drawables.
+    pub fn batches_count(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Captures drawable output using current draw context transform and camera.
+    pub fn submit(
+        &mut self,
+        context: &mut GameContext,
+        layer: i32,
+        sort_key: f32,
+        drawable: &dyn Drawable,
+    ) {
+        self.submit_raw(context.draw, &*context.graphics, layer, sort_key, drawable);
+    }
+
+    /// Same as `submit`, with Y position of object used as sort key.
+    pub fn submit_y_sorted(
+        &mut self,
+        context: &mut GameContext,
+        layer: i32,
+        y: f32,
+        drawable: &dyn Drawable,
+    ) {
+        self.submit(context, layer, y, drawable);
+    }
+
+    pub fn submit_raw(
+        &mut self,
+        draw: &mut DrawContext,
+        graphics: &dyn GraphicsTarget<Vertex>,
+        layer: i32,
+        sort_key: f32,
+        drawable: &dyn Drawable,
+    ) {
+        self.capture.main_camera = graphics.state().main_camera;
+        self.capture.color = graphics.state().color;
+        // Last batch can get extended by drawable with equal batch.
+        let start = self.capture.stream.batches().len().saturating_sub(1);
+        let first_triangle = self.capture.stream.triangles().len();
+        drawable.draw(draw, &mut self.capture);
+        self.capture.stream.batch_end();
+        let submission = self.submissions;
+        self.submissions += 1;
+        let mut submission_batch = None;
+        for (batch, triangles) in &self.capture.stream.batches()[start..] {
+            let triangles = triangles.start.max(first_triangle)..triangles.end;
+            if triangles.is_empty() {
+                continue;
+            }
+            let batch = match self.batches.iter().position(|item| item == batch) {
+                Some(index) => index,
+                None => {
+                    self.batches.push(batch.clone());
+                    self.batches.len() - 1
+                }
+            };
+            self.items.push(RenderQueueItem {
+                layer,
+                sort_key,
+                submission,
+                submission_batch: *submission_batch.get_or_insert(batch),
+                batch,
+                triangles,
+            });
+        }
+    }
+
+    pub fn flush(&mut self, context: &mut GameContext) {
+        self.flush_raw(context.graphics);
+    }
+
+    /// Sorts submitted items and emits them into graphics stream.
+    pub fn flush_raw(&mut self, graphics: &mut dyn GraphicsTarget<Vertex>) {
+        let layers = &self.layers;
+        self.items.sort_by(|a, b| {
+            a.layer.cmp(&b.layer).then_with(|| {
+                match layers.get(&a.layer).copied().unwrap_or_default() {
+                    RenderLayerSorting::Submission => Ordering::Equal,
+                    RenderLayerSorting::SortKey => a
+                        .sort_key
+                        .total_cmp(&b.sort_key)
+                        .then(a.submission_batch.cmp(&b.submission_batch)),
+                    RenderLayerSorting::Batch => a.batch.cmp(&b.batch),
+                }
+                .then(a.submission.cmp(&b.submission))
+            })
+        });
+        let vertices = self.capture.stream.vertices();
+        let triangles = self.capture.stream.triangles();
+        let stream = &mut graphics.state_mut().stream;
+        for item in &self.items {
+            let triangles = &triangles[item.triangles.clone()];
+            let Some(min) = triangles.iter().map(|t| t.a.min(t.b).min(t.c)).min() else {
+                continue;
+            };
+            let max = triangles
+                .iter()
+                .map(|t| t.a.max(t.b).max(t.c))
+                .max()
+                .unwrap_or(min);
+            stream.batch_optimized(self.batches[item.batch].clone());
+            stream.extend(
+                vertices[min as usize..=max as usize].iter().copied(),
+                triangles.iter().map(|triangle| Triangle {
+                    a: triangle.a - min,
+                    b: triangle.b - min,
+                    c: triangle.c - min,
+                }),
+            );
+        }
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.capture.stream.clear();
+        self.batches.clear();
+        self.items.clear();
+        self.submissions = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spitfire_glow::renderer::GlowBlending;
+
+    struct Quad(GlowBlending);
+
+    impl Drawable for Quad {
+        fn draw(&self, _: &mut DrawContext, graphics: &mut dyn GraphicsTarget<Vertex>) {
+            let stream = &mut graphics.state_mut().stream;
+            stream.batch_optimized(GraphicsBatch {
+                blending: self.0,
+                ..Default::default()
+            });
+            stream.quad([Vertex::default(); 4]);
+        }
+    }
+
+    #[test]
+    fn test_render_queue() {
+        let mut draw = DrawContext::default();
+        let mut graphics = GraphicsState::<Vertex>::default();
+        let mut queue = RenderQueue::default().layer(1, RenderLayerSorting::Batch);
+        queue.submit_raw(&mut draw, &graphics, 1, 0.0, &Quad(GlowBlending::Alpha));
+        queue.submit_raw(&mut draw, &graphics, 1, 0.0, &Quad(GlowBlending::Additive));
+        queue.submit_raw(&mut draw, &graphics, 1, 0.0, &Quad(GlowBlending::Alpha));
+        queue.submit_raw(&mut draw, &graphics, 0, 2.0, &Quad(GlowBlending::Additive));
+        queue.submit_raw(&mut draw, &graphics, 0, 1.0, &Quad(GlowBlending::Alpha));
+        assert_eq!(queue.len(), 5);
+        assert_eq!(queue.batches_count(), 2);
+        queue.flush_raw(&mut graphics);
+        assert!(queue.is_empty());
+        graphics.stream.batch_end();
+        let batches = graphics
+            .stream
+            .batches()
+            .iter()
+            .map(|(batch, range)| (batch.blending, range.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            batches,
+            vec![
+                (GlowBlending::Alpha, 2),
+                (GlowBlending::Additive, 2),
+                (GlowBlending::Alpha, 4),
+                (GlowBlending::Additive, 2),
+            ]
+        );
+        assert_eq!(graphics.stream.vertices().len(), 20);
+    }
+
+    #[test]
+    fn test_render_queue_equal_batches() {
+        let mut draw = DrawContext::default();
+        let mut graphics = GraphicsState::<Vertex>::default();
+        let mut queue = RenderQueue::default();
+        queue.submit_raw(&mut draw, &graphics, 0, 2.0, &Quad(GlowBlending::Alpha));
+        queue.submit_raw(&mut draw, &graphics, 0, 0.0, &Quad(GlowBlending::Alpha));
+        queue.submit_raw(&mut draw, &graphics, 0, 1.0, &Quad(GlowBlending::Alpha));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.batches_count(), 1);
+        queue.flush_raw(&mut graphics);
+        graphics.stream.batch_end();
+        assert_eq!(graphics.stream.batches().len(), 1);
+        assert_eq!(graphics.stream.vertices().len(), 12);
+        assert_eq!(graphics.stream.triangles().len(), 6);
+    }
+}